@@ -0,0 +1,133 @@
+-- Add up migration script here
+-- Double-entry journal underneath transactions and asset balances.
+-- Amounts are signed: debits are positive, credits are negative.
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NULL REFERENCES transactions(id) ON DELETE SET NULL,
+    description TEXT NULL,
+    entry_time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS journal_postings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    entry_id UUID NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    ledger TEXT NOT NULL CHECK (ledger IN ('Asset', 'Income', 'Expense', 'Fee', 'Equity')),
+    asset_id UUID NULL REFERENCES assets(id) ON DELETE CASCADE,
+    amount DECIMAL(12,2) NOT NULL,
+    CHECK ((ledger = 'Asset') = (asset_id IS NOT NULL))
+);
+
+CREATE INDEX idx_journal_entries_transaction_id ON journal_entries (transaction_id);
+CREATE INDEX idx_journal_postings_entry_id ON journal_postings (entry_id);
+CREATE INDEX idx_journal_postings_ledger ON journal_postings (account_id, ledger, asset_id);
+
+-- Every entry must balance within each account once the surrounding transaction commits
+CREATE OR REPLACE FUNCTION check_journal_entry_balanced() RETURNS TRIGGER AS $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM journal_postings
+        WHERE entry_id = NEW.entry_id
+        GROUP BY account_id
+        HAVING SUM(amount) <> 0
+    ) THEN
+        RAISE EXCEPTION 'journal entry % is not balanced', NEW.entry_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER journal_postings_balanced
+    AFTER INSERT OR UPDATE ON journal_postings
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_journal_entry_balanced();
+
+-- Deleting an asset removes whole entries that touched it, so the remaining books still balance
+CREATE OR REPLACE FUNCTION delete_asset_journal_entries() RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM journal_entries
+    WHERE id IN (SELECT entry_id FROM journal_postings WHERE asset_id = OLD.id);
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER assets_delete_journal_entries
+    BEFORE DELETE ON assets
+    FOR EACH ROW EXECUTE FUNCTION delete_asset_journal_entries();
+
+-- Backfill: one entry per existing transaction that touches an asset
+INSERT INTO journal_entries (transaction_id, description, entry_time, created_at)
+SELECT t.id, t.notes, COALESCE(t.transaction_time, t.created_at), t.created_at
+FROM transactions t
+WHERE t.from_asset_id IS NOT NULL OR t.to_asset_id IS NOT NULL;
+
+INSERT INTO journal_postings (entry_id, account_id, ledger, asset_id, amount)
+-- Destination asset receives the amount
+SELECT e.id, ta.account_id, 'Asset', ta.id, t.amount
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets ta ON ta.id = t.to_asset_id
+UNION ALL
+-- Source asset pays the amount plus fee
+SELECT e.id, fa.account_id, 'Asset', fa.id, -(t.amount + t.fee)
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets fa ON fa.id = t.from_asset_id
+UNION ALL
+-- Fee paid by the source account
+SELECT e.id, fa.account_id, 'Fee', NULL, t.fee
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets fa ON fa.id = t.from_asset_id
+WHERE t.fee <> 0
+UNION ALL
+-- Money arriving from outside the books
+SELECT e.id, ta.account_id, 'Income', NULL, -t.amount
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets ta ON ta.id = t.to_asset_id
+WHERE t.from_asset_id IS NULL
+UNION ALL
+-- Money leaving the books
+SELECT e.id, fa.account_id, 'Expense', NULL, t.amount
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets fa ON fa.id = t.from_asset_id
+WHERE t.to_asset_id IS NULL
+UNION ALL
+-- Transfers between two accounts settle through equity on both sides
+SELECT e.id, fa.account_id, 'Equity', NULL, t.amount
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets fa ON fa.id = t.from_asset_id
+JOIN assets ta ON ta.id = t.to_asset_id
+WHERE fa.account_id <> ta.account_id
+UNION ALL
+SELECT e.id, ta.account_id, 'Equity', NULL, -t.amount
+FROM journal_entries e
+JOIN transactions t ON t.id = e.transaction_id
+JOIN assets fa ON fa.id = t.from_asset_id
+JOIN assets ta ON ta.id = t.to_asset_id
+WHERE fa.account_id <> ta.account_id;
+
+-- Backfill: opening balance entries for whatever the transactions don't explain
+WITH openings AS (
+    SELECT
+        gen_random_uuid() AS entry_id,
+        a.id AS asset_id,
+        a.account_id,
+        COALESCE(a.created_at, now()) AS entry_time,
+        a.balance - COALESCE(
+            (SELECT SUM(p.amount) FROM journal_postings p WHERE p.asset_id = a.id), 0
+        ) AS amount
+    FROM assets a
+),
+entries AS (
+    INSERT INTO journal_entries (id, description, entry_time)
+    SELECT entry_id, 'Opening balance', entry_time FROM openings WHERE amount <> 0
+)
+INSERT INTO journal_postings (entry_id, account_id, ledger, asset_id, amount)
+SELECT entry_id, account_id, 'Asset', asset_id, amount FROM openings WHERE amount <> 0
+UNION ALL
+SELECT entry_id, account_id, 'Equity', NULL, -amount FROM openings WHERE amount <> 0;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+-- Indexes backing the filtered and paginated transaction listing.
+-- The sort key falls back to created_at for rows without a transaction_time.
+CREATE INDEX IF NOT EXISTS idx_transactions_from_account_time
+    ON transactions (from_account_id, (COALESCE(transaction_time, created_at)), id);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_to_account_time
+    ON transactions (to_account_id, (COALESCE(transaction_time, created_at)), id);
+
+-- Full-text search on notes
+CREATE INDEX IF NOT EXISTS idx_transactions_notes_fts
+    ON transactions USING GIN (to_tsvector('simple', COALESCE(notes, '')));
@@ -0,0 +1,48 @@
+-- Add up migration script here
+-- Per-account, two-level category tree (e.g. Food > Restaurants)
+CREATE TABLE IF NOT EXISTS categories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    parent_id UUID NULL REFERENCES categories(id) ON DELETE CASCADE,
+    name TEXT NOT NULL CHECK (length(trim(name)) > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (parent_id IS DISTINCT FROM id)
+);
+
+-- Sibling names are unique; top-level categories share the nil parent
+CREATE UNIQUE INDEX idx_categories_unique_name
+    ON categories (account_id, COALESCE(parent_id, '00000000-0000-0000-0000-000000000000'), name);
+CREATE INDEX idx_categories_parent_id ON categories (parent_id);
+
+-- A parent must be a top-level category of the same account, and a category with
+-- subcategories cannot itself be moved under another one
+CREATE OR REPLACE FUNCTION check_category_depth() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.parent_id IS NOT NULL THEN
+        IF NOT EXISTS (
+            SELECT 1 FROM categories
+            WHERE id = NEW.parent_id AND account_id = NEW.account_id AND parent_id IS NULL
+        ) THEN
+            RAISE EXCEPTION 'category % cannot be nested under %', NEW.id, NEW.parent_id
+                USING ERRCODE = 'check_violation';
+        END IF;
+        IF EXISTS (SELECT 1 FROM categories WHERE parent_id = NEW.id) THEN
+            RAISE EXCEPTION 'category % has subcategories and cannot be nested', NEW.id
+                USING ERRCODE = 'check_violation';
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER categories_check_depth
+    BEFORE INSERT OR UPDATE OF parent_id, account_id ON categories
+    FOR EACH ROW EXECUTE FUNCTION check_category_depth();
+
+ALTER TABLE transactions
+    ADD COLUMN category_id UUID NULL REFERENCES categories(id) ON DELETE SET NULL;
+CREATE INDEX idx_transactions_category_id ON transactions (category_id);
+
+ALTER TABLE recurring_transactions
+    ADD COLUMN category_id UUID NULL REFERENCES categories(id) ON DELETE SET NULL;
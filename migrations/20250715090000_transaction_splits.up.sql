@@ -0,0 +1,62 @@
+-- Add up migration script here
+-- Split lines spreading one transaction over several categories
+CREATE TABLE IF NOT EXISTS transaction_splits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    category_id UUID NULL REFERENCES categories(id) ON DELETE SET NULL,
+    amount DECIMAL(12,2) NOT NULL CHECK (amount <> 0),
+    notes TEXT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_transaction_splits_transaction_id ON transaction_splits (transaction_id, position);
+CREATE INDEX idx_transaction_splits_category_id ON transaction_splits (category_id);
+
+-- A split transaction's lines must add up to its amount once the surrounding
+-- database transaction commits
+CREATE OR REPLACE FUNCTION check_transaction_splits_total(tx_id UUID) RETURNS VOID AS $$
+DECLARE
+    split_total DECIMAL(12,2);
+    tx_amount DECIMAL(12,2);
+BEGIN
+    SELECT SUM(amount) INTO split_total FROM transaction_splits WHERE transaction_id = tx_id;
+    IF split_total IS NULL THEN
+        RETURN;
+    END IF;
+
+    SELECT amount INTO tx_amount FROM transactions WHERE id = tx_id;
+    IF tx_amount IS NOT NULL AND split_total <> tx_amount THEN
+        RAISE EXCEPTION 'splits of transaction % add up to %, expected %', tx_id, split_total, tx_amount
+            USING ERRCODE = 'check_violation';
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_splits_on_split_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM check_transaction_splits_total(OLD.transaction_id);
+    ELSE
+        PERFORM check_transaction_splits_total(NEW.transaction_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_splits_on_amount_change() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM check_transaction_splits_total(NEW.id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER transaction_splits_total
+    AFTER INSERT OR UPDATE OR DELETE ON transaction_splits
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_splits_on_split_change();
+
+CREATE CONSTRAINT TRIGGER transactions_splits_total
+    AFTER UPDATE OF amount ON transactions
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_splits_on_amount_change();
@@ -0,0 +1,26 @@
+-- Add up migration script here
+-- Saved column mappings for importing bank and credit-card CSV statements
+CREATE TABLE IF NOT EXISTS csv_import_profiles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    encoding TEXT NOT NULL DEFAULT 'UTF-8',
+    delimiter TEXT NOT NULL DEFAULT ',' CHECK (length(delimiter) = 1),
+    has_header BOOLEAN NOT NULL DEFAULT TRUE,
+    skip_rows INTEGER NOT NULL DEFAULT 0 CHECK (skip_rows >= 0),
+    date_column TEXT NOT NULL,
+    date_format TEXT NOT NULL DEFAULT '%Y-%m-%d',
+    description_column TEXT NULL,
+    sign_convention TEXT NOT NULL
+        CHECK (sign_convention IN ('NegativeIsExpense', 'PositiveIsExpense', 'SeparateColumns')),
+    amount_column TEXT NULL,
+    debit_column TEXT NULL,
+    credit_column TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (account_id, name),
+    CHECK (
+        (sign_convention = 'SeparateColumns' AND debit_column IS NOT NULL AND credit_column IS NOT NULL)
+        OR (sign_convention <> 'SeparateColumns' AND amount_column IS NOT NULL)
+    )
+);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- Bank-assigned IDs (e.g. OFX FITID) of imported statement lines, so re-importing
+-- the same statement into the same asset is a no-op
+CREATE TABLE IF NOT EXISTS imported_statement_lines (
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    external_id TEXT NOT NULL,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    imported_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (asset_id, external_id)
+);
+
+CREATE INDEX idx_imported_statement_lines_transaction_id
+    ON imported_statement_lines (transaction_id);
@@ -0,0 +1,38 @@
+-- Add up migration script here
+-- Files (receipts, invoices) attached to transactions. The bytes live in the
+-- attachment storage backend under `storage_key`; only metadata is kept here.
+CREATE TABLE IF NOT EXISTS attachments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    storage_key TEXT NOT NULL UNIQUE,
+    thumbnail_key TEXT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_attachments_transaction_id ON attachments (transaction_id, created_at);
+
+-- Stored objects whose attachment row is gone (directly or through a deleted
+-- transaction) and that still have to be removed from storage
+CREATE TABLE IF NOT EXISTS attachment_purge_queue (
+    storage_key TEXT PRIMARY KEY,
+    queued_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE OR REPLACE FUNCTION queue_attachment_purge() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO attachment_purge_queue (storage_key) VALUES (OLD.storage_key)
+        ON CONFLICT DO NOTHING;
+    IF OLD.thumbnail_key IS NOT NULL THEN
+        INSERT INTO attachment_purge_queue (storage_key) VALUES (OLD.thumbnail_key)
+            ON CONFLICT DO NOTHING;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_attachments_queue_purge
+    AFTER DELETE ON attachments
+    FOR EACH ROW EXECUTE FUNCTION queue_attachment_purge();
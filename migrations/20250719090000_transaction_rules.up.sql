@@ -0,0 +1,38 @@
+-- Add up migration script here
+-- Free-form labels on transactions (e.g. "convenience", "business-trip")
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+CREATE INDEX idx_transactions_tags ON transactions USING GIN (tags);
+
+-- Per-account rules that categorise and tag transactions as they are created or imported.
+-- Every condition that is set must hold for a rule to match.
+CREATE TABLE IF NOT EXISTS transaction_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    name TEXT NOT NULL CHECK (length(trim(name)) > 0),
+    -- Lower runs first; the first matching rule with a category decides it
+    priority INTEGER NOT NULL DEFAULT 0,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+
+    -- Conditions
+    notes_contains TEXT NULL CHECK (length(trim(notes_contains)) > 0),
+    transaction_type INTEGER NULL CHECK (transaction_type BETWEEN 1 AND 4),
+    asset_id UUID NULL REFERENCES assets(id) ON DELETE CASCADE,
+    amount_equals DECIMAL(12,2) NULL,
+    min_amount DECIMAL(12,2) NULL,
+    max_amount DECIMAL(12,2) NULL,
+
+    -- Actions
+    category_id UUID NULL REFERENCES categories(id) ON DELETE SET NULL,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    -- A rule without conditions would match every transaction
+    CHECK (
+        notes_contains IS NOT NULL OR transaction_type IS NOT NULL OR asset_id IS NOT NULL
+        OR amount_equals IS NOT NULL OR min_amount IS NOT NULL OR max_amount IS NOT NULL
+    )
+);
+
+CREATE INDEX idx_transaction_rules_account_id ON transaction_rules (account_id, priority);
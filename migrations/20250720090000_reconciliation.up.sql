@@ -0,0 +1,75 @@
+-- Add up migration script here
+-- Reconciliation of an asset against a bank statement: the transactions the bank has
+-- cleared up to the statement date must add up to the statement's ending balance
+CREATE TABLE IF NOT EXISTS reconciliations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    asset_id UUID NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    statement_date DATE NOT NULL,
+    statement_balance DECIMAL(12,2) NOT NULL,
+    status TEXT NOT NULL DEFAULT 'Open' CHECK (status IN ('Open', 'Completed')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    completed_at TIMESTAMPTZ NULL
+);
+
+-- At most one open session per asset
+CREATE UNIQUE INDEX idx_reconciliations_open_asset
+    ON reconciliations (asset_id) WHERE status = 'Open';
+CREATE INDEX idx_reconciliations_asset_id ON reconciliations (asset_id, statement_date);
+
+-- Pending: entered but not seen on a statement yet; Cleared: seen on a statement;
+-- Reconciled: part of a completed reconciliation and locked
+ALTER TABLE transactions
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'Pending'
+        CHECK (status IN ('Pending', 'Cleared', 'Reconciled')),
+    ADD COLUMN IF NOT EXISTS reconciliation_id UUID NULL
+        REFERENCES reconciliations(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_transactions_reconciliation_id ON transactions (reconciliation_id);
+
+-- Reconciled transactions can still be categorised and tagged, but nothing that
+-- affects balances may change and they can't be deleted directly. Changes cascading
+-- from a deleted asset, account or reconciliation (trigger depth > 1) are let through.
+CREATE OR REPLACE FUNCTION protect_reconciled_transaction() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.status <> 'Reconciled' OR pg_trigger_depth() > 1 THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND
+        (NEW.from_asset_id, NEW.to_asset_id, NEW.transaction_type, NEW.amount, NEW.fee,
+         NEW.from_account_id, NEW.to_account_id, NEW.transaction_time, NEW.notes, NEW.image,
+         NEW.status, NEW.reconciliation_id)
+        IS NOT DISTINCT FROM
+        (OLD.from_asset_id, OLD.to_asset_id, OLD.transaction_type, OLD.amount, OLD.fee,
+         OLD.from_account_id, OLD.to_account_id, OLD.transaction_time, OLD.notes, OLD.image,
+         OLD.status, OLD.reconciliation_id) THEN
+        RETURN NEW;
+    END IF;
+
+    RAISE EXCEPTION 'transaction % is reconciled and locked', OLD.id
+        USING ERRCODE = 'object_in_use';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_transactions_protect_reconciled
+    BEFORE UPDATE OR DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION protect_reconciled_transaction();
+
+-- The split lines of a reconciled transaction are locked along with it
+CREATE OR REPLACE FUNCTION protect_reconciled_splits() RETURNS TRIGGER AS $$
+BEGIN
+    IF pg_trigger_depth() = 1 AND EXISTS (
+        SELECT 1 FROM transactions
+        WHERE id = COALESCE(NEW.transaction_id, OLD.transaction_id) AND status = 'Reconciled'
+    ) THEN
+        RAISE EXCEPTION 'transaction % is reconciled and locked',
+            COALESCE(NEW.transaction_id, OLD.transaction_id)
+            USING ERRCODE = 'object_in_use';
+    END IF;
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_transaction_splits_protect_reconciled
+    BEFORE INSERT OR UPDATE OR DELETE ON transaction_splits
+    FOR EACH ROW EXECUTE FUNCTION protect_reconciled_splits();
@@ -0,0 +1,49 @@
+-- Add up migration script here
+-- Every insert, update and delete of a transaction is recorded with the row as it was
+-- before and after the change. There is no foreign key to transactions so the history
+-- of a deleted transaction survives it.
+CREATE TABLE IF NOT EXISTS transaction_history (
+    id BIGSERIAL PRIMARY KEY,
+    transaction_id UUID NOT NULL,
+    version INTEGER NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('Create', 'Update', 'Delete')),
+    before JSONB NULL,
+    after JSONB NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (transaction_id, version)
+);
+
+-- Image of a transaction row in the shape of the transaction API, so it reads back
+-- as a `Transaction`: type by name, amounts as exact strings
+CREATE OR REPLACE FUNCTION transaction_image(t transactions) RETURNS JSONB AS $$
+    SELECT to_jsonb(t) || jsonb_build_object(
+        'transaction_type', CASE t.transaction_type
+            WHEN 1 THEN 'Income'
+            WHEN 2 THEN 'Expense'
+            WHEN 3 THEN 'Transfer'
+            WHEN 4 THEN 'InternalTransfer'
+        END,
+        'amount', t.amount::TEXT,
+        'fee', t.fee::TEXT
+    );
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION record_transaction_history() RETURNS TRIGGER AS $$
+DECLARE
+    target UUID := COALESCE(NEW.id, OLD.id);
+BEGIN
+    INSERT INTO transaction_history (transaction_id, version, operation, before, after)
+    VALUES (
+        target,
+        COALESCE((SELECT MAX(version) FROM transaction_history WHERE transaction_id = target), 0) + 1,
+        CASE TG_OP WHEN 'INSERT' THEN 'Create' WHEN 'UPDATE' THEN 'Update' ELSE 'Delete' END,
+        CASE WHEN TG_OP = 'INSERT' THEN NULL ELSE transaction_image(OLD) END,
+        CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE transaction_image(NEW) END
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_transactions_history
+    AFTER INSERT OR UPDATE OR DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION record_transaction_history();
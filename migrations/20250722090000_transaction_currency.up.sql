@@ -0,0 +1,116 @@
+-- Add up migration script here
+-- Every asset holds one currency. Rates in `currencies` are TWD per 1 unit of that
+-- currency, so TWD is the base currency and isn't listed there.
+ALTER TABLE assets
+    ADD COLUMN IF NOT EXISTS currency_code VARCHAR(3) NOT NULL DEFAULT 'TWD'
+        CHECK (currency_code ~ '^[A-Z]{3}$');
+
+-- `amount` and `fee` are in `currency_code`, the currency of the paying side.
+-- A transaction between assets of different currencies also records the rate at
+-- transaction time and the amount the receiving side gets in `to_currency_code`.
+-- Both are NULL when the two sides share a currency.
+ALTER TABLE transactions
+    ADD COLUMN IF NOT EXISTS currency_code VARCHAR(3) NOT NULL DEFAULT 'TWD',
+    ADD COLUMN IF NOT EXISTS to_currency_code VARCHAR(3) NOT NULL DEFAULT 'TWD',
+    ADD COLUMN IF NOT EXISTS exchange_rate NUMERIC(20,10) NULL CHECK (exchange_rate > 0),
+    ADD COLUMN IF NOT EXISTS to_amount DECIMAL(12,2) NULL;
+
+-- Current rate from one currency to another, from the rates kept by the currency
+-- scheduler; NULL when either rate is unknown
+CREATE OR REPLACE FUNCTION currency_exchange_rate(from_code TEXT, to_code TEXT) RETURNS NUMERIC AS $$
+    WITH rates AS (
+        SELECT code, CASE WHEN rate ~ '^[0-9]+(\.[0-9]+)?$' THEN rate::NUMERIC END AS twd_per_unit
+        FROM currencies
+        WHERE code IN (from_code, to_code)
+        UNION ALL
+        SELECT 'TWD', 1
+    )
+    SELECT ROUND(
+        (SELECT twd_per_unit FROM rates WHERE code = from_code LIMIT 1)
+        / NULLIF((SELECT twd_per_unit FROM rates WHERE code = to_code LIMIT 1), 0),
+        10
+    );
+$$ LANGUAGE sql STABLE;
+
+-- Take each side's currency from its asset and work out what the receiving side gets.
+-- The rate is looked up when the currencies first differ and otherwise kept, so later
+-- edits of the amount use the rate of the transaction time; an explicit rate always wins.
+CREATE OR REPLACE FUNCTION convert_transaction_currency() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.currency_code := COALESCE(
+        (SELECT currency_code FROM assets WHERE id = NEW.from_asset_id),
+        (SELECT currency_code FROM assets WHERE id = NEW.to_asset_id),
+        NEW.currency_code
+    );
+    NEW.to_currency_code := COALESCE(
+        (SELECT currency_code FROM assets WHERE id = NEW.to_asset_id),
+        NEW.currency_code
+    );
+
+    IF NEW.currency_code = NEW.to_currency_code THEN
+        NEW.exchange_rate := NULL;
+        NEW.to_amount := NULL;
+        RETURN NEW;
+    END IF;
+
+    IF NEW.exchange_rate IS NULL OR (
+        TG_OP = 'UPDATE'
+        AND NEW.exchange_rate IS NOT DISTINCT FROM OLD.exchange_rate
+        AND (NEW.currency_code, NEW.to_currency_code)
+            IS DISTINCT FROM (OLD.currency_code, OLD.to_currency_code)
+    ) THEN
+        NEW.exchange_rate := currency_exchange_rate(NEW.currency_code, NEW.to_currency_code);
+    END IF;
+
+    IF NEW.exchange_rate IS NULL THEN
+        RAISE EXCEPTION 'no exchange rate from % to %', NEW.currency_code, NEW.to_currency_code
+            USING ERRCODE = 'check_violation';
+    END IF;
+
+    NEW.to_amount := ROUND(NEW.amount * NEW.exchange_rate, 2);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trg_transactions_convert_currency
+    BEFORE INSERT OR UPDATE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION convert_transaction_currency();
+
+-- The converted amount moves balances, so it is locked on reconciled transactions too
+CREATE OR REPLACE FUNCTION protect_reconciled_transaction() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.status <> 'Reconciled' OR pg_trigger_depth() > 1 THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND
+        (NEW.from_asset_id, NEW.to_asset_id, NEW.transaction_type, NEW.amount, NEW.fee,
+         NEW.from_account_id, NEW.to_account_id, NEW.transaction_time, NEW.notes, NEW.image,
+         NEW.status, NEW.reconciliation_id, NEW.exchange_rate, NEW.to_amount)
+        IS NOT DISTINCT FROM
+        (OLD.from_asset_id, OLD.to_asset_id, OLD.transaction_type, OLD.amount, OLD.fee,
+         OLD.from_account_id, OLD.to_account_id, OLD.transaction_time, OLD.notes, OLD.image,
+         OLD.status, OLD.reconciliation_id, OLD.exchange_rate, OLD.to_amount) THEN
+        RETURN NEW;
+    END IF;
+
+    RAISE EXCEPTION 'transaction % is reconciled and locked', OLD.id
+        USING ERRCODE = 'object_in_use';
+END;
+$$ LANGUAGE plpgsql;
+
+-- History images keep the exact rate and converted amount
+CREATE OR REPLACE FUNCTION transaction_image(t transactions) RETURNS JSONB AS $$
+    SELECT to_jsonb(t) || jsonb_build_object(
+        'transaction_type', CASE t.transaction_type
+            WHEN 1 THEN 'Income'
+            WHEN 2 THEN 'Expense'
+            WHEN 3 THEN 'Transfer'
+            WHEN 4 THEN 'InternalTransfer'
+        END,
+        'amount', t.amount::TEXT,
+        'fee', t.fee::TEXT,
+        'exchange_rate', t.exchange_rate::TEXT,
+        'to_amount', t.to_amount::TEXT
+    );
+$$ LANGUAGE sql STABLE;
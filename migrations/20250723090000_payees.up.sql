@@ -0,0 +1,17 @@
+-- Add up migration script here
+-- Per-account payees (shops, employers, people). `aliases` holds the other spellings
+-- the payee shows up under, e.g. on bank statements; matching is done in the app.
+CREATE TABLE IF NOT EXISTS payees (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    name TEXT NOT NULL CHECK (length(trim(name)) > 0),
+    aliases TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX idx_payees_unique_name ON payees (account_id, lower(name));
+
+ALTER TABLE transactions
+    ADD COLUMN payee_id UUID NULL REFERENCES payees(id) ON DELETE SET NULL;
+CREATE INDEX idx_transactions_payee_id ON transactions (payee_id);
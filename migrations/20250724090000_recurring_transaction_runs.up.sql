@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- One row per executed occurrence of a recurring transaction. The unique key on the
+-- scheduled time is what keeps an occurrence from being posted twice.
+CREATE TABLE IF NOT EXISTS recurring_transaction_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    recurring_transaction_id UUID NOT NULL REFERENCES recurring_transactions(id) ON DELETE CASCADE,
+    scheduled_at TIMESTAMPTZ NOT NULL,
+    executed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    transaction_id UUID NULL REFERENCES transactions(id) ON DELETE SET NULL,
+    UNIQUE (recurring_transaction_id, scheduled_at)
+);
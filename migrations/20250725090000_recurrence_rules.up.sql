@@ -0,0 +1,15 @@
+-- Add up migration script here
+-- RRULE-style schedules for recurring transactions. Rows without a rule keep running
+-- every `interval`; the rule is parsed and validated by the app.
+ALTER TABLE recurring_transactions
+    ADD COLUMN recurrence_rule TEXT NULL,
+    ADD COLUMN starts_at TIMESTAMPTZ NULL,
+    ADD COLUMN ends_at TIMESTAMPTZ NULL,
+    ADD COLUMN max_occurrences INTEGER NULL CHECK (max_occurrences > 0);
+
+UPDATE recurring_transactions SET starts_at = created_at;
+
+ALTER TABLE recurring_transactions
+    ALTER COLUMN starts_at SET NOT NULL,
+    ALTER COLUMN starts_at SET DEFAULT now(),
+    ADD CONSTRAINT recurring_transactions_ends_after_start CHECK (ends_at >= starts_at);
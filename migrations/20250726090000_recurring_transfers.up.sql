@@ -0,0 +1,20 @@
+-- Add up migration script here
+-- Recurring transfers (standing orders) and fees. For transfers `asset_id` is the source
+-- and `to_asset_id` the destination, in `to_account_id` (default the same account).
+ALTER TABLE recurring_transactions
+    DROP CONSTRAINT recurring_transactions_transaction_type_check,
+    ADD CONSTRAINT recurring_transactions_transaction_type_check
+        CHECK (transaction_type IN (1, 2, 3, 4)),
+    ADD COLUMN to_asset_id UUID NULL REFERENCES assets(id) ON DELETE CASCADE,
+    ADD COLUMN to_account_id UUID NULL REFERENCES accounts ON DELETE CASCADE,
+    ADD COLUMN fee DECIMAL(12,2) NOT NULL DEFAULT 0 CHECK (fee >= 0),
+    -- Only transfers have a destination asset, and they need one
+    ADD CONSTRAINT recurring_transactions_transfer_destination
+        CHECK ((transaction_type IN (3, 4)) = (to_asset_id IS NOT NULL)),
+    ADD CONSTRAINT recurring_transactions_distinct_assets
+        CHECK (to_asset_id IS DISTINCT FROM asset_id),
+    ADD CONSTRAINT recurring_transactions_to_account_of_transfer
+        CHECK (to_account_id IS NULL OR transaction_type IN (3, 4)),
+    -- A fee is paid from the source, which income doesn't have
+    ADD CONSTRAINT recurring_transactions_fee_with_source
+        CHECK (transaction_type <> 1 OR fee = 0);
@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- What happened to each scheduled occurrence, and what to do with occurrences missed
+-- while the server was down: post them all, post only the latest, or skip them.
+ALTER TABLE recurring_transactions
+    ADD COLUMN missed_run_policy TEXT NOT NULL DEFAULT 'CatchUpAll'
+        CHECK (missed_run_policy IN ('CatchUpAll', 'LatestOnly', 'Skip'));
+
+ALTER TABLE recurring_transaction_runs
+    ADD COLUMN outcome TEXT NOT NULL DEFAULT 'Posted'
+        CHECK (outcome IN ('Posted', 'Skipped', 'Failed')),
+    ADD COLUMN error TEXT NULL,
+    ADD CONSTRAINT recurring_transaction_runs_error_of_failure
+        CHECK ((outcome = 'Failed') = (error IS NOT NULL)),
+    DROP CONSTRAINT recurring_transaction_runs_recurring_transaction_id_schedul_key;
+
+-- An occurrence is posted or skipped once. A failed attempt doesn't settle it: it is
+-- retried, and its row keeps the last error.
+CREATE UNIQUE INDEX recurring_transaction_runs_settled
+    ON recurring_transaction_runs (recurring_transaction_id, scheduled_at)
+    WHERE outcome <> 'Failed';
+CREATE UNIQUE INDEX recurring_transaction_runs_failed
+    ON recurring_transaction_runs (recurring_transaction_id, scheduled_at)
+    WHERE outcome = 'Failed';
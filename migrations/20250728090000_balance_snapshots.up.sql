@@ -0,0 +1,70 @@
+-- Add up migration script here
+-- Currency stocks of an exchange are quoted in; NULL for exchanges we don't price
+CREATE OR REPLACE FUNCTION stock_currency(country TEXT) RETURNS TEXT AS $$
+    SELECT CASE upper(country) WHEN 'TW' THEN 'TWD' WHEN 'US' THEN 'USD' END;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Everything an account holds, at current prices and rates. `quantity` is the balance,
+-- number of shares or amount of foreign currency, `amount` its worth in `currency_code`
+-- and `value` that worth in the base currency (TWD). Unknown prices or rates leave
+-- `amount` or `value` NULL.
+CREATE OR REPLACE VIEW holding_valuations AS
+    SELECT
+        account_id,
+        'Asset' AS holding_type,
+        id AS holding_id,
+        asset_type AS name,
+        currency_code::TEXT AS currency_code,
+        balance::NUMERIC AS quantity,
+        balance::NUMERIC AS amount,
+        balance * currency_exchange_rate(currency_code, 'TWD') AS value
+    FROM assets
+    UNION ALL
+    SELECT
+        h.account_id,
+        'StockHolding',
+        h.id,
+        m.ticker_symbol::TEXT,
+        stock_currency(m.country),
+        h.quantity,
+        h.quantity * p.price,
+        h.quantity * p.price * currency_exchange_rate(stock_currency(m.country), 'TWD')
+    FROM stock_holdings h
+    JOIN stock_metadata m ON m.id = h.stock_id
+    LEFT JOIN LATERAL (
+        SELECT replace(i.closing_price, ',', '')::NUMERIC AS price
+        FROM stock_infos i
+        WHERE i.country = m.country AND i.ticker_symbol = m.ticker_symbol
+            AND replace(i.closing_price, ',', '') ~ '^[0-9]+(\.[0-9]+)?$'
+        LIMIT 1
+    ) p ON TRUE
+    UNION ALL
+    SELECT
+        account_id,
+        'CurrencyHolding',
+        id,
+        currency_code::TEXT,
+        currency_code::TEXT,
+        amount_held,
+        amount_held,
+        amount_held * currency_exchange_rate(currency_code, 'TWD')
+    FROM currency_holding;
+
+-- End-of-day copy of `holding_valuations`, one row per holding and day
+CREATE TABLE IF NOT EXISTS balance_snapshots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    snapshot_date DATE NOT NULL,
+    holding_type TEXT NOT NULL CHECK (holding_type IN ('Asset', 'StockHolding', 'CurrencyHolding')),
+    -- The asset, stock holding or currency holding; kept after it is deleted
+    holding_id UUID NOT NULL,
+    currency_code TEXT NULL,
+    quantity NUMERIC(24,4) NOT NULL,
+    amount NUMERIC(24,4) NULL,
+    value NUMERIC(24,4) NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (holding_type, holding_id, snapshot_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_balance_snapshots_account_date
+    ON balance_snapshots (account_id, snapshot_date);
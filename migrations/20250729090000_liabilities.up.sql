@@ -0,0 +1,70 @@
+-- Add up migration script here
+-- Liabilities are assets of another kind. Their balance is signed like any other: charges
+-- and interest take it below zero and payments bring it back up, so `balance_owed` is
+-- its opposite. The terms only apply to liabilities.
+ALTER TABLE assets
+    ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'Asset'
+        CHECK (kind IN ('Asset', 'CreditCard', 'PersonalLoan', 'Mortgage')),
+    ADD COLUMN IF NOT EXISTS credit_limit NUMERIC(12,2) NULL CHECK (credit_limit >= 0),
+    -- Annual percentage rate, e.g. 15.5 for 15.5%
+    ADD COLUMN IF NOT EXISTS apr NUMERIC(7,4) NULL CHECK (apr >= 0),
+    -- Days of the month the statement closes and its payment is due
+    ADD COLUMN IF NOT EXISTS statement_day SMALLINT NULL CHECK (statement_day BETWEEN 1 AND 31),
+    ADD COLUMN IF NOT EXISTS payment_due_day SMALLINT NULL CHECK (payment_due_day BETWEEN 1 AND 31),
+    ADD COLUMN IF NOT EXISTS balance_owed NUMERIC(12,2)
+        GENERATED ALWAYS AS (CASE WHEN kind <> 'Asset' THEN -balance END) STORED;
+
+ALTER TABLE assets
+    ADD CONSTRAINT assets_liability_terms CHECK (
+        kind <> 'Asset'
+        OR (credit_limit IS NULL AND apr IS NULL AND statement_day IS NULL AND payment_due_day IS NULL)
+    );
+
+-- Snapshots and valuations of liabilities are kept apart from those of assets; their
+-- values are negative and so come off the net worth
+ALTER TABLE balance_snapshots DROP CONSTRAINT IF EXISTS balance_snapshots_holding_type_check;
+ALTER TABLE balance_snapshots
+    ADD CONSTRAINT balance_snapshots_holding_type_check
+        CHECK (holding_type IN ('Asset', 'Liability', 'StockHolding', 'CurrencyHolding'));
+
+CREATE OR REPLACE VIEW holding_valuations AS
+    SELECT
+        account_id,
+        CASE WHEN kind = 'Asset' THEN 'Asset' ELSE 'Liability' END AS holding_type,
+        id AS holding_id,
+        asset_type AS name,
+        currency_code::TEXT AS currency_code,
+        balance::NUMERIC AS quantity,
+        balance::NUMERIC AS amount,
+        balance * currency_exchange_rate(currency_code, 'TWD') AS value
+    FROM assets
+    UNION ALL
+    SELECT
+        h.account_id,
+        'StockHolding',
+        h.id,
+        m.ticker_symbol::TEXT,
+        stock_currency(m.country),
+        h.quantity,
+        h.quantity * p.price,
+        h.quantity * p.price * currency_exchange_rate(stock_currency(m.country), 'TWD')
+    FROM stock_holdings h
+    JOIN stock_metadata m ON m.id = h.stock_id
+    LEFT JOIN LATERAL (
+        SELECT replace(i.closing_price, ',', '')::NUMERIC AS price
+        FROM stock_infos i
+        WHERE i.country = m.country AND i.ticker_symbol = m.ticker_symbol
+            AND replace(i.closing_price, ',', '') ~ '^[0-9]+(\.[0-9]+)?$'
+        LIMIT 1
+    ) p ON TRUE
+    UNION ALL
+    SELECT
+        account_id,
+        'CurrencyHolding',
+        id,
+        currency_code::TEXT,
+        currency_code::TEXT,
+        amount_held,
+        amount_held,
+        amount_held * currency_exchange_rate(currency_code, 'TWD')
+    FROM currency_holding;
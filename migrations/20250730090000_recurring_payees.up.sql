@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- Payee and notes copied onto every transaction a recurring transaction posts
+ALTER TABLE recurring_transactions
+    ADD COLUMN payee_id UUID NULL REFERENCES payees(id) ON DELETE SET NULL,
+    ADD COLUMN notes TEXT NULL;
@@ -0,0 +1,60 @@
+-- Add up migration script here
+-- Currency each account's net worth is valued in: by the daily snapshots, and by default
+-- when it is reported
+ALTER TABLE accounts
+    ADD COLUMN IF NOT EXISTS base_currency VARCHAR(3) NOT NULL DEFAULT 'TWD'
+        CHECK (base_currency ~ '^[A-Z]{3}$');
+
+-- Snapshots keep the currency their value is in, so those taken before the account's base
+-- currency changed can still be converted
+ALTER TABLE balance_snapshots
+    ADD COLUMN IF NOT EXISTS value_currency TEXT NOT NULL DEFAULT 'TWD';
+
+-- `value` is now in the base currency of the holding's account, given as `value_currency`
+CREATE OR REPLACE VIEW holding_valuations AS
+    SELECT
+        s.account_id,
+        CASE WHEN s.kind = 'Asset' THEN 'Asset' ELSE 'Liability' END AS holding_type,
+        s.id AS holding_id,
+        s.asset_type AS name,
+        s.currency_code::TEXT AS currency_code,
+        s.balance::NUMERIC AS quantity,
+        s.balance::NUMERIC AS amount,
+        s.balance * currency_exchange_rate(s.currency_code, a.base_currency) AS value,
+        a.base_currency::TEXT AS value_currency
+    FROM assets s
+    JOIN accounts a ON a.account_id = s.account_id
+    UNION ALL
+    SELECT
+        h.account_id,
+        'StockHolding',
+        h.id,
+        m.ticker_symbol::TEXT,
+        stock_currency(m.country),
+        h.quantity,
+        h.quantity * p.price,
+        h.quantity * p.price * currency_exchange_rate(stock_currency(m.country), a.base_currency),
+        a.base_currency::TEXT
+    FROM stock_holdings h
+    JOIN accounts a ON a.account_id = h.account_id
+    JOIN stock_metadata m ON m.id = h.stock_id
+    LEFT JOIN LATERAL (
+        SELECT replace(i.closing_price, ',', '')::NUMERIC AS price
+        FROM stock_infos i
+        WHERE i.country = m.country AND i.ticker_symbol = m.ticker_symbol
+            AND replace(i.closing_price, ',', '') ~ '^[0-9]+(\.[0-9]+)?$'
+        LIMIT 1
+    ) p ON TRUE
+    UNION ALL
+    SELECT
+        c.account_id,
+        'CurrencyHolding',
+        c.id,
+        c.currency_code::TEXT,
+        c.currency_code::TEXT,
+        c.amount_held,
+        c.amount_held,
+        c.amount_held * currency_exchange_rate(c.currency_code, a.base_currency),
+        a.base_currency::TEXT
+    FROM currency_holding c
+    JOIN accounts a ON a.account_id = c.account_id;
@@ -14,6 +14,9 @@ pub struct Account {
     /// The current balance of the account
     pub balance: Decimal,
 
+    /// Currency its net worth is valued in (e.g., "TWD", "USD")
+    pub base_currency: String,
+
     /// Timestamp indicating when the account was created
     pub created_at: DateTime<Utc>,
 
@@ -26,6 +26,9 @@ pub struct CreateAccountRequest {
 #[derive(Deserialize)]
 pub struct UpdateAccountRequest {
     pub balance: Option<Decimal>,
+
+    /// Currency the account's net worth is valued in from now on
+    pub base_currency: Option<String>,
 }
 
 /// Handler to retrieve all accounts
@@ -70,14 +73,20 @@ pub async fn add_account_handler(
     }
 }
 
-/// Handler to update an existing account's balance
+/// Handler to update an existing account's balance or base currency
 pub async fn update_account_handler(
     State(pool): State<Arc<PgPool>>,
     Path(account_id): Path<Uuid>,
     Json(payload): Json<UpdateAccountRequest>,
 ) -> impl IntoResponse {
-    match update_account_info(&pool, account_id, payload.balance).await {
+    let base_currency = payload.base_currency.map(|code| code.trim().to_uppercase());
+
+    match update_account_info(&pool, account_id, payload.balance, base_currency).await {
         Ok(account) => account.into_response(),
+        Err(sqlx::Error::Database(err)) if err.is_check_violation() => {
+            eprintln!("Invalid base currency for account {}: {}", account_id, err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
         Err(err) => {
             eprintln!("Failed to update account {}: {:#?}", account_id, err);
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -45,15 +45,16 @@ pub async fn create_account(
         .await
 }
 
-/// Update the balance of an existing account
+/// Update the balance or base currency of an existing account
 ///
-/// Only updates if `new_balance` is provided; otherwise, returns `RowNotFound` error
+/// Only updates the fields provided; if there are none, returns `RowNotFound` error
 pub async fn update_account_info(
     pool: &PgPool,
     account_id: Uuid,
     new_balance: Option<Decimal>,
+    base_currency: Option<String>,
 ) -> Result<Account, sqlx::Error> {
-    if new_balance.is_none() {
+    if new_balance.is_none() && base_currency.is_none() {
         return Err(sqlx::Error::RowNotFound);
     }
 
@@ -64,6 +65,11 @@ pub async fn update_account_info(
         builder.push(", ");
     }
 
+    if let Some(base_currency) = base_currency {
+        builder.push("base_currency = ").push_bind(base_currency);
+        builder.push(", ");
+    }
+
     // Always update the `updated_at` field
     builder.push("updated_at = ").push_bind(Utc::now());
 
@@ -178,13 +184,13 @@ mod tests {
 
         // Update account balance
         let updated_balance = Decimal::new(7500, 2); // 75.00
-        let updated = update_account_info(&pool, user_id, Some(updated_balance))
+        let updated = update_account_info(&pool, user_id, Some(updated_balance), None)
             .await
             .expect("update_account_info failed");
         assert_eq!(updated.balance, updated_balance);
 
         // Attempt to update with None (should fail)
-        let result = update_account_info(&pool, user_id, None).await;
+        let result = update_account_info(&pool, user_id, None, None).await;
         assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
 
         // Delete account
@@ -96,6 +96,18 @@ pub async fn api_register(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    // Seed the default category tree (e.g. Food > Groceries) for the new account
+    backend
+        .create_default_categories_(&new_user)
+        .await
+        .map_err(|err| {
+            eprintln!(
+                "Failed to create default categories for user {}: {:?}",
+                new_user.id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
     Ok((
         StatusCode::CREATED,
         Json(json!({
@@ -5,6 +5,64 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::double_option;
+
+/// What an asset is: money the account has, or a liability it owes
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy, Default)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum AssetKind {
+    /// Cash, bank accounts and other positive money
+    #[default]
+    Asset,
+    CreditCard,
+    PersonalLoan,
+    Mortgage,
+}
+
+impl AssetKind {
+    pub fn is_liability(&self) -> bool {
+        *self != AssetKind::Asset
+    }
+}
+
+/// Terms of a liability; all unset for other assets
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, Default, PartialEq)]
+pub struct LiabilityTerms {
+    /// Most that can be owed, for credit cards and credit lines
+    pub credit_limit: Option<Decimal>,
+
+    /// Annual percentage rate, e.g. 15.5 for 15.5%
+    pub apr: Option<Decimal>,
+
+    /// Day of the month the statement closes
+    pub statement_day: Option<i16>,
+
+    /// Day of the month the statement balance is due
+    pub payment_due_day: Option<i16>,
+}
+
+impl LiabilityTerms {
+    pub fn is_empty(&self) -> bool {
+        *self == LiabilityTerms::default()
+    }
+}
+
+/// Terms of a liability to change: those left out are kept and those set to null cleared
+#[derive(Debug, Deserialize, Default)]
+pub struct LiabilityTermsUpdate {
+    #[serde(default, deserialize_with = "double_option")]
+    pub credit_limit: Option<Option<Decimal>>,
+
+    #[serde(default, deserialize_with = "double_option")]
+    pub apr: Option<Option<Decimal>>,
+
+    #[serde(default, deserialize_with = "double_option")]
+    pub statement_day: Option<Option<i16>>,
+
+    #[serde(default, deserialize_with = "double_option")]
+    pub payment_due_day: Option<Option<i16>>,
+}
+
 /// Represents an asset belonging to an account
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Asset {
@@ -17,9 +75,21 @@ pub struct Asset {
     /// Type of asset (e.g., "cash", "stock", etc.)
     pub asset_type: String,
 
-    /// The current balance of this asset
+    pub kind: AssetKind,
+
+    /// The current balance of this asset; below zero when a liability is owed
     pub balance: Decimal,
 
+    /// What a liability owes, the opposite of its balance; unset for other assets
+    pub balance_owed: Option<Decimal>,
+
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub terms: LiabilityTerms,
+
+    /// ISO 4217 code of the currency the balance is held in (e.g., "TWD", "USD")
+    pub currency_code: String,
+
     /// Timestamp indicating when the asset was created
     pub created_at: DateTime<Utc>,
 
@@ -27,6 +97,17 @@ pub struct Asset {
     pub updated_at: DateTime<Utc>,
 }
 
+impl Asset {
+    /// Lowest balance the asset is meant to reach: zero for assets, the credit limit
+    /// for liabilities that have one. `None` when there is no such bound.
+    pub fn lowest_allowed_balance(&self) -> Option<Decimal> {
+        if !self.kind.is_liability() {
+            return Some(Decimal::ZERO);
+        }
+        self.terms.credit_limit.map(|limit| -limit)
+    }
+}
+
 /// Allows an Asset instance to be returned directly as a JSON HTTP response
 impl IntoResponse for Asset {
     fn into_response(self) -> axum::response::Response {
@@ -10,17 +10,41 @@ use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::models::AssetList;
+use crate::models::{
+    Asset, AssetKind, AssetList, LiabilityTerms, LiabilityTermsUpdate, BASE_CURRENCY,
+};
 use crate::repository::{
-    create_asset, delete_asset, get_asset_by_user_id, get_assets, update_asset_info,
+    create_asset, create_liability, delete_asset, get_asset_by_id, get_asset_by_user_id,
+    get_assets, lock_asset_by_id, post_asset_adjustment, update_asset_info, update_liability_terms,
 };
 
-/// Request payload for creating a new asset
+/// Request payload for creating a new asset or liability
 #[derive(Deserialize)]
 pub struct CreateAssetRequest {
     pub account_id: Uuid,
     pub asset_type: String,
-    pub balance: Decimal,
+
+    /// `Asset` by default; `CreditCard`, `PersonalLoan` or `Mortgage` for a liability
+    #[serde(default)]
+    pub kind: AssetKind,
+
+    /// Opening balance, zero if unset
+    pub balance: Option<Decimal>,
+
+    /// What a liability owes at creation; an alternative to a negative `balance`
+    pub balance_owed: Option<Decimal>,
+
+    /// Currency of the asset; fixed once created
+    #[serde(default = "default_currency_code")]
+    pub currency_code: String,
+
+    /// Only for liabilities
+    #[serde(flatten)]
+    pub terms: LiabilityTerms,
+}
+
+fn default_currency_code() -> String {
+    BASE_CURRENCY.to_string()
 }
 
 /// Request payload for updating an existing asset
@@ -28,6 +52,13 @@ pub struct CreateAssetRequest {
 pub struct UpdateAssetRequest {
     pub asset_type: Option<String>,
     pub balance: Option<Decimal>,
+
+    /// Only for liabilities; an alternative to `balance`
+    pub balance_owed: Option<Decimal>,
+
+    /// Terms of a liability to change; those left out are kept and those set to null cleared
+    #[serde(flatten)]
+    pub terms: LiabilityTermsUpdate,
 }
 
 /// Handler: Fetch all asset records from the system
@@ -60,33 +91,62 @@ pub async fn add_asset_handler(
     State(pool): State<Arc<PgPool>>,
     Json(payload): Json<CreateAssetRequest>,
 ) -> impl IntoResponse {
-    match create_asset(
-        &pool,
-        payload.account_id,
-        payload.asset_type,
-        payload.balance,
-    )
-    .await
+    let account_id = payload.account_id;
+    if payload.balance_owed.is_some() && (!payload.kind.is_liability() || payload.balance.is_some())
     {
+        eprintln!(
+            "Rejected asset for account {}: balance_owed is only for liabilities, instead of balance",
+            account_id
+        );
+        return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+    }
+
+    match create_asset_with_opening_balance(&pool, payload).await {
         Ok(asset) => asset.into_response(),
+        Err(sqlx::Error::Database(err)) if err.is_check_violation() => {
+            eprintln!("Rejected asset for account {}: {}", account_id, err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
         Err(err) => {
             eprintln!(
                 "Failed to create asset for account {}: {:#?}",
-                payload.account_id, err
+                account_id, err
             );
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
 
-/// Handler: Update an asset’s type or balance
+/// Handler: Update an asset’s type or balance, or a liability's terms
 pub async fn update_asset_handler(
     State(pool): State<Arc<PgPool>>,
     Path(asset_id): Path<Uuid>,
     Json(payload): Json<UpdateAssetRequest>,
 ) -> impl IntoResponse {
-    match update_asset_info(&pool, asset_id, payload.asset_type, payload.balance).await {
+    if payload.balance_owed.is_some() {
+        match get_asset_by_id(&*pool, asset_id).await {
+            Ok(asset) if asset.kind.is_liability() && payload.balance.is_none() => {}
+            Ok(_) => {
+                eprintln!(
+                    "Rejected update of asset {}: balance_owed is only for liabilities, instead of balance",
+                    asset_id
+                );
+                return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+            }
+            Err(sqlx::Error::RowNotFound) => return StatusCode::NOT_FOUND.into_response(),
+            Err(err) => {
+                eprintln!("Failed to fetch asset {}: {:#?}", asset_id, err);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    }
+
+    match update_asset_with_adjustment(&pool, asset_id, payload).await {
         Ok(asset) => asset.into_response(),
+        Err(sqlx::Error::Database(err)) if err.is_check_violation() => {
+            eprintln!("Rejected update of asset {}: {}", asset_id, err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
         Err(err) => {
             eprintln!("Failed to update asset {}: {:#?}", asset_id, err);
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
@@ -107,3 +167,73 @@ pub async fn delete_asset_handler(
         }
     }
 }
+
+/// Create the asset or liability and journal its initial balance as an opening entry.
+/// Terms on an asset that isn't a liability are refused by a check constraint.
+async fn create_asset_with_opening_balance(
+    pool: &PgPool,
+    payload: CreateAssetRequest,
+) -> Result<Asset, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+
+    let balance = payload.balance.unwrap_or_default();
+    let asset = if payload.kind.is_liability() || !payload.terms.is_empty() {
+        create_liability(
+            &mut *db_tx,
+            payload.account_id,
+            payload.asset_type,
+            payload.kind,
+            payload.balance_owed.unwrap_or(-balance),
+            &payload.currency_code,
+            &payload.terms,
+        )
+        .await?
+    } else {
+        create_asset(
+            &mut *db_tx,
+            payload.account_id,
+            payload.asset_type,
+            balance,
+            &payload.currency_code,
+        )
+        .await?
+    };
+    post_asset_adjustment(
+        &mut db_tx,
+        asset.account_id,
+        asset.id,
+        asset.balance,
+        "Opening balance",
+    )
+    .await?;
+
+    db_tx.commit().await?;
+    Ok(asset)
+}
+
+/// Update the asset and journal any manual change of its balance
+async fn update_asset_with_adjustment(
+    pool: &PgPool,
+    asset_id: Uuid,
+    payload: UpdateAssetRequest,
+) -> Result<Asset, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+
+    let old_asset = lock_asset_by_id(&mut db_tx, asset_id).await?;
+    let balance = payload.balance_owed.map(|owed| -owed).or(payload.balance);
+    let mut asset = update_liability_terms(&mut *db_tx, asset_id, &payload.terms).await?;
+    if payload.asset_type.is_some() || balance.is_some() {
+        asset = update_asset_info(&mut *db_tx, asset_id, payload.asset_type, balance).await?;
+    }
+    post_asset_adjustment(
+        &mut db_tx,
+        asset.account_id,
+        asset.id,
+        asset.balance - old_asset.balance,
+        "Balance adjustment",
+    )
+    .await?;
+
+    db_tx.commit().await?;
+    Ok(asset)
+}
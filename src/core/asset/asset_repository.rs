@@ -1,20 +1,35 @@
 use chrono::Utc;
 use rust_decimal::Decimal;
-use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use sqlx::{Executor, PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
-use crate::models::Asset;
+use crate::models::{Asset, AssetKind, LiabilityTerms, LiabilityTermsUpdate};
 
 // SQL query constants
 const QUERY_SELECT_ALL: &str = "SELECT * FROM assets";
 const QUERY_SELECT_BY_USER_ID: &str = "SELECT * FROM assets WHERE account_id = $1";
+const QUERY_SELECT_BY_ID: &str = "SELECT * FROM assets WHERE id = $1";
+const QUERY_SELECT_BY_ID_FOR_UPDATE: &str = "SELECT * FROM assets WHERE id = $1 FOR UPDATE";
 const QUERY_INSERT: &str = "
-    INSERT INTO assets (id, account_id, asset_type, balance, created_at, updated_at)
-    VALUES ($1, $2, $3, $4, $5, $6)
+    INSERT INTO assets (id, account_id, asset_type, balance, currency_code, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    RETURNING *
+";
+const QUERY_INSERT_LIABILITY: &str = "
+    INSERT INTO assets (
+        id, account_id, asset_type, kind, balance, currency_code,
+        credit_limit, apr, statement_day, payment_due_day, created_at, updated_at
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
     RETURNING *
 ";
 const QUERY_UPDATE_BALANCE: &str =
-    "UPDATE ASSETS SET balance = balance + $1, updated_at = now() WHERE id = $2";
+    "UPDATE assets SET balance = balance + $1, updated_at = now() WHERE id = $2 RETURNING *";
+const QUERY_UPDATE_BALANCES: &str = "
+    UPDATE assets SET balance = assets.balance + changes.amount, updated_at = now()
+    FROM UNNEST($1::UUID[], $2::NUMERIC[]) AS changes (asset_id, amount)
+    WHERE assets.id = changes.asset_id
+";
 const QUERY_DELETE: &str = "DELETE FROM assets WHERE id = $1";
 
 /// Fetch all asset records from the database
@@ -32,44 +47,136 @@ pub async fn get_asset_by_user_id(pool: &PgPool, user_id: Uuid) -> Result<Vec<As
         .await
 }
 
-/// Fetch the asset type (e.g., "cash", "bank") given an asset ID
-pub async fn get_asset_type_by_asset_id(
-    pool: &PgPool,
-    asset_id: Uuid,
-) -> Result<String, sqlx::Error> {
-    let row = sqlx::query("SELECT asset_type FROM assets WHERE id = $1")
+/// Fetch a single asset by its ID
+pub async fn get_asset_by_id<'e, E>(executor: E, asset_id: Uuid) -> Result<Asset, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Asset>(QUERY_SELECT_BY_ID)
         .bind(asset_id)
-        .fetch_one(pool)
-        .await?;
+        .fetch_one(executor)
+        .await
+}
 
-    Ok(row.get("asset_type"))
+/// Fetch an asset by ID and lock it against concurrent balance changes.
+/// Must be called inside a database transaction for the lock to have any effect.
+pub async fn lock_asset_by_id(
+    conn: &mut PgConnection,
+    asset_id: Uuid,
+) -> Result<Asset, sqlx::Error> {
+    sqlx::query_as::<_, Asset>(QUERY_SELECT_BY_ID_FOR_UPDATE)
+        .bind(asset_id)
+        .fetch_one(conn)
+        .await
 }
 
-/// Create a new asset for a given account, with an initial balance
-pub async fn create_asset(
-    pool: &PgPool,
+/// Create a new asset for a given account, with an initial balance in the given currency
+pub async fn create_asset<'e, E>(
+    executor: E,
     account_id: Uuid,
     asset_type: String,
     balance: Decimal,
-) -> Result<Asset, sqlx::Error> {
+    currency_code: &str,
+) -> Result<Asset, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     sqlx::query_as::<_, Asset>(QUERY_INSERT)
         .bind(Uuid::new_v4()) // Auto-generate asset ID
         .bind(account_id)
         .bind(asset_type)
         .bind(balance)
+        .bind(currency_code)
+        .bind(Utc::now()) // created_at
+        .bind(Utc::now()) // updated_at
+        .fetch_one(executor)
+        .await
+}
+
+/// Create a credit card, loan or mortgage for a given account. `balance_owed` is what is
+/// owed at creation; the liability's balance starts at its opposite.
+pub async fn create_liability<'e, E>(
+    executor: E,
+    account_id: Uuid,
+    asset_type: String,
+    kind: AssetKind,
+    balance_owed: Decimal,
+    currency_code: &str,
+    terms: &LiabilityTerms,
+) -> Result<Asset, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Asset>(QUERY_INSERT_LIABILITY)
+        .bind(Uuid::new_v4()) // Auto-generate asset ID
+        .bind(account_id)
+        .bind(asset_type)
+        .bind(kind)
+        .bind(-balance_owed)
+        .bind(currency_code)
+        .bind(terms.credit_limit)
+        .bind(terms.apr)
+        .bind(terms.statement_day)
+        .bind(terms.payment_due_day)
         .bind(Utc::now()) // created_at
         .bind(Utc::now()) // updated_at
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await
 }
 
+/// Set or clear the terms of a liability that are provided and keep the others.
+/// Terms on an asset that isn't a liability are refused by a check constraint.
+pub async fn update_liability_terms<'e, E>(
+    executor: E,
+    asset_id: Uuid,
+    terms: &LiabilityTermsUpdate,
+) -> Result<Asset, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("UPDATE assets SET ");
+
+    if let Some(credit_limit) = terms.credit_limit {
+        builder.push("credit_limit = ").push_bind(credit_limit);
+        builder.push(", ");
+    }
+
+    if let Some(apr) = terms.apr {
+        builder.push("apr = ").push_bind(apr);
+        builder.push(", ");
+    }
+
+    if let Some(statement_day) = terms.statement_day {
+        builder.push("statement_day = ").push_bind(statement_day);
+        builder.push(", ");
+    }
+
+    if let Some(payment_due_day) = terms.payment_due_day {
+        builder
+            .push("payment_due_day = ")
+            .push_bind(payment_due_day);
+        builder.push(", ");
+    }
+
+    // Always update `updated_at`
+    builder.push("updated_at = ").push_bind(Utc::now());
+
+    builder.push(" WHERE id = ").push_bind(asset_id);
+    builder.push(" RETURNING *");
+
+    builder.build_query_as::<Asset>().fetch_one(executor).await
+}
+
 /// Update asset fields such as `asset_type` or `balance`, if provided
-pub async fn update_asset_info(
-    pool: &PgPool,
+pub async fn update_asset_info<'e, E>(
+    executor: E,
     asset_id: Uuid,
     asset_type: Option<String>,
     balance: Option<Decimal>,
-) -> Result<Asset, sqlx::Error> {
+) -> Result<Asset, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     // Ensure at least one field is being updated
     if asset_type.is_none() && balance.is_none() {
         return Err(sqlx::Error::RowNotFound);
@@ -94,24 +201,51 @@ pub async fn update_asset_info(
     builder.push(" RETURNING *");
 
     let query = builder.build_query_as::<Asset>();
-    let asset = query.fetch_one(pool).await?;
+    let asset = query.fetch_one(executor).await?;
 
     Ok(asset)
 }
 
 /// Add or subtract from an asset’s balance (atomic operation using SQL)
-pub async fn update_asset_balance(
-    pool: &PgPool,
+///
+/// Accepts any executor so it can run inside a caller's database transaction.
+/// Returns `RowNotFound` if the asset does not exist.
+pub async fn update_asset_balance<'e, E>(
+    executor: E,
     asset_id: Uuid,
     amount: Decimal,
-) -> Result<Asset, sqlx::Error> {
+) -> Result<Asset, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     sqlx::query_as::<_, Asset>(QUERY_UPDATE_BALANCE)
         .bind(amount) // Positive or negative value
         .bind(asset_id)
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await
 }
 
+/// Add amounts to several asset balances in one statement.
+/// Returns `RowNotFound` if any of the assets does not exist.
+pub async fn update_asset_balances<'e, E>(
+    executor: E,
+    changes: &[(Uuid, Decimal)],
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let (asset_ids, amounts): (Vec<Uuid>, Vec<Decimal>) = changes.iter().copied().unzip();
+    let result = sqlx::query(QUERY_UPDATE_BALANCES)
+        .bind(asset_ids)
+        .bind(amounts)
+        .execute(executor)
+        .await?;
+    if result.rows_affected() != changes.len() as u64 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
 /// Delete an asset record by its ID
 pub async fn delete_asset(pool: &PgPool, asset_id: Uuid) -> Result<(), sqlx::Error> {
     sqlx::query(QUERY_DELETE)
@@ -0,0 +1,83 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Largest file accepted as an attachment, in bytes
+pub const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
+/// Longest side of a generated thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// A file (receipt, invoice) attached to a transaction
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Attachment {
+    /// Unique attachment ID
+    pub id: Uuid,
+
+    /// Transaction the file belongs to; the attachment goes when the transaction does
+    pub transaction_id: Uuid,
+
+    /// Original file name as uploaded
+    pub file_name: String,
+
+    /// MIME type detected from the file contents (e.g., "image/jpeg")
+    pub content_type: String,
+
+    pub size_bytes: i64,
+
+    /// Where the file is kept in the storage backend; never exposed to clients
+    #[serde(skip)]
+    pub storage_key: String,
+
+    /// Where the thumbnail is kept, for images only
+    #[serde(skip)]
+    pub thumbnail_key: Option<String>,
+
+    /// When the file was uploaded
+    pub created_at: DateTime<Utc>,
+}
+
+/// An attachment as returned to clients, with the URLs to download it from.
+/// The URLs sit behind the same login as the rest of the API.
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    pub url: String,
+    pub thumbnail_url: Option<String>,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> Self {
+        let url = format!("/attachments/{}/content", attachment.id);
+        let thumbnail_url = attachment
+            .thumbnail_key
+            .as_ref()
+            .map(|_| format!("/attachments/{}/thumbnail", attachment.id));
+        AttachmentResponse {
+            attachment,
+            url,
+            thumbnail_url,
+        }
+    }
+}
+
+/// Allows an `AttachmentResponse` to be returned as a JSON response
+impl IntoResponse for AttachmentResponse {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Wrapper for returning the attachments of a transaction
+#[derive(Debug, Serialize)]
+pub struct AttachmentList(pub Vec<AttachmentResponse>);
+
+/// Enables `AttachmentList` to be returned as a JSON response
+impl IntoResponse for AttachmentList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
@@ -0,0 +1,77 @@
+use image::{ImageFormat, ImageReader};
+use std::io::Cursor;
+
+use crate::core::attachment::attachment::THUMBNAIL_SIZE;
+
+/// File types accepted as attachments: MIME type, file extension and leading magic bytes
+const ACCEPTED_TYPES: &[(&str, &str, &[u8])] = &[
+    ("image/jpeg", "jpg", b"\xFF\xD8\xFF"),
+    ("image/png", "png", b"\x89PNG\r\n\x1A\n"),
+    ("application/pdf", "pdf", b"%PDF-"),
+];
+
+/// Detect the type of an uploaded file from its contents, ignoring whatever the
+/// client claims. Returns the MIME type and file extension, or None when the file
+/// is not one of the accepted types.
+pub fn detect_content_type(data: &[u8]) -> Option<(&'static str, &'static str)> {
+    // WebP: "RIFF", 4 size bytes, "WEBP"
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some(("image/webp", "webp"));
+    }
+
+    ACCEPTED_TYPES
+        .iter()
+        .find(|(_, _, magic)| data.starts_with(magic))
+        .map(|(content_type, extension, _)| (*content_type, *extension))
+}
+
+/// Render a JPEG thumbnail of an image attachment, no larger than `THUMBNAIL_SIZE`
+/// on either side. Returns None for files that aren't images.
+pub fn make_thumbnail(content_type: &str, data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let format = match content_type {
+        "image/jpeg" => ImageFormat::Jpeg,
+        "image/png" => ImageFormat::Png,
+        "image/webp" => ImageFormat::WebP,
+        _ => return Ok(None),
+    };
+
+    let image = ImageReader::with_format(Cursor::new(data), format)
+        .decode()
+        .map_err(|err| err.to_string())?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8();
+
+    let mut encoded = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Jpeg)
+        .map_err(|err| err.to_string())?;
+    Ok(Some(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_detect_type_and_thumbnail() {
+        let mut png = Vec::new();
+        ImageBuffer::from_pixel(1024, 512, Rgb([200u8, 30, 30]))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert_eq!(detect_content_type(&png), Some(("image/png", "png")));
+        assert_eq!(
+            detect_content_type(b"%PDF-1.7\n..."),
+            Some(("application/pdf", "pdf"))
+        );
+        assert_eq!(detect_content_type(b"<html>not a receipt</html>"), None);
+
+        let thumbnail = make_thumbnail("image/png", &png).unwrap().unwrap();
+        assert_eq!(detect_content_type(&thumbnail), Some(("image/jpeg", "jpg")));
+        let decoded = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (256, 128));
+
+        assert_eq!(make_thumbnail("application/pdf", b"%PDF-").unwrap(), None);
+        // Right magic bytes, broken image
+        assert!(make_thumbnail("image/png", b"\x89PNG\r\n\x1A\ngarbage").is_err());
+    }
+}
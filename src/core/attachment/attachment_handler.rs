@@ -0,0 +1,281 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::attachment::attachment_file::{detect_content_type, make_thumbnail};
+use crate::core::attachment::attachment_storage::AttachmentStorage;
+use crate::models::{Attachment, AttachmentList, AttachmentResponse, MAX_ATTACHMENT_SIZE};
+use crate::repository::{
+    create_attachment, delete_attachment, get_attachment_by_id, get_attachments_by_transaction_id,
+    purge_deleted_attachments,
+};
+
+/// Shared state of the attachment routes: the database and the file storage backend
+#[derive(Clone)]
+pub struct AttachmentState {
+    pub pool: Arc<PgPool>,
+    pub storage: Arc<dyn AttachmentStorage>,
+}
+
+/// Handler: Fetch the attachments of a transaction
+pub async fn get_attachments_handler(
+    State(state): State<AttachmentState>,
+    Path(transaction_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_attachments_by_transaction_id(&state.pool, transaction_id).await {
+        Ok(attachments) => {
+            AttachmentList(attachments.into_iter().map(Into::into).collect()).into_response()
+        }
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch attachments of transaction {}: {:#?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Fetch an attachment's metadata and download URLs
+pub async fn get_attachment_handler(
+    State(state): State<AttachmentState>,
+    Path(attachment_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_attachment_by_id(&state.pool, attachment_id).await {
+        Ok(attachment) => AttachmentResponse::from(attachment).into_response(),
+        Err(err) => {
+            eprintln!("Failed to fetch attachment {}: {:#?}", attachment_id, err);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// Handler: Upload a file (multipart field `file`) and attach it to a transaction.
+///
+/// Only JPEG, PNG, WebP and PDF files up to `MAX_ATTACHMENT_SIZE` are accepted; the
+/// type is detected from the contents. Images also get a thumbnail.
+pub async fn upload_attachment_handler(
+    State(state): State<AttachmentState>,
+    Path(transaction_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let (file_name, data) = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("file") => {
+                let file_name = field.file_name().map(str::to_string);
+                match field.bytes().await {
+                    Ok(data) => break (file_name, data),
+                    Err(err) => return err.status().into_response(),
+                }
+            }
+            Ok(Some(_)) => continue,
+            Ok(None) => return StatusCode::BAD_REQUEST.into_response(),
+            Err(err) => return err.status().into_response(),
+        }
+    };
+
+    if data.is_empty() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    if data.len() > MAX_ATTACHMENT_SIZE {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    }
+    let Some((content_type, extension)) = detect_content_type(&data) else {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    };
+
+    // Decoding and resizing is CPU-bound; keep it off the async workers
+    let image = data.clone();
+    let thumbnail =
+        match tokio::task::spawn_blocking(move || make_thumbnail(content_type, &image)).await {
+            Ok(Ok(thumbnail)) => thumbnail,
+            Ok(Err(err)) => {
+                eprintln!("Rejected unreadable {} upload: {}", content_type, err);
+                return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+            }
+            Err(err) => {
+                eprintln!(
+                    "Thumbnail task of a {} upload failed: {}",
+                    content_type, err
+                );
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+    let file_name = file_name
+        .map(|name| name.trim().chars().take(255).collect::<String>())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("attachment.{}", extension));
+
+    match store_attachment(
+        &state,
+        transaction_id,
+        &file_name,
+        content_type,
+        extension,
+        &data,
+        thumbnail.as_deref(),
+    )
+    .await
+    {
+        Ok(attachment) => {
+            (StatusCode::CREATED, AttachmentResponse::from(attachment)).into_response()
+        }
+        Err(sqlx::Error::Database(err)) if err.is_foreign_key_violation() => {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Err(err) => {
+            eprintln!(
+                "Failed to attach file to transaction {}: {:?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Download the file of an attachment
+pub async fn download_attachment_handler(
+    State(state): State<AttachmentState>,
+    Path(attachment_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let attachment = match get_attachment_by_id(&state.pool, attachment_id).await {
+        Ok(attachment) => attachment,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    match state.storage.get(&attachment.storage_key).await {
+        Ok(data) => file_response(attachment.content_type, &attachment.file_name, data),
+        Err(err) => {
+            eprintln!("Failed to read attachment {}: {}", attachment_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Download the thumbnail of an image attachment
+pub async fn download_thumbnail_handler(
+    State(state): State<AttachmentState>,
+    Path(attachment_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let thumbnail_key = match get_attachment_by_id(&state.pool, attachment_id).await {
+        Ok(Attachment {
+            thumbnail_key: Some(key),
+            ..
+        }) => key,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    match state.storage.get(&thumbnail_key).await {
+        Ok(data) => file_response("image/jpeg".to_string(), "thumbnail.jpg", data),
+        Err(err) => {
+            eprintln!("Failed to read thumbnail of {}: {}", attachment_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Delete an attachment and its stored file
+pub async fn delete_attachment_handler(
+    State(state): State<AttachmentState>,
+    Path(attachment_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_attachment(&state.pool, attachment_id).await {
+        Ok(()) => {
+            // Remove the file right away instead of waiting for the scheduled purge
+            if let Err(err) = purge_deleted_attachments(&state.pool, state.storage.as_ref()).await {
+                eprintln!("Failed to purge deleted attachments: {:#?}", err);
+            }
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!("Failed to delete attachment {}: {:#?}", attachment_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Write the file and thumbnail to storage, then record them.
+/// The stored objects are removed again when the row can't be inserted;
+/// storage failures are reported as `sqlx::Error::Io`.
+async fn store_attachment(
+    state: &AttachmentState,
+    transaction_id: Uuid,
+    file_name: &str,
+    content_type: &str,
+    extension: &str,
+    data: &[u8],
+    thumbnail: Option<&[u8]>,
+) -> Result<Attachment, sqlx::Error> {
+    let object_id = Uuid::new_v4();
+    let storage_key = format!("{}/{}.{}", transaction_id, object_id, extension);
+    let thumbnail_key = thumbnail.map(|_| format!("{}/{}.thumb.jpg", transaction_id, object_id));
+
+    let mut stored = Vec::new();
+    let result = async {
+        state.storage.put(&storage_key, data).await?;
+        stored.push(storage_key.as_str());
+        if let (Some(key), Some(thumbnail)) = (thumbnail_key.as_deref(), thumbnail) {
+            state.storage.put(key, thumbnail).await?;
+            stored.push(key);
+        }
+
+        create_attachment(
+            &*state.pool,
+            transaction_id,
+            file_name,
+            content_type,
+            data.len() as i64,
+            &storage_key,
+            thumbnail_key.as_deref(),
+        )
+        .await
+    }
+    .await;
+
+    if result.is_err() {
+        for key in stored {
+            if let Err(err) = state.storage.delete(key).await {
+                eprintln!(
+                    "Failed to remove orphaned attachment object {}: {}",
+                    key, err
+                );
+            }
+        }
+    }
+    result
+}
+
+/// Response carrying a stored file, shown inline by browsers
+fn file_response(content_type: String, file_name: &str, data: Vec<u8>) -> axum::response::Response {
+    // Keep the header value plain ASCII and free of quotes
+    let file_name: String = file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", file_name),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        data,
+    )
+        .into_response()
+}
@@ -0,0 +1,238 @@
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::core::attachment::attachment_storage::AttachmentStorage;
+use crate::models::Attachment;
+
+// SQL query to fetch the attachments of a transaction, oldest first
+const QUERY_SELECT_BY_TRANSACTION_ID: &str =
+    "SELECT * FROM attachments WHERE transaction_id = $1 ORDER BY created_at, id";
+
+// SQL query to fetch a single attachment by ID
+const QUERY_SELECT_BY_ID: &str = "SELECT * FROM attachments WHERE id = $1";
+
+// SQL insert query for recording an uploaded attachment
+const QUERY_INSERT: &str = "
+    INSERT INTO attachments (
+        transaction_id, file_name, content_type, size_bytes, storage_key, thumbnail_key
+    ) VALUES ($1, $2, $3, $4, $5, $6)
+    RETURNING *
+";
+
+// SQL query to delete an attachment; a trigger queues its stored objects for removal
+const QUERY_DELETE: &str = "DELETE FROM attachments WHERE id = $1";
+
+// SQL query to fetch the next batch of stored objects waiting to be removed
+const QUERY_SELECT_PURGE_QUEUE: &str =
+    "SELECT storage_key FROM attachment_purge_queue ORDER BY queued_at LIMIT 500";
+
+// SQL query to drop removed objects from the purge queue
+const QUERY_DELETE_FROM_PURGE_QUEUE: &str =
+    "DELETE FROM attachment_purge_queue WHERE storage_key = ANY($1)";
+
+/// Get the attachments of a transaction
+pub async fn get_attachments_by_transaction_id(
+    pool: &PgPool,
+    transaction_id: Uuid,
+) -> Result<Vec<Attachment>, sqlx::Error> {
+    sqlx::query_as::<_, Attachment>(QUERY_SELECT_BY_TRANSACTION_ID)
+        .bind(transaction_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Get an attachment by its ID
+pub async fn get_attachment_by_id(
+    pool: &PgPool,
+    attachment_id: Uuid,
+) -> Result<Attachment, sqlx::Error> {
+    sqlx::query_as::<_, Attachment>(QUERY_SELECT_BY_ID)
+        .bind(attachment_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Record an attachment whose file (and thumbnail) are already in storage
+pub async fn create_attachment<'e, E>(
+    executor: E,
+    transaction_id: Uuid,
+    file_name: &str,
+    content_type: &str,
+    size_bytes: i64,
+    storage_key: &str,
+    thumbnail_key: Option<&str>,
+) -> Result<Attachment, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Attachment>(QUERY_INSERT)
+        .bind(transaction_id)
+        .bind(file_name)
+        .bind(content_type)
+        .bind(size_bytes)
+        .bind(storage_key)
+        .bind(thumbnail_key)
+        .fetch_one(executor)
+        .await
+}
+
+/// Delete an attachment; its stored objects are queued for `purge_deleted_attachments`
+pub async fn delete_attachment(pool: &PgPool, attachment_id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(QUERY_DELETE)
+        .bind(attachment_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+/// Remove the stored objects of deleted attachments, including those that went with
+/// a deleted transaction. Objects that fail to delete stay queued for the next run.
+///
+/// Returns how many objects were removed.
+pub async fn purge_deleted_attachments(
+    pool: &PgPool,
+    storage: &dyn AttachmentStorage,
+) -> Result<usize, sqlx::Error> {
+    let keys: Vec<String> = sqlx::query_scalar(QUERY_SELECT_PURGE_QUEUE)
+        .fetch_all(pool)
+        .await?;
+
+    let mut removed = Vec::with_capacity(keys.len());
+    for key in keys {
+        match storage.delete(&key).await {
+            Ok(()) => removed.push(key),
+            Err(err) => eprintln!("Failed to remove attachment object {}: {}", key, err),
+        }
+    }
+
+    sqlx::query(QUERY_DELETE_FROM_PURGE_QUEUE)
+        .bind(&removed)
+        .execute(pool)
+        .await?;
+    Ok(removed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::attachment::attachment_storage::LocalStorage;
+    use rust_decimal::Decimal;
+    use sqlx::{migrate::MigrateDatabase, Postgres};
+    use std::env;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to test DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn insert_transaction(pool: &PgPool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, $2, now(), now())")
+            .bind(user_id)
+            .bind(Decimal::ZERO)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        sqlx::query_scalar(
+            "INSERT INTO transactions (transaction_type, amount, fee, from_account_id, created_at, updated_at)
+                     VALUES (2, $1, 0, $2, now(), now()) RETURNING id",
+        )
+        .bind(Decimal::new(1250, 2))
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_attachments_are_purged_with_their_transaction() {
+        let pool = setup_test_db().await;
+        let transaction_id = insert_transaction(&pool).await;
+
+        let root = env::temp_dir().join(format!("vito-attachments-{}", Uuid::new_v4()));
+        let storage = LocalStorage::new(&root);
+        let key = format!("{}.jpg", Uuid::new_v4());
+        let thumbnail_key = format!("{}.thumb.jpg", Uuid::new_v4());
+        storage.put(&key, b"receipt").await.unwrap();
+        storage.put(&thumbnail_key, b"thumbnail").await.unwrap();
+
+        let attachment = create_attachment(
+            &pool,
+            transaction_id,
+            "receipt.jpg",
+            "image/jpeg",
+            7,
+            &key,
+            Some(&thumbnail_key),
+        )
+        .await
+        .unwrap();
+        let listed = get_attachments_by_transaction_id(&pool, transaction_id)
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, attachment.id);
+
+        // Deleting the transaction takes the attachment row with it ...
+        sqlx::query("DELETE FROM transactions WHERE id = $1")
+            .bind(transaction_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(matches!(
+            get_attachment_by_id(&pool, attachment.id).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+
+        // ... and the purge removes both stored objects
+        purge_deleted_attachments(&pool, &storage).await.unwrap();
+        assert!(storage.get(&key).await.is_err());
+        assert!(storage.get(&thumbnail_key).await.is_err());
+
+        let queued: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM attachment_purge_queue WHERE storage_key = ANY($1)",
+        )
+        .bind(vec![key, thumbnail_key])
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(queued, 0);
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{extract::DefaultBodyLimit, routing::get, Router};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::core::attachment::attachment_storage::AttachmentStorage;
+use crate::{
+    core::attachment::attachment_handler::*,
+    models::{Backend, MAX_ATTACHMENT_SIZE},
+};
+
+/// Defines routes for uploading and downloading transaction attachments
+pub fn attachment_routes(state: Arc<PgPool>, storage: Arc<dyn AttachmentStorage>) -> Router {
+    Router::new()
+        // GET  /transactions/{id}/attachments -> Attachments of a transaction
+        // POST /transactions/{id}/attachments (multipart field `file`) -> Upload one
+        .route(
+            "/transactions/{id}/attachments",
+            get(get_attachments_handler).post(upload_attachment_handler),
+        )
+        // GET    /attachments/{id} -> Metadata and download URLs
+        // DELETE /attachments/{id} -> Delete the attachment and its file
+        .route(
+            "/attachments/{id}",
+            get(get_attachment_handler).delete(delete_attachment_handler),
+        )
+        // GET /attachments/{id}/content -> Download the file
+        .route(
+            "/attachments/{id}/content",
+            get(download_attachment_handler),
+        )
+        // GET /attachments/{id}/thumbnail -> Download the thumbnail of an image
+        .route(
+            "/attachments/{id}/thumbnail",
+            get(download_thumbnail_handler),
+        )
+        // Leave room for the multipart framing around the largest accepted file
+        .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE + 64 * 1024))
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(AttachmentState {
+            pool: state,
+            storage,
+        })
+}
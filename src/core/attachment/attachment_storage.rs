@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Where attachment files are kept. Keys are generated by the server and only ever
+/// contain ASCII letters, digits, `-`, `.` and `/`.
+#[async_trait]
+pub trait AttachmentStorage: Send + Sync {
+    /// Store `data` under `key`, replacing anything already there
+    async fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Read the object stored under `key`
+    async fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+
+    /// Remove the object stored under `key`; removing a missing object is not an error
+    async fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+/// Storage backend writing to a directory on the local filesystem
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    /// Path of a key below the root, refusing keys that could escape it
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let valid = !key.is_empty()
+            && key
+                .split('/')
+                .all(|part| !part.is_empty() && part != "." && part != "..")
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '/'));
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid storage key {:?}", key),
+            ));
+        }
+        Ok(self.root.join(Path::new(key)))
+    }
+}
+
+#[async_trait]
+impl AttachmentStorage for LocalStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Write to a temporary file first so readers never see a partial object
+        let temporary = path.with_extension("partial");
+        tokio::fs::write(&temporary, data).await?;
+        tokio::fs::rename(&temporary, &path).await
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        tokio::fs::read(self.path(key)?).await
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Build the storage backend selected by the environment.
+///
+/// - `ATTACHMENT_STORAGE`: backend name, only `local` for now (default)
+/// - `ATTACHMENT_DIR`: root directory of the `local` backend (default `attachments`)
+pub fn attachment_storage_from_env() -> Arc<dyn AttachmentStorage> {
+    let backend = std::env::var("ATTACHMENT_STORAGE").unwrap_or_else(|_| "local".to_string());
+    match backend.as_str() {
+        "local" => {
+            let root =
+                std::env::var("ATTACHMENT_DIR").unwrap_or_else(|_| "attachments".to_string());
+            Arc::new(LocalStorage::new(root))
+        }
+        other => panic!("Unsupported ATTACHMENT_STORAGE backend: {}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_local_storage_roundtrip() {
+        let root = std::env::temp_dir().join(format!("vito-attachments-{}", Uuid::new_v4()));
+        let storage = LocalStorage::new(&root);
+
+        storage.put("ab/receipt.jpg", b"jpeg bytes").await.unwrap();
+        assert_eq!(storage.get("ab/receipt.jpg").await.unwrap(), b"jpeg bytes");
+
+        storage.delete("ab/receipt.jpg").await.unwrap();
+        assert!(storage.get("ab/receipt.jpg").await.is_err());
+        // Deleting twice is fine
+        storage.delete("ab/receipt.jpg").await.unwrap();
+
+        // Keys can't leave the root
+        assert!(storage.put("../outside", b"x").await.is_err());
+        assert!(storage.put("/etc/passwd", b"x").await.is_err());
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
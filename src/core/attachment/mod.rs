@@ -0,0 +1,6 @@
+pub mod attachment;
+pub mod attachment_file;
+pub mod attachment_handler;
+pub mod attachment_repository;
+pub mod attachment_routes;
+pub mod attachment_storage;
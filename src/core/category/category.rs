@@ -0,0 +1,113 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Categories created for every new user: top-level name and its subcategories
+pub const DEFAULT_CATEGORIES: &[(&str, &[&str])] = &[
+    ("Food", &["Groceries", "Restaurants", "Coffee & Snacks"]),
+    ("Housing", &["Rent", "Utilities", "Maintenance"]),
+    ("Transportation", &["Public Transit", "Fuel", "Parking"]),
+    ("Shopping", &["Clothing", "Electronics", "Household"]),
+    (
+        "Entertainment",
+        &["Movies & Events", "Subscriptions", "Travel"],
+    ),
+    ("Health", &["Medical", "Insurance", "Fitness"]),
+    ("Income", &["Salary", "Bonus", "Investment Income"]),
+    ("Other", &[]),
+];
+
+/// A transaction category; top-level when `parent_id` is None, otherwise a subcategory
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Category {
+    /// Unique category ID
+    pub id: Uuid,
+
+    /// Account the category belongs to
+    pub account_id: Uuid,
+
+    /// Parent category (only one level of nesting is allowed)
+    pub parent_id: Option<Uuid>,
+
+    /// Display name, unique among its siblings
+    pub name: String,
+
+    /// When the category was created
+    pub created_at: DateTime<Utc>,
+
+    /// When the category was last updated
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Allows a `Category` to be returned as a JSON response
+impl IntoResponse for Category {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// A top-level category together with its subcategories
+#[derive(Debug, Serialize)]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub category: Category,
+    pub children: Vec<Category>,
+}
+
+/// Category tree of an account
+#[derive(Debug, Serialize)]
+pub struct CategoryTree(pub Vec<CategoryNode>);
+
+impl CategoryTree {
+    /// Build the tree from a flat list ordered so parents come before their children
+    pub fn from_categories(categories: Vec<Category>) -> Self {
+        let mut nodes: Vec<CategoryNode> = Vec::new();
+        for category in categories {
+            match category.parent_id {
+                None => nodes.push(CategoryNode {
+                    category,
+                    children: Vec::new(),
+                }),
+                Some(parent_id) => {
+                    if let Some(node) = nodes.iter_mut().find(|n| n.category.id == parent_id) {
+                        node.children.push(category);
+                    }
+                }
+            }
+        }
+        Self(nodes)
+    }
+}
+
+/// Enables `CategoryTree` to be returned as a JSON response
+impl IntoResponse for CategoryTree {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Income and expense booked against one category over a period.
+/// Split transactions count each line under its own category.
+#[derive(Debug, Serialize, FromRow)]
+pub struct CategoryTotal {
+    /// None collects the uncategorized amounts
+    pub category_id: Option<Uuid>,
+    pub parent_id: Option<Uuid>,
+    pub name: Option<String>,
+    pub income: Decimal,
+    pub expense: Decimal,
+}
+
+/// Wrapper for returning per-category totals
+#[derive(Debug, Serialize)]
+pub struct CategoryTotalList(pub Vec<CategoryTotal>);
+
+/// Enables `CategoryTotalList` to be returned as a JSON response
+impl IntoResponse for CategoryTotalList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
@@ -0,0 +1,160 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{double_option, CategoryTotalList, CategoryTree};
+use crate::repository::{
+    create_category, delete_category, get_categories_by_account_id, get_category_by_id,
+    get_category_totals, update_category_info,
+};
+
+/// Request payload for creating a category
+#[derive(Deserialize)]
+pub struct CreateCategoryRequest {
+    pub account_id: Uuid,
+
+    /// Top-level category to nest under; omit for a top-level category
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+}
+
+/// Request payload for renaming or moving a category
+#[derive(Deserialize)]
+pub struct UpdateCategoryRequest {
+    pub name: Option<String>,
+
+    /// Top-level category to move under; `null` moves the category to the top level
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+/// Query parameters for per-category totals
+#[derive(Deserialize)]
+pub struct CategoryTotalsQuery {
+    /// Start of the period (defaults to 30 days before `to`)
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the period, exclusive (defaults to now)
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Map a failed category write to a response status:
+/// duplicate sibling names conflict, invalid nesting or parents are bad requests
+fn category_error_status(err: &sqlx::Error) -> StatusCode {
+    match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => StatusCode::CONFLICT,
+        sqlx::Error::Database(db_err)
+            if db_err.is_check_violation() || db_err.is_foreign_key_violation() =>
+        {
+            StatusCode::BAD_REQUEST
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Handler: Fetch the category tree of an account
+pub async fn get_categories_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_categories_by_account_id(&pool, account_id).await {
+        Ok(categories) => CategoryTree::from_categories(categories).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch categories for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Fetch a single category by ID
+pub async fn get_category_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(category_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_category_by_id(&pool, category_id).await {
+        Ok(category) => category.into_response(),
+        Err(err) => {
+            eprintln!("Failed to fetch category {}: {:#?}", category_id, err);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// Handler: Create a category or subcategory
+pub async fn add_category_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<CreateCategoryRequest>,
+) -> impl IntoResponse {
+    match create_category(&*pool, payload.account_id, payload.parent_id, &payload.name).await {
+        Ok(category) => (StatusCode::CREATED, category).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to create category for account {}: {:#?}",
+                payload.account_id, err
+            );
+            category_error_status(&err).into_response()
+        }
+    }
+}
+
+/// Handler: Rename a category or move it under another top-level category
+pub async fn update_category_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(category_id): Path<Uuid>,
+    Json(payload): Json<UpdateCategoryRequest>,
+) -> impl IntoResponse {
+    match update_category_info(&pool, category_id, payload.name, payload.parent_id).await {
+        Ok(category) => category.into_response(),
+        Err(err) => {
+            eprintln!("Failed to update category {}: {:#?}", category_id, err);
+            category_error_status(&err).into_response()
+        }
+    }
+}
+
+/// Handler: Delete a category and its subcategories
+pub async fn delete_category_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(category_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_category(&pool, category_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            eprintln!("Failed to delete category {}: {:#?}", category_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Income and expense totals per category of an account
+pub async fn get_category_totals_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<CategoryTotalsQuery>,
+) -> impl IntoResponse {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+
+    match get_category_totals(&pool, account_id, from, to).await {
+        Ok(totals) => CategoryTotalList(totals).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch category totals for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
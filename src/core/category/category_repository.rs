@@ -0,0 +1,308 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgConnection, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::{Category, CategoryTotal, DEFAULT_CATEGORIES};
+
+// SQL query to fetch an account's categories, top-level ones first
+const QUERY_SELECT_BY_ACCOUNT_ID: &str = "
+    SELECT * FROM categories
+    WHERE account_id = $1
+    ORDER BY parent_id IS NOT NULL, name
+";
+const QUERY_SELECT_BY_ID: &str = "SELECT * FROM categories WHERE id = $1";
+const QUERY_INSERT: &str = "
+    INSERT INTO categories (account_id, parent_id, name)
+    VALUES ($1, $2, $3)
+    RETURNING *
+";
+const QUERY_DELETE: &str = "DELETE FROM categories WHERE id = $1";
+
+// SQL query summing income and expenses per category over a period.
+// A split transaction contributes its lines instead of its own category and amount.
+const QUERY_SELECT_TOTALS: &str = "
+    WITH lines AS (
+        SELECT
+            CASE WHEN s.id IS NULL THEN t.category_id ELSE s.category_id END AS category_id,
+            COALESCE(s.amount, t.amount) AS amount,
+            t.transaction_type
+        FROM transactions t
+        LEFT JOIN transaction_splits s ON s.transaction_id = t.id
+        WHERE (t.from_account_id = $1 OR t.to_account_id = $1)
+          AND t.transaction_type IN (1, 2)
+          AND COALESCE(t.transaction_time, t.created_at) >= $2
+          AND COALESCE(t.transaction_time, t.created_at) < $3
+    )
+    SELECT
+        l.category_id,
+        c.parent_id,
+        c.name,
+        COALESCE(SUM(l.amount) FILTER (WHERE l.transaction_type = 1), 0) AS income,
+        COALESCE(SUM(l.amount) FILTER (WHERE l.transaction_type = 2), 0) AS expense
+    FROM lines l
+    LEFT JOIN categories c ON c.id = l.category_id
+    GROUP BY l.category_id, c.parent_id, c.name
+    ORDER BY expense DESC, income DESC
+";
+
+/// Fetch all categories of an account, top-level categories before subcategories
+pub async fn get_categories_by_account_id(
+    pool: &PgPool,
+    account_id: Uuid,
+) -> Result<Vec<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>(QUERY_SELECT_BY_ACCOUNT_ID)
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Fetch a single category by its ID
+pub async fn get_category_by_id(pool: &PgPool, category_id: Uuid) -> Result<Category, sqlx::Error> {
+    sqlx::query_as::<_, Category>(QUERY_SELECT_BY_ID)
+        .bind(category_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Create a category; pass a `parent_id` to create a subcategory
+pub async fn create_category<'e, E>(
+    executor: E,
+    account_id: Uuid,
+    parent_id: Option<Uuid>,
+    name: &str,
+) -> Result<Category, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Category>(QUERY_INSERT)
+        .bind(account_id)
+        .bind(parent_id)
+        .bind(name.trim())
+        .fetch_one(executor)
+        .await
+}
+
+/// Rename a category and/or move it under another top-level category;
+/// `Some(None)` as `parent_id` moves it to the top level
+pub async fn update_category_info(
+    pool: &PgPool,
+    category_id: Uuid,
+    name: Option<String>,
+    parent_id: Option<Option<Uuid>>,
+) -> Result<Category, sqlx::Error> {
+    // If no fields are provided to update, return an error
+    if name.is_none() && parent_id.is_none() {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("UPDATE categories SET ");
+
+    if let Some(name) = name {
+        builder
+            .push("name = ")
+            .push_bind(name.trim().to_string())
+            .push(", ");
+    }
+    if let Some(parent_id) = parent_id {
+        builder.push("parent_id = ").push_bind(parent_id).push(", ");
+    }
+
+    // Always update the timestamp
+    builder.push("updated_at = ").push_bind(Utc::now());
+    builder.push(" WHERE id = ").push_bind(category_id);
+    builder.push(" RETURNING *");
+
+    builder.build_query_as::<Category>().fetch_one(pool).await
+}
+
+/// Delete a category together with its subcategories.
+/// Transactions in the deleted categories become uncategorized.
+pub async fn delete_category(pool: &PgPool, category_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(QUERY_DELETE)
+        .bind(category_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Sum the income and expenses of an account per category between `from` (inclusive)
+/// and `to` (exclusive)
+pub async fn get_category_totals(
+    pool: &PgPool,
+    account_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<CategoryTotal>, sqlx::Error> {
+    sqlx::query_as::<_, CategoryTotal>(QUERY_SELECT_TOTALS)
+        .bind(account_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+}
+
+/// Seed `DEFAULT_CATEGORIES` for a newly created account
+pub async fn create_default_categories(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+) -> Result<Vec<Category>, sqlx::Error> {
+    let mut categories = Vec::new();
+
+    for (name, children) in DEFAULT_CATEGORIES {
+        let parent = create_category(&mut *conn, account_id, None, name).await?;
+        let parent_id = parent.id;
+        categories.push(parent);
+
+        if children.is_empty() {
+            continue;
+        }
+
+        let mut builder: QueryBuilder<Postgres> =
+            QueryBuilder::new("INSERT INTO categories (account_id, parent_id, name) ");
+        builder.push_values(children.iter(), |mut row, child| {
+            row.push_bind(account_id)
+                .push_bind(parent_id)
+                .push_bind(*child);
+        });
+        builder.push(" RETURNING *");
+
+        let mut inserted = builder
+            .build_query_as::<Category>()
+            .fetch_all(&mut *conn)
+            .await?;
+        categories.append(&mut inserted);
+    }
+
+    Ok(categories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
+    use std::env;
+    use uuid::Uuid;
+
+    use crate::models::CategoryTree;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url =
+            env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env.test");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to test DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn insert_user_and_account(pool: &PgPool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, $2, now(), now())")
+            .bind(user_id)
+            .bind(Decimal::new(0, 2))
+            .execute(pool)
+            .await
+            .unwrap();
+
+        user_id
+    }
+
+    #[tokio::test]
+    async fn test_category_tree_crud() {
+        let pool = setup_test_db().await;
+        let account_id = insert_user_and_account(&pool).await;
+        let other_account_id = insert_user_and_account(&pool).await;
+
+        // Defaults form a two-level tree
+        let mut conn = pool.acquire().await.unwrap();
+        let seeded = create_default_categories(&mut conn, account_id)
+            .await
+            .unwrap();
+        let tree = CategoryTree::from_categories(
+            get_categories_by_account_id(&pool, account_id)
+                .await
+                .unwrap(),
+        );
+        assert_eq!(tree.0.len(), DEFAULT_CATEGORIES.len());
+        assert_eq!(
+            tree.0
+                .iter()
+                .map(|node| node.children.len() + 1)
+                .sum::<usize>(),
+            seeded.len()
+        );
+
+        // Create a top-level category and a subcategory
+        let travel = create_category(&pool, account_id, None, "Vacation")
+            .await
+            .unwrap();
+        let flights = create_category(&pool, account_id, Some(travel.id), " Flights ")
+            .await
+            .unwrap();
+        assert_eq!(flights.parent_id, Some(travel.id));
+        assert_eq!(flights.name, "Flights");
+
+        // Sibling names are unique
+        let duplicate = create_category(&pool, account_id, None, "Vacation").await;
+        assert!(matches!(duplicate, Err(sqlx::Error::Database(ref e)) if e.is_unique_violation()));
+
+        // No third level, no parents from another account, no nesting a parent
+        let third = create_category(&pool, account_id, Some(flights.id), "Budget").await;
+        assert!(matches!(third, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+        let foreign = create_category(&pool, other_account_id, Some(travel.id), "Hotels").await;
+        assert!(matches!(foreign, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+        let food = tree.0[0].category.id;
+        let nested = update_category_info(&pool, travel.id, None, Some(Some(food))).await;
+        assert!(matches!(nested, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+
+        // Rename
+        let renamed = update_category_info(&pool, flights.id, Some("Airfare".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(renamed.name, "Airfare");
+
+        // Move to the top level and back
+        let moved = update_category_info(&pool, flights.id, None, Some(None))
+            .await
+            .unwrap();
+        assert_eq!(moved.parent_id, None);
+        let moved = update_category_info(&pool, flights.id, None, Some(Some(travel.id)))
+            .await
+            .unwrap();
+        assert_eq!(moved.parent_id, Some(travel.id));
+
+        // Deleting a parent removes its subcategories
+        delete_category(&pool, travel.id).await.unwrap();
+        let result = get_category_by_id(&pool, flights.id).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+    }
+}
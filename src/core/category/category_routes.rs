@@ -0,0 +1,34 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::category::category_handler::*, models::Backend};
+
+/// Defines routes for managing the per-account category tree
+pub fn category_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // POST /categories -> Create a category or subcategory
+        .route("/categories", post(add_category_handler))
+        // GET    /categories/{id} -> Fetch one by ID
+        // PATCH  /categories/{id} -> Rename or move a category
+        // DELETE /categories/{id} -> Delete a category and its subcategories
+        .route(
+            "/categories/{id}",
+            get(get_category_handler)
+                .patch(update_category_handler)
+                .delete(delete_category_handler),
+        )
+        // GET /categories/account/{id} -> Category tree of an account
+        .route("/categories/account/{id}", get(get_categories_handler))
+        // GET /categories/account/{id}/totals?from=&to= -> Income and expenses per category
+        .route(
+            "/categories/account/{id}/totals",
+            get(get_category_totals_handler),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
@@ -0,0 +1,4 @@
+pub mod category;
+pub mod category_handler;
+pub mod category_repository;
+pub mod category_routes;
@@ -0,0 +1,193 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Where a projected balance change comes from
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(tag = "source", content = "id", rename_all = "snake_case")]
+pub enum ForecastSource {
+    /// An already recorded transaction dated in the future
+    Transaction(Uuid),
+    /// A future occurrence of a recurring transaction
+    RecurringTransaction(Uuid),
+}
+
+/// A single projected change of one asset balance
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ForecastEvent {
+    pub date: NaiveDate,
+    pub asset_id: Uuid,
+
+    /// Signed change of the asset balance, in the asset's currency
+    pub amount: Decimal,
+
+    #[serde(flatten)]
+    pub source: ForecastSource,
+}
+
+/// Projected balances at the end of one day
+#[derive(Debug, Serialize)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+
+    /// Balance of every asset of the account, keyed by asset ID
+    pub balances: BTreeMap<Uuid, Decimal>,
+
+    /// Assets whose balance is below zero at the end of the day, or for liabilities
+    /// past their credit limit
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub negative_asset_ids: Vec<Uuid>,
+
+    /// Changes booked on this day
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<ForecastEvent>,
+}
+
+/// Day-by-day balance projection of the assets of an account
+#[derive(Debug, Serialize)]
+pub struct Forecast {
+    pub account_id: Uuid,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+
+    /// Balances the projection starts from, before any event of `from`
+    pub starting_balances: BTreeMap<Uuid, Decimal>,
+
+    pub days: Vec<ForecastDay>,
+
+    /// Days on which at least one asset is projected to be negative or over its limit
+    pub negative_dates: Vec<NaiveDate>,
+}
+
+/// Allows a Forecast to be returned directly as a JSON HTTP response
+impl IntoResponse for Forecast {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+impl Forecast {
+    /// Projects `starting_balances` from `from` to `to` (inclusive) by applying `events`
+    /// on their dates. Events dated before `from` are booked on `from`, those after `to`
+    /// are ignored, and events of assets missing from `starting_balances` are dropped.
+    /// Assets are flagged on the days their balance is below their entry in `floors`;
+    /// those without one are never flagged.
+    pub fn project(
+        account_id: Uuid,
+        starting_balances: BTreeMap<Uuid, Decimal>,
+        floors: &BTreeMap<Uuid, Decimal>,
+        mut events: Vec<ForecastEvent>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Self {
+        events.retain(|event| event.date <= to && starting_balances.contains_key(&event.asset_id));
+        events.sort_by_key(|event| event.date);
+
+        let mut balances = starting_balances.clone();
+        let mut pending = events.into_iter().peekable();
+        let mut days = Vec::new();
+        let mut negative_dates = Vec::new();
+
+        let mut date = from;
+        while date <= to {
+            let mut day_events = Vec::new();
+            while let Some(event) = pending.next_if(|event| event.date <= date) {
+                *balances.entry(event.asset_id).or_default() += event.amount;
+                day_events.push(ForecastEvent { date, ..event });
+            }
+
+            let negative_asset_ids: Vec<Uuid> = balances
+                .iter()
+                .filter(|(asset_id, balance)| {
+                    floors.get(asset_id).is_some_and(|floor| *balance < floor)
+                })
+                .map(|(asset_id, _)| *asset_id)
+                .collect();
+            if !negative_asset_ids.is_empty() {
+                negative_dates.push(date);
+            }
+
+            days.push(ForecastDay {
+                date,
+                balances: balances.clone(),
+                negative_asset_ids,
+                events: day_events,
+            });
+
+            match date.checked_add_days(Days::new(1)) {
+                Some(next) => date = next,
+                None => break,
+            }
+        }
+
+        Self {
+            account_id,
+            from,
+            to,
+            starting_balances,
+            days,
+            negative_dates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_flags_negative_days() {
+        let date = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        let cash = Uuid::new_v4();
+        let bank = Uuid::new_v4();
+        // Owes 100 of a 150 limit
+        let card = Uuid::new_v4();
+        let rent = ForecastSource::RecurringTransaction(Uuid::new_v4());
+        let event = |d, asset_id, amount| ForecastEvent {
+            date: date(d),
+            asset_id,
+            amount: Decimal::new(amount, 0),
+            source: rent,
+        };
+
+        let forecast = Forecast::project(
+            Uuid::new_v4(),
+            BTreeMap::from([
+                (cash, Decimal::new(50, 0)),
+                (bank, Decimal::new(100, 0)),
+                (card, Decimal::new(-100, 0)),
+            ]),
+            &BTreeMap::from([
+                (cash, Decimal::ZERO),
+                (bank, Decimal::ZERO),
+                (card, Decimal::new(-150, 0)),
+            ]),
+            vec![
+                event(3, cash, -60),
+                event(5, card, -80),
+                // Overdue occurrences land on the first day
+                event(1, bank, -10),
+                event(4, cash, 20),
+                // Beyond the horizon
+                event(9, bank, -500),
+            ],
+            date(2),
+            date(5),
+        );
+
+        assert_eq!(forecast.days.len(), 4);
+        assert_eq!(forecast.days[0].date, date(2));
+        assert_eq!(forecast.days[0].balances[&bank], Decimal::new(90, 0));
+        assert_eq!(forecast.days[0].events[0].date, date(2));
+        assert_eq!(forecast.days[1].balances[&cash], Decimal::new(-10, 0));
+        assert_eq!(forecast.days[1].negative_asset_ids, vec![cash]);
+        assert_eq!(forecast.days[2].balances[&cash], Decimal::new(10, 0));
+        assert_eq!(forecast.days[3].balances[&bank], Decimal::new(90, 0));
+        assert_eq!(forecast.days[3].negative_asset_ids, vec![card]);
+        assert_eq!(forecast.negative_dates, vec![date(3), date(5)]);
+        assert_eq!(forecast.starting_balances[&cash], Decimal::new(50, 0));
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{Months, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::repository::get_account_forecast;
+
+/// Query parameters for the cash-flow forecast
+#[derive(Deserialize)]
+pub struct ForecastQuery {
+    /// Number of months to project, 6 by default and at most 24
+    pub months: Option<u32>,
+}
+
+/// Handler: Project the balance of every asset of an account day by day
+pub async fn get_account_forecast_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<ForecastQuery>,
+) -> impl IntoResponse {
+    let months = query.months.unwrap_or(6).clamp(1, 24);
+    let now = Utc::now();
+    let Some(to) = now.date_naive().checked_add_months(Months::new(months)) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    match get_account_forecast(&pool, account_id, now, to).await {
+        Ok(forecast) => forecast.into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to forecast balances for account {}: {:?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
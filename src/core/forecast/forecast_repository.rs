@@ -0,0 +1,357 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::models::{Forecast, ForecastEvent, ForecastSource, RecurringTransaction, Transaction};
+use crate::repository::{get_account_by_id, get_asset_by_user_id};
+
+// Transactions touching the given assets that are dated after $2
+const QUERY_SELECT_FUTURE_TRANSACTIONS: &str = "
+    SELECT * FROM transactions
+    WHERE (from_asset_id = ANY($1) OR to_asset_id = ANY($1)) AND transaction_time > $2
+";
+const QUERY_SELECT_ACTIVE_RECURRING: &str = "
+    SELECT * FROM recurring_transactions
+    WHERE is_active AND (asset_id = ANY($1) OR to_asset_id = ANY($1))
+";
+// Whether a transfer changes currency, and what its receiving side gets at today's rate,
+// worked out like the `convert_transaction_currency` trigger does when it is posted
+const QUERY_SELECT_CONVERTED_AMOUNT: &str = "
+    SELECT
+        f.currency_code <> t.currency_code,
+        ROUND($3 * currency_exchange_rate(f.currency_code, t.currency_code), 2)
+    FROM assets f, assets t
+    WHERE f.id = $1 AND t.id = $2
+";
+const QUERY_COUNT_RUNS: &str = "
+    SELECT COUNT(*) FROM recurring_transaction_runs
+    WHERE recurring_transaction_id = $1 AND outcome = 'Posted'
+";
+
+/// Project the balance of every asset of an account from `now` to the end of `to`.
+///
+/// Future-dated transactions are already included in `assets.balance`, so they are taken
+/// out of the starting balances and booked again on their own date. Every remaining
+/// occurrence of the active recurring transactions is added on its scheduled date;
+/// occurrences that are already due count as today. Transfers into another currency are
+/// received at today's rate, and left out when there is none, as they could not be posted.
+pub async fn get_account_forecast(
+    pool: &PgPool,
+    account_id: Uuid,
+    now: DateTime<Utc>,
+    to: NaiveDate,
+) -> Result<Forecast, sqlx::Error> {
+    get_account_by_id(pool, account_id).await?;
+
+    let assets = get_asset_by_user_id(pool, account_id).await?;
+    let asset_ids: Vec<Uuid> = assets.iter().map(|asset| asset.id).collect();
+    let mut starting_balances: BTreeMap<Uuid, Decimal> = assets
+        .iter()
+        .map(|asset| (asset.id, asset.balance))
+        .collect();
+    let floors: BTreeMap<Uuid, Decimal> = assets
+        .iter()
+        .filter_map(|asset| Some((asset.id, asset.lowest_allowed_balance()?)))
+        .collect();
+    let mut events = Vec::new();
+
+    let future_transactions = sqlx::query_as::<_, Transaction>(QUERY_SELECT_FUTURE_TRANSACTIONS)
+        .bind(&asset_ids)
+        .bind(now)
+        .fetch_all(pool)
+        .await?;
+    for tx in &future_transactions {
+        let Some(transaction_time) = tx.transaction_time else {
+            continue;
+        };
+        for (asset_id, amount) in tx.balance_effects() {
+            if let Some(balance) = starting_balances.get_mut(&asset_id) {
+                *balance -= amount;
+                events.push(ForecastEvent {
+                    date: transaction_time.date_naive(),
+                    asset_id,
+                    amount,
+                    source: ForecastSource::Transaction(tx.id),
+                });
+            }
+        }
+    }
+
+    let recurring_transactions =
+        sqlx::query_as::<_, RecurringTransaction>(QUERY_SELECT_ACTIVE_RECURRING)
+            .bind(&asset_ids)
+            .fetch_all(pool)
+            .await?;
+    for recurring in &recurring_transactions {
+        let remaining = match recurring.max_occurrences {
+            Some(max) => {
+                let executed: i64 = sqlx::query_scalar(QUERY_COUNT_RUNS)
+                    .bind(recurring.id)
+                    .fetch_one(pool)
+                    .await?;
+                (i64::from(max) - executed).max(0) as usize
+            }
+            None => usize::MAX,
+        };
+
+        let mut to_amount = None;
+        if let Some(to_asset_id) = recurring.to_asset_id {
+            let converted: Option<(bool, Option<Decimal>)> =
+                sqlx::query_as(QUERY_SELECT_CONVERTED_AMOUNT)
+                    .bind(recurring.asset_id)
+                    .bind(to_asset_id)
+                    .bind(recurring.amount)
+                    .fetch_optional(pool)
+                    .await?;
+            if let Some((true, converted)) = converted {
+                if converted.is_none() {
+                    continue;
+                }
+                to_amount = converted;
+            }
+        }
+
+        let executions = recurring
+            .upcoming_executions()
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?
+            .take_while(|at| at.date_naive() <= to)
+            .take(remaining);
+        for scheduled_at in executions {
+            let transaction = Transaction {
+                to_amount,
+                ..recurring.transaction_at(scheduled_at)
+            };
+            for (asset_id, amount) in transaction.balance_effects() {
+                events.push(ForecastEvent {
+                    date: scheduled_at.date_naive(),
+                    asset_id,
+                    amount,
+                    source: ForecastSource::RecurringTransaction(recurring.id),
+                });
+            }
+        }
+    }
+
+    Ok(Forecast::project(
+        account_id,
+        starting_balances,
+        &floors,
+        events,
+        now.date_naive(),
+        to,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::recurring_transaction::recurring_transaction::RecurrenceSchedule;
+    use crate::models::{
+        IntervalChoices, MissedRunPolicy, NewRecurringTransaction, RecurringTransactionType,
+        TransactionType,
+    };
+    use crate::repository::{apply_transaction_balance, create_asset, create_transaction};
+    use chrono::Duration;
+    use sqlx::{migrate::MigrateDatabase, Postgres};
+    use std::env;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Migrations failed");
+
+        pool
+    }
+
+    async fn insert_user_and_account(pool: &PgPool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, 0, now(), now())")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        user_id
+    }
+
+    #[tokio::test]
+    async fn test_account_forecast() {
+        let pool = setup_test_db().await;
+        let account_id = insert_user_and_account(&pool).await;
+        let cash = create_asset(
+            &pool,
+            account_id,
+            "cash".to_string(),
+            Decimal::new(50, 0),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+
+        let now = Utc::now();
+        let today = now.date_naive();
+
+        // A purchase dated in three days is already taken off the stored balance
+        let mut conn = pool.acquire().await.unwrap();
+        let purchase = create_transaction(
+            &mut *conn,
+            Some(cash.id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(10, 0),
+            None,
+            Some(account_id),
+            None,
+            Some(now + Duration::days(3)),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut conn, &purchase)
+            .await
+            .unwrap();
+        drop(conn);
+
+        // A monthly bill of 30 starting tomorrow
+        crate::repository::create_recurring_transaction(
+            &pool,
+            &NewRecurringTransaction {
+                account_id,
+                asset_id: cash.id,
+                to_asset_id: None,
+                to_account_id: None,
+                amount: Decimal::new(30, 0),
+                fee: Decimal::ZERO,
+                transaction_type: RecurringTransactionType::Expense,
+                category_id: None,
+                payee_id: None,
+                notes: None,
+                missed_run_policy: MissedRunPolicy::CatchUpAll,
+                schedule: RecurrenceSchedule {
+                    interval: Some(IntervalChoices::Monthly),
+                    starts_at: Some(now + Duration::days(1)),
+                    ..RecurrenceSchedule::default()
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        // A monthly 320 moved to a wallet in the test currency, at 32 to the unit
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate) VALUES ('XTS', 'Test currency', '32.0')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let savings = create_asset(
+            &pool,
+            account_id,
+            "savings".to_string(),
+            Decimal::new(1000, 0),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+        let wallet = create_asset(
+            &pool,
+            account_id,
+            "travel wallet".to_string(),
+            Decimal::ZERO,
+            "XTS",
+        )
+        .await
+        .unwrap();
+        crate::repository::create_recurring_transaction(
+            &pool,
+            &NewRecurringTransaction {
+                account_id,
+                asset_id: savings.id,
+                to_asset_id: Some(wallet.id),
+                to_account_id: None,
+                amount: Decimal::new(320, 0),
+                fee: Decimal::ZERO,
+                transaction_type: RecurringTransactionType::InternalTransfer,
+                category_id: None,
+                payee_id: None,
+                notes: None,
+                missed_run_policy: MissedRunPolicy::CatchUpAll,
+                schedule: RecurrenceSchedule {
+                    interval: Some(IntervalChoices::Monthly),
+                    starts_at: Some(now + Duration::days(1)),
+                    ..RecurrenceSchedule::default()
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        let to = today + Duration::days(40);
+        let forecast = get_account_forecast(&pool, account_id, now, to)
+            .await
+            .unwrap();
+
+        let asset_balance_on = |asset_id: Uuid, days: i64| {
+            let date = today + Duration::days(days);
+            forecast
+                .days
+                .iter()
+                .find(|day| day.date == date)
+                .unwrap()
+                .balances[&asset_id]
+        };
+        let balance_on = |days: i64| asset_balance_on(cash.id, days);
+
+        assert_eq!(forecast.starting_balances[&cash.id], Decimal::new(50, 0));
+        assert_eq!(forecast.days.len(), 41);
+        assert_eq!(balance_on(0), Decimal::new(50, 0));
+        assert_eq!(balance_on(1), Decimal::new(20, 0));
+        assert_eq!(balance_on(3), Decimal::new(10, 0));
+        assert_eq!(balance_on(40), Decimal::new(-20, 0));
+
+        // The wallet receives the converted amount, not the 320 sent
+        assert_eq!(asset_balance_on(wallet.id, 0), Decimal::ZERO);
+        assert_eq!(asset_balance_on(wallet.id, 1), Decimal::new(10, 0));
+        assert_eq!(asset_balance_on(wallet.id, 40), Decimal::new(20, 0));
+        assert_eq!(asset_balance_on(savings.id, 40), Decimal::new(360, 0));
+        assert!(forecast.negative_dates.contains(&to));
+        assert!(!forecast
+            .negative_dates
+            .contains(&(today + Duration::days(3))));
+    }
+}
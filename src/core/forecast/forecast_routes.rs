@@ -0,0 +1,16 @@
+use axum::{routing::get, Router};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::forecast::forecast_handler::*, models::Backend};
+
+/// Defines routes projecting future balances
+pub fn forecast_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // GET /accounts/{id}/forecast?months=6
+        // -> Daily balance of every asset, flagging the days one goes negative
+        .route("/accounts/{id}/forecast", get(get_account_forecast_handler))
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
@@ -0,0 +1,4 @@
+pub mod forecast;
+pub mod forecast_handler;
+pub mod forecast_repository;
+pub mod forecast_routes;
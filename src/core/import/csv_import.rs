@@ -0,0 +1,276 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use encoding_rs::Encoding;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use crate::models::{
+    CsvImportProfile, ParsedStatement, SignConvention, StatementLine, TransactionType,
+};
+
+/// Resolved column positions of a profile against one file
+struct Columns {
+    date: usize,
+    description: Option<usize>,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+}
+
+/// Decode and parse a CSV statement with the given profile.
+///
+/// Fails as a whole when the file can't be decoded or a mapped column is missing;
+/// individual lines that can't be read are returned as errors.
+pub fn parse_csv_statement(
+    profile: &CsvImportProfile,
+    bytes: &[u8],
+) -> Result<ParsedStatement, String> {
+    let encoding = Encoding::for_label(profile.encoding.trim().as_bytes())
+        .ok_or_else(|| format!("unknown encoding {}", profile.encoding))?;
+    let (text, _, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(format!("file is not valid {}", encoding.name()));
+    }
+
+    let delimiter = match profile.delimiter.as_bytes() {
+        [delimiter] => *delimiter,
+        _ => return Err("delimiter must be a single ASCII character".to_string()),
+    };
+
+    // Drop banner lines before the header or the first record
+    let skip_rows = profile.skip_rows.max(0) as usize;
+    let body = text
+        .split_inclusive('\n')
+        .skip(skip_rows)
+        .collect::<String>();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let mut records = reader.records();
+
+    let headers = if profile.has_header {
+        match records.next() {
+            Some(header) => header
+                .map_err(|err| err.to_string())?
+                .iter()
+                .map(|name| name.trim().to_string())
+                .collect(),
+            None => return Ok(ParsedStatement::default()),
+        }
+    } else {
+        Vec::new()
+    };
+    let columns = resolve_columns(profile, &headers)?;
+
+    let mut lines = Vec::new();
+    for record in records {
+        let (line, parsed) = match record {
+            Ok(record) => {
+                if record.iter().all(|field| field.trim().is_empty()) {
+                    continue;
+                }
+                let line = record.position().map_or(0, |pos| pos.line());
+                (line, parse_record(profile, &columns, &record))
+            }
+            Err(err) => {
+                let line = err.position().map_or(0, |pos| pos.line());
+                (line, Err(err.to_string()))
+            }
+        };
+        lines.push((line + skip_rows as u64, parsed));
+    }
+
+    // CSV exports carry no closing balance to check against
+    Ok(ParsedStatement {
+        lines,
+        balance: None,
+    })
+}
+
+/// Find the position of every mapped column, by header name first and then by index
+fn resolve_columns(profile: &CsvImportProfile, headers: &[String]) -> Result<Columns, String> {
+    let resolve = |spec: &str| -> Result<usize, String> {
+        let spec = spec.trim();
+        headers
+            .iter()
+            .position(|name| name == spec)
+            .or_else(|| spec.parse().ok())
+            .ok_or_else(|| format!("column {} not found", spec))
+    };
+    let resolve_optional = |spec: &Option<String>| spec.as_deref().map(resolve).transpose();
+
+    Ok(Columns {
+        date: resolve(&profile.date_column)?,
+        description: resolve_optional(&profile.description_column)?,
+        amount: resolve_optional(&profile.amount_column)?,
+        debit: resolve_optional(&profile.debit_column)?,
+        credit: resolve_optional(&profile.credit_column)?,
+    })
+}
+
+/// Turn one CSV record into a statement line
+fn parse_record(
+    profile: &CsvImportProfile,
+    columns: &Columns,
+    record: &csv::StringRecord,
+) -> Result<StatementLine, String> {
+    let field = |index: usize| record.get(index).unwrap_or("").trim();
+
+    let date = parse_date(field(columns.date), &profile.date_format)?;
+
+    // Signed amount: positive means money into the asset
+    let signed = match profile.sign_convention {
+        SignConvention::NegativeIsExpense | SignConvention::PositiveIsExpense => {
+            let column = columns.amount.ok_or("amount column is not mapped")?;
+            let amount = parse_amount(field(column))?.ok_or("amount is empty")?;
+            if profile.sign_convention == SignConvention::PositiveIsExpense {
+                -amount
+            } else {
+                amount
+            }
+        }
+        SignConvention::SeparateColumns => {
+            let debit = columns.debit.ok_or("debit column is not mapped")?;
+            let credit = columns.credit.ok_or("credit column is not mapped")?;
+            let debit = parse_amount(field(debit))?.unwrap_or(Decimal::ZERO);
+            let credit = parse_amount(field(credit))?.unwrap_or(Decimal::ZERO);
+            credit.abs() - debit.abs()
+        }
+    };
+    if signed.is_zero() {
+        return Err("amount is zero".to_string());
+    }
+
+    let transaction_type = if signed > Decimal::ZERO {
+        TransactionType::Income
+    } else {
+        TransactionType::Expense
+    };
+    let notes = columns
+        .description
+        .map(field)
+        .filter(|description| !description.is_empty())
+        .map(str::to_string);
+
+    Ok(StatementLine {
+        date,
+        amount: signed.abs(),
+        transaction_type,
+        notes,
+        external_id: None,
+    })
+}
+
+/// Parse a date, accepting formats that also carry a time of day
+fn parse_date(value: &str, format: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, format)
+        .or_else(|_| NaiveDateTime::parse_from_str(value, format).map(|dt| dt.date()))
+        .map_err(|_| format!("invalid date {:?} for format {:?}", value, format))
+}
+
+/// Parse a statement amount such as "1,234.50", "-80", "NT$ 99" or "(12.00)".
+/// Returns None for an empty cell.
+fn parse_amount(value: &str) -> Result<Option<Decimal>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    // Accounting style negatives: "(12.00)"
+    let (negative, value) = match value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, value),
+    };
+    let cleaned: String = value
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect();
+
+    let amount = Decimal::from_str(&cleaned).map_err(|_| format!("invalid amount {:?}", value))?;
+    Ok(Some(if negative { -amount } else { amount }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn profile(sign_convention: SignConvention) -> CsvImportProfile {
+        CsvImportProfile {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            name: "test".to_string(),
+            encoding: "UTF-8".to_string(),
+            delimiter: ",".to_string(),
+            has_header: true,
+            skip_rows: 0,
+            date_column: "Date".to_string(),
+            date_format: "%Y/%m/%d".to_string(),
+            description_column: Some("Description".to_string()),
+            sign_convention,
+            amount_column: Some("Amount".to_string()),
+            debit_column: None,
+            credit_column: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_parse_big5_statement_with_separate_columns() {
+        let mut profile = profile(SignConvention::SeparateColumns);
+        profile.encoding = "Big5".to_string();
+        profile.skip_rows = 1;
+        profile.date_column = "交易日期".to_string();
+        profile.description_column = Some("摘要".to_string());
+        profile.amount_column = None;
+        profile.debit_column = Some("支出".to_string());
+        profile.credit_column = Some("存入".to_string());
+
+        let text = "帳號 123-456\n交易日期,摘要,支出,存入\n2025/07/01,薪資,,\"52,000\"\n2025/07/02,全聯,350,\n2025/07/03,oops,,\n";
+        let (bytes, _, _) = encoding_rs::BIG5.encode(text);
+
+        let lines = parse_csv_statement(&profile, &bytes).unwrap().lines;
+        assert_eq!(lines.len(), 3);
+
+        let (line, salary) = &lines[0];
+        let salary = salary.as_ref().unwrap();
+        assert_eq!(*line, 3);
+        assert_eq!(salary.transaction_type, TransactionType::Income);
+        assert_eq!(salary.amount, Decimal::new(52000, 0));
+        assert_eq!(salary.notes.as_deref(), Some("薪資"));
+
+        let grocery = lines[1].1.as_ref().unwrap();
+        assert_eq!(grocery.transaction_type, TransactionType::Expense);
+        assert_eq!(grocery.amount, Decimal::new(350, 0));
+        assert_eq!(grocery.date, NaiveDate::from_ymd_opt(2025, 7, 2).unwrap());
+
+        assert!(lines[2].1.is_err());
+    }
+
+    #[test]
+    fn test_parse_credit_card_statement() {
+        let profile = profile(SignConvention::PositiveIsExpense);
+        let text = "Date,Description,Amount\n2025/07/05,Coffee,120\n2025/07/06,Refund,(30.50)\n2025/13/01,Bad date,10\n";
+
+        let lines = parse_csv_statement(&profile, text.as_bytes())
+            .unwrap()
+            .lines;
+        let charge = lines[0].1.as_ref().unwrap();
+        assert_eq!(charge.transaction_type, TransactionType::Expense);
+        assert_eq!(charge.amount, Decimal::new(120, 0));
+
+        let refund = lines[1].1.as_ref().unwrap();
+        assert_eq!(refund.transaction_type, TransactionType::Income);
+        assert_eq!(refund.amount, Decimal::new(3050, 2));
+
+        assert!(lines[2].1.is_err());
+
+        // A missing mapped column fails the whole file
+        let text = "When,Description,Amount\n2025/07/05,Coffee,120\n";
+        assert!(parse_csv_statement(&profile, text.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::TransactionType;
+
+/// How the amount columns of a statement encode money in and out of the asset
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum SignConvention {
+    /// One amount column; negative amounts leave the asset (typical bank account export)
+    NegativeIsExpense,
+    /// One amount column; positive amounts are charges (typical credit-card export)
+    PositiveIsExpense,
+    /// Withdrawals and deposits in separate `debit_column` / `credit_column`
+    SeparateColumns,
+}
+
+/// Saved column mapping used to read one bank's CSV statements.
+/// Columns are referenced by header name, or by zero-based index when there is no header.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CsvImportProfile {
+    pub id: Uuid,
+
+    /// Account the profile belongs to
+    pub account_id: Uuid,
+
+    /// Display name, unique per account (e.g., "Cathay United Bank")
+    pub name: String,
+
+    /// Character encoding of the file (e.g., "UTF-8", "Big5")
+    pub encoding: String,
+
+    /// Field delimiter, a single character
+    pub delimiter: String,
+
+    /// Whether the first row after `skip_rows` holds column names
+    pub has_header: bool,
+
+    /// Lines to drop before the header or first record (bank banners, account numbers)
+    pub skip_rows: i32,
+
+    pub date_column: String,
+
+    /// `chrono` format of the date column (e.g., "%Y/%m/%d")
+    pub date_format: String,
+
+    pub description_column: Option<String>,
+
+    pub sign_convention: SignConvention,
+
+    /// Signed amount column, for `NegativeIsExpense` and `PositiveIsExpense`
+    pub amount_column: Option<String>,
+
+    /// Withdrawal column, for `SeparateColumns`
+    pub debit_column: Option<String>,
+
+    /// Deposit column, for `SeparateColumns`
+    pub credit_column: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A column mapping profile as submitted by the client; unset options take the defaults
+#[derive(Debug, Deserialize)]
+pub struct NewCsvImportProfile {
+    pub account_id: Uuid,
+    pub name: String,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    #[serde(default)]
+    pub skip_rows: i32,
+    pub date_column: String,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    pub description_column: Option<String>,
+    pub sign_convention: SignConvention,
+    pub amount_column: Option<String>,
+    pub debit_column: Option<String>,
+    pub credit_column: Option<String>,
+}
+
+fn default_encoding() -> String {
+    "UTF-8".to_string()
+}
+
+fn default_delimiter() -> String {
+    ",".to_string()
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+/// Allows a `CsvImportProfile` to be returned as a JSON response
+impl IntoResponse for CsvImportProfile {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Wrapper for returning a list of CSV import profiles
+#[derive(Debug, Serialize)]
+pub struct CsvImportProfileList(pub Vec<CsvImportProfile>);
+
+/// Enables `CsvImportProfileList` to be returned as a JSON response
+impl IntoResponse for CsvImportProfileList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// One movement read from a statement, independent of the file format
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementLine {
+    pub date: NaiveDate,
+
+    /// Always positive; the direction is given by `transaction_type`
+    pub amount: Decimal,
+
+    /// `Income` for money into the asset, `Expense` for money out
+    pub transaction_type: TransactionType,
+
+    /// Statement description, stored as the transaction notes
+    pub notes: Option<String>,
+
+    /// ID the bank gave the line (OFX `FITID`), when the format has one
+    pub external_id: Option<String>,
+}
+
+/// A statement line together with its line number in the file (starting at 1),
+/// or the reason it could not be read
+pub type ParsedLine = (u64, Result<StatementLine, String>);
+
+/// Closing balance reported by a statement (OFX `LEDGERBAL`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatementBalance {
+    pub amount: Decimal,
+    pub as_of: NaiveDate,
+}
+
+/// A parsed statement, ready to be previewed or committed
+#[derive(Debug, Default, Clone)]
+pub struct ParsedStatement {
+    pub lines: Vec<ParsedLine>,
+    pub balance: Option<StatementBalance>,
+}
+
+/// Statement closing balance compared with the asset balance after the import
+#[derive(Debug, Serialize)]
+pub struct StatementBalanceCheck {
+    pub statement_balance: Decimal,
+    pub as_of: NaiveDate,
+
+    /// Asset balance once the new lines are applied (projected in a preview)
+    pub asset_balance: Decimal,
+
+    /// `asset_balance - statement_balance`; zero when the asset agrees with the bank
+    pub difference: Decimal,
+}
+
+/// Outcome of one statement line in a preview or commit
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum ImportRowStatus {
+    /// Will be (or was) imported
+    New,
+    /// Already imported (same bank ID), or matches an existing transaction
+    /// on date, amount and notes; skipped
+    Duplicate,
+    /// Could not be parsed; skipped
+    Invalid,
+    /// Breaks a rule of its transaction type; not imported
+    Rejected,
+}
+
+/// One statement line as shown in a preview
+#[derive(Debug, Serialize)]
+pub struct ImportRow {
+    /// Line number in the file, starting at 1
+    pub line: u64,
+    pub status: ImportRowStatus,
+    pub date: Option<NaiveDate>,
+    pub amount: Option<Decimal>,
+    pub transaction_type: Option<TransactionType>,
+    pub notes: Option<String>,
+    pub external_id: Option<String>,
+
+    /// Why the line is `Invalid` or `Rejected`
+    pub error: Option<String>,
+}
+
+/// Result of parsing a statement without writing anything
+#[derive(Debug, Serialize)]
+pub struct ImportPreview {
+    pub rows: Vec<ImportRow>,
+    pub new_count: usize,
+    pub duplicate_count: usize,
+    pub invalid_count: usize,
+
+    /// Only for formats that report a closing balance
+    pub balance_check: Option<StatementBalanceCheck>,
+}
+
+/// Enables `ImportPreview` to be returned as a JSON response
+impl IntoResponse for ImportPreview {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Result of committing a statement
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    /// IDs of the transactions that were created
+    pub transaction_ids: Vec<Uuid>,
+    pub duplicate_count: usize,
+    pub invalid_count: usize,
+
+    /// New lines refused by transaction validation, with the rule they break
+    pub rejected: Vec<ImportRow>,
+
+    /// Only for formats that report a closing balance
+    pub balance_check: Option<StatementBalanceCheck>,
+}
+
+/// Enables `ImportResult` to be returned as a JSON response
+impl IntoResponse for ImportResult {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
@@ -0,0 +1,248 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use encoding_rs::Encoding;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::import::csv_import::parse_csv_statement;
+use crate::core::import::ofx_import::parse_ofx_statement;
+use crate::models::{CsvImportProfileList, ImportResult, NewCsvImportProfile, ParsedStatement};
+use crate::repository::{
+    commit_statement_lines, create_csv_import_profile, delete_csv_import_profile,
+    get_csv_import_profile_by_id, get_csv_import_profiles_by_account_id, preview_statement_lines,
+};
+
+/// Query parameters selecting the profile and target asset of a CSV import
+#[derive(Deserialize)]
+pub struct CsvImportQuery {
+    pub profile_id: Uuid,
+    pub asset_id: Uuid,
+}
+
+/// Query parameters selecting the target asset of an OFX/QFX import
+#[derive(Deserialize)]
+pub struct OfxImportQuery {
+    pub asset_id: Uuid,
+}
+
+/// Handler: Fetch the CSV import profiles of an account
+pub async fn get_csv_import_profiles_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_csv_import_profiles_by_account_id(&pool, account_id).await {
+        Ok(profiles) => CsvImportProfileList(profiles).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch CSV import profiles for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Fetch a CSV import profile by ID
+pub async fn get_csv_import_profile_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(profile_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_csv_import_profile_by_id(&pool, profile_id).await {
+        Ok(profile) => profile.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch CSV import profile {}: {:#?}",
+                profile_id, err
+            );
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// Handler: Save a CSV column mapping profile
+pub async fn add_csv_import_profile_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<NewCsvImportProfile>,
+) -> impl IntoResponse {
+    // Reject profiles that could never read a file
+    if Encoding::for_label(payload.encoding.trim().as_bytes()).is_none()
+        || payload.delimiter.len() != 1
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    match create_csv_import_profile(&pool, payload).await {
+        Ok(profile) => (StatusCode::CREATED, profile).into_response(),
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            StatusCode::CONFLICT.into_response()
+        }
+        Err(sqlx::Error::Database(err)) if err.is_check_violation() => {
+            eprintln!("Rejected CSV import profile: {}", err);
+            StatusCode::BAD_REQUEST.into_response()
+        }
+        Err(err) => {
+            eprintln!("Failed to create CSV import profile: {:#?}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Delete a CSV import profile by ID
+pub async fn delete_csv_import_profile_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(profile_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_csv_import_profile(&pool, profile_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to delete CSV import profile {}: {:#?}",
+                profile_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Parse a CSV statement (raw request body) and report which lines would be
+/// imported, which are duplicates and which can't be read. Nothing is written.
+pub async fn preview_csv_import_handler(
+    State(pool): State<Arc<PgPool>>,
+    Query(query): Query<CsvImportQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let statement = match read_csv_statement(&pool, query.profile_id, &body).await {
+        Ok(statement) => statement,
+        Err(status) => return status.into_response(),
+    };
+
+    preview_statement(&pool, query.asset_id, statement).await
+}
+
+/// Handler: Import the new lines of a CSV statement (raw request body) into an asset.
+/// Either every new line is imported or none is.
+pub async fn commit_csv_import_handler(
+    State(pool): State<Arc<PgPool>>,
+    Query(query): Query<CsvImportQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let statement = match read_csv_statement(&pool, query.profile_id, &body).await {
+        Ok(statement) => statement,
+        Err(status) => return status.into_response(),
+    };
+
+    commit_statement(&pool, query.asset_id, statement).await
+}
+
+/// Handler: Parse an OFX/QFX statement (raw request body) and report which transactions
+/// would be imported and how the statement balance compares with the asset. Nothing is written.
+pub async fn preview_ofx_import_handler(
+    State(pool): State<Arc<PgPool>>,
+    Query(query): Query<OfxImportQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    match parse_ofx_statement(&body) {
+        Ok(statement) => preview_statement(&pool, query.asset_id, statement).await,
+        Err(err) => {
+            eprintln!("Rejected OFX statement: {}", err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+    }
+}
+
+/// Handler: Import the new transactions of an OFX/QFX statement (raw request body) into an asset.
+/// Transactions already imported under the same FITID are skipped, so a file can be re-imported.
+pub async fn commit_ofx_import_handler(
+    State(pool): State<Arc<PgPool>>,
+    Query(query): Query<OfxImportQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    match parse_ofx_statement(&body) {
+        Ok(statement) => commit_statement(&pool, query.asset_id, statement).await,
+        Err(err) => {
+            eprintln!("Rejected OFX statement: {}", err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+    }
+}
+
+/// Preview a parsed statement against an asset
+async fn preview_statement(
+    pool: &PgPool,
+    asset_id: Uuid,
+    statement: ParsedStatement,
+) -> axum::response::Response {
+    let result = match pool.acquire().await {
+        Ok(mut conn) => preview_statement_lines(&mut conn, asset_id, statement).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(preview) => preview.into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to preview import into asset {}: {:#?}",
+                asset_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Commit a parsed statement into an asset
+async fn commit_statement(
+    pool: &PgPool,
+    asset_id: Uuid,
+    statement: ParsedStatement,
+) -> axum::response::Response {
+    match commit_statement_lines_atomically(pool, asset_id, statement).await {
+        Ok(result) => (StatusCode::CREATED, result).into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!("Failed to import into asset {}: {:#?}", asset_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Load the profile and parse the statement with it
+async fn read_csv_statement(
+    pool: &PgPool,
+    profile_id: Uuid,
+    body: &[u8],
+) -> Result<ParsedStatement, StatusCode> {
+    let profile = get_csv_import_profile_by_id(pool, profile_id)
+        .await
+        .map_err(|err| {
+            eprintln!(
+                "Failed to fetch CSV import profile {}: {:#?}",
+                profile_id, err
+            );
+            StatusCode::NOT_FOUND
+        })?;
+
+    parse_csv_statement(&profile, body).map_err(|err| {
+        eprintln!("Rejected CSV statement: {}", err);
+        StatusCode::UNPROCESSABLE_ENTITY
+    })
+}
+
+/// Run the import in one database transaction
+async fn commit_statement_lines_atomically(
+    pool: &PgPool,
+    asset_id: Uuid,
+    statement: ParsedStatement,
+) -> Result<ImportResult, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+    let result = commit_statement_lines(&mut db_tx, asset_id, statement).await?;
+    db_tx.commit().await?;
+    Ok(result)
+}
@@ -0,0 +1,626 @@
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::core::transaction::transaction_validation::{check_transaction, TransactionError};
+use crate::models::{
+    CsvImportProfile, ImportPreview, ImportResult, ImportRow, ImportRowStatus, NewCsvImportProfile,
+    ParsedStatement, StatementBalance, StatementBalanceCheck, StatementLine, TransactionType,
+};
+use crate::repository::{
+    apply_rules_to_transaction, apply_transaction_balance, create_transaction, find_payee_by_alias,
+    get_asset_by_id, lock_asset_by_id,
+};
+
+// SQL query constants
+const QUERY_SELECT_PROFILES_BY_ACCOUNT_ID: &str =
+    "SELECT * FROM csv_import_profiles WHERE account_id = $1 ORDER BY name";
+const QUERY_SELECT_PROFILE_BY_ID: &str = "SELECT * FROM csv_import_profiles WHERE id = $1";
+const QUERY_INSERT_PROFILE: &str = "
+    INSERT INTO csv_import_profiles (
+        account_id, name, encoding, delimiter, has_header, skip_rows,
+        date_column, date_format, description_column,
+        sign_convention, amount_column, debit_column, credit_column
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13
+    )
+    RETURNING *
+";
+const QUERY_DELETE_PROFILE: &str = "DELETE FROM csv_import_profiles WHERE id = $1";
+
+// SQL query to fetch the date, amount, type, notes and bank ID of an asset's transactions
+// in a date range, used to recognise statement lines that were imported before
+const QUERY_SELECT_EXISTING_LINES: &str = "
+    SELECT
+        (COALESCE(t.transaction_time, t.created_at) AT TIME ZONE 'UTC')::date AS date,
+        t.amount, t.transaction_type, t.notes, i.external_id
+    FROM transactions t
+    LEFT JOIN imported_statement_lines i ON i.transaction_id = t.id AND i.asset_id = $1
+    WHERE (t.from_asset_id = $1 OR t.to_asset_id = $1)
+      AND COALESCE(t.transaction_time, t.created_at) >= $2
+      AND COALESCE(t.transaction_time, t.created_at) < $3
+";
+
+// SQL query to find which of the given bank IDs were already imported into an asset
+const QUERY_SELECT_IMPORTED_EXTERNAL_IDS: &str = "
+    SELECT external_id FROM imported_statement_lines
+    WHERE asset_id = $1 AND external_id = ANY($2)
+";
+
+// SQL query recording the bank ID of an imported line
+const QUERY_INSERT_IMPORTED_LINE: &str = "
+    INSERT INTO imported_statement_lines (asset_id, external_id, transaction_id)
+    VALUES ($1, $2, $3)
+";
+
+/// Key two statement lines must share to count as the same movement
+type DuplicateKey = (NaiveDate, Decimal, i32, Option<String>);
+
+/// Fetch the CSV import profiles of an account
+pub async fn get_csv_import_profiles_by_account_id(
+    pool: &PgPool,
+    account_id: Uuid,
+) -> Result<Vec<CsvImportProfile>, sqlx::Error> {
+    sqlx::query_as::<_, CsvImportProfile>(QUERY_SELECT_PROFILES_BY_ACCOUNT_ID)
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Fetch a CSV import profile by ID
+pub async fn get_csv_import_profile_by_id(
+    pool: &PgPool,
+    profile_id: Uuid,
+) -> Result<CsvImportProfile, sqlx::Error> {
+    sqlx::query_as::<_, CsvImportProfile>(QUERY_SELECT_PROFILE_BY_ID)
+        .bind(profile_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Save a new CSV column mapping profile
+pub async fn create_csv_import_profile(
+    pool: &PgPool,
+    profile: NewCsvImportProfile,
+) -> Result<CsvImportProfile, sqlx::Error> {
+    sqlx::query_as::<_, CsvImportProfile>(QUERY_INSERT_PROFILE)
+        .bind(profile.account_id)
+        .bind(profile.name)
+        .bind(profile.encoding)
+        .bind(profile.delimiter)
+        .bind(profile.has_header)
+        .bind(profile.skip_rows)
+        .bind(profile.date_column)
+        .bind(profile.date_format)
+        .bind(profile.description_column)
+        .bind(profile.sign_convention)
+        .bind(profile.amount_column)
+        .bind(profile.debit_column)
+        .bind(profile.credit_column)
+        .fetch_one(pool)
+        .await
+}
+
+/// Delete a CSV import profile by ID
+pub async fn delete_csv_import_profile(pool: &PgPool, profile_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(QUERY_DELETE_PROFILE)
+        .bind(profile_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Classify parsed statement lines against an asset's existing transactions.
+///
+/// A line with a bank ID is a duplicate when that ID was imported into the asset before.
+/// Otherwise a line is a duplicate when an existing transaction of the asset has the same
+/// date, amount, direction and notes; transactions imported under another bank ID never
+/// match a line that has one. Matching is one-to-one, so two identical coffees on the same
+/// day are both kept unless the asset already has two of them.
+pub async fn preview_statement_lines(
+    conn: &mut PgConnection,
+    asset_id: Uuid,
+    statement: ParsedStatement,
+) -> Result<ImportPreview, sqlx::Error> {
+    let asset = get_asset_by_id(&mut *conn, asset_id).await?;
+
+    let external_ids: Vec<String> = statement
+        .lines
+        .iter()
+        .filter_map(|(_, line)| line.as_ref().ok()?.external_id.clone())
+        .collect();
+    let imported: HashSet<String> = if external_ids.is_empty() {
+        HashSet::new()
+    } else {
+        sqlx::query_scalar(QUERY_SELECT_IMPORTED_EXTERNAL_IDS)
+            .bind(asset_id)
+            .bind(&external_ids)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .collect()
+    };
+
+    // Existing transactions in the statement period, bucketed by duplicate key
+    let dates = statement
+        .lines
+        .iter()
+        .filter_map(|(_, line)| line.as_ref().ok().map(|line| line.date));
+    let mut existing: HashMap<DuplicateKey, Vec<Option<String>>> = HashMap::new();
+    if let (Some(first), Some(last)) = (dates.clone().min(), dates.max()) {
+        let from = first.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let to = (last + Days::new(1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+
+        let rows = sqlx::query_as::<_, (NaiveDate, Decimal, i32, Option<String>, Option<String>)>(
+            QUERY_SELECT_EXISTING_LINES,
+        )
+        .bind(asset_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&mut *conn)
+        .await?;
+        for (date, amount, transaction_type, notes, external_id) in rows {
+            existing
+                .entry((date, amount, transaction_type, notes))
+                .or_default()
+                .push(external_id);
+        }
+    }
+
+    let mut rows = Vec::with_capacity(statement.lines.len());
+    let mut new_total = Decimal::ZERO;
+    for (line, parsed) in statement.lines {
+        let statement_line = match parsed {
+            Ok(statement_line) => statement_line,
+            Err(error) => {
+                rows.push(ImportRow {
+                    line,
+                    status: ImportRowStatus::Invalid,
+                    date: None,
+                    amount: None,
+                    transaction_type: None,
+                    notes: None,
+                    external_id: None,
+                    error: Some(error),
+                });
+                continue;
+            }
+        };
+
+        let bucket = existing.entry(duplicate_key(&statement_line)).or_default();
+        let matched = match &statement_line.external_id {
+            Some(external_id) if imported.contains(external_id) => {
+                bucket.retain(|id| id.as_ref() != Some(external_id));
+                true
+            }
+            external_id => {
+                let position = bucket
+                    .iter()
+                    .position(|id| external_id.is_none() || id.is_none());
+                position
+                    .map(|position| bucket.swap_remove(position))
+                    .is_some()
+            }
+        };
+
+        let status = if matched {
+            ImportRowStatus::Duplicate
+        } else {
+            new_total += signed_amount(&statement_line);
+            ImportRowStatus::New
+        };
+        rows.push(ImportRow {
+            line,
+            status,
+            date: Some(statement_line.date),
+            amount: Some(statement_line.amount),
+            transaction_type: Some(statement_line.transaction_type),
+            notes: statement_line.notes,
+            external_id: statement_line.external_id,
+            error: None,
+        });
+    }
+
+    let count = |status| rows.iter().filter(|row| row.status == status).count();
+    Ok(ImportPreview {
+        new_count: count(ImportRowStatus::New),
+        duplicate_count: count(ImportRowStatus::Duplicate),
+        invalid_count: count(ImportRowStatus::Invalid),
+        balance_check: statement
+            .balance
+            .map(|balance| balance_check(balance, asset.balance + new_total)),
+        rows,
+    })
+}
+
+/// Create a transaction against the asset for every new statement line and apply the
+/// balance effects and transaction rules through the same path as manually entered
+/// transactions.
+/// Duplicate and invalid lines are skipped; lines whose transaction fails validation are
+/// rolled back and reported as rejected.
+///
+/// The asset row stays locked until the surrounding database transaction ends, so two
+/// concurrent imports of the same statement can't both pass the duplicate check.
+pub async fn commit_statement_lines(
+    conn: &mut PgConnection,
+    asset_id: Uuid,
+    statement: ParsedStatement,
+) -> Result<ImportResult, sqlx::Error> {
+    let asset = lock_asset_by_id(conn, asset_id).await?;
+    let balance = statement.balance;
+    let preview = preview_statement_lines(conn, asset_id, statement).await?;
+
+    let mut transaction_ids = Vec::with_capacity(preview.new_count);
+    let mut rejected = Vec::new();
+    for row in preview.rows {
+        let (Some(date), Some(amount), Some(transaction_type)) =
+            (row.date, row.amount, row.transaction_type)
+        else {
+            continue;
+        };
+        if row.status != ImportRowStatus::New {
+            continue;
+        }
+
+        // Money into the asset is income, money out of it an expense
+        let (from_asset_id, to_asset_id, from_account_id, to_account_id) = match transaction_type {
+            TransactionType::Income => (None, Some(asset.id), None, Some(asset.account_id)),
+            _ => (Some(asset.id), None, Some(asset.account_id), None),
+        };
+
+        let payee_id = match row.notes.as_deref() {
+            Some(notes) => find_payee_by_alias(&mut *conn, asset.account_id, notes)
+                .await?
+                .map(|payee| payee.id),
+            None => None,
+        };
+
+        // Each line gets a savepoint, so a rejected one leaves no trace
+        let mut savepoint = conn.begin().await?;
+        let mut transaction = create_transaction(
+            &mut *savepoint,
+            from_asset_id,
+            to_asset_id,
+            transaction_type,
+            amount,
+            None,
+            from_account_id,
+            to_account_id,
+            Some(date.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+            row.notes.clone(),
+            None,
+            None,
+            None,
+            payee_id,
+        )
+        .await?;
+        match check_transaction(&mut savepoint, &transaction).await {
+            Ok(()) => {}
+            Err(TransactionError::Invalid(err)) => {
+                savepoint.rollback().await?;
+                rejected.push(ImportRow {
+                    status: ImportRowStatus::Rejected,
+                    error: Some(err.to_string()),
+                    ..row
+                });
+                continue;
+            }
+            Err(TransactionError::Database(err)) => return Err(err),
+        }
+        apply_transaction_balance(&mut savepoint, &transaction).await?;
+        apply_rules_to_transaction(&mut savepoint, &mut transaction).await?;
+
+        if let Some(external_id) = row.external_id {
+            sqlx::query(QUERY_INSERT_IMPORTED_LINE)
+                .bind(asset.id)
+                .bind(external_id)
+                .bind(transaction.id)
+                .execute(&mut *savepoint)
+                .await?;
+        }
+        savepoint.commit().await?;
+        transaction_ids.push(transaction.id);
+    }
+
+    let balance_check = match balance {
+        Some(balance) => {
+            let asset = get_asset_by_id(&mut *conn, asset_id).await?;
+            Some(balance_check(balance, asset.balance))
+        }
+        None => None,
+    };
+
+    Ok(ImportResult {
+        transaction_ids,
+        duplicate_count: preview.duplicate_count,
+        invalid_count: preview.invalid_count,
+        rejected,
+        balance_check,
+    })
+}
+
+/// Compare a statement's closing balance with the asset balance
+fn balance_check(balance: StatementBalance, asset_balance: Decimal) -> StatementBalanceCheck {
+    StatementBalanceCheck {
+        statement_balance: balance.amount,
+        as_of: balance.as_of,
+        asset_balance,
+        difference: asset_balance - balance.amount,
+    }
+}
+
+/// Effect of a statement line on the asset balance
+fn signed_amount(line: &StatementLine) -> Decimal {
+    match line.transaction_type {
+        TransactionType::Income => line.amount,
+        _ => -line.amount,
+    }
+}
+
+/// Duplicate-detection key of a statement line
+fn duplicate_key(line: &StatementLine) -> DuplicateKey {
+    (
+        line.date,
+        line.amount,
+        line.transaction_type as i32,
+        line.notes.clone(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
+    use std::env;
+    use uuid::Uuid;
+
+    use crate::core::import::csv_import::parse_csv_statement;
+    use crate::core::import::ofx_import::parse_ofx_statement;
+    use crate::models::SignConvention;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url =
+            env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env.test");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to test DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn insert_account_and_asset(pool: &PgPool) -> (Uuid, Uuid) {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, $2, now(), now())")
+            .bind(user_id)
+            .bind(Decimal::ZERO)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let asset_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO assets (id, account_id, asset_type, balance, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, now(), now())",
+        )
+        .bind(asset_id)
+        .bind(user_id)
+        .bind("bank")
+        .bind(Decimal::new(100000, 2))
+        .execute(pool)
+        .await
+        .unwrap();
+
+        (user_id, asset_id)
+    }
+
+    async fn import(pool: &PgPool, asset_id: Uuid, statement: ParsedStatement) -> ImportResult {
+        let mut db_tx = pool.begin().await.unwrap();
+        let result = commit_statement_lines(&mut db_tx, asset_id, statement)
+            .await
+            .unwrap();
+        db_tx.commit().await.unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn test_csv_import_skips_duplicates() {
+        let pool = setup_test_db().await;
+        let (account_id, asset_id) = insert_account_and_asset(&pool).await;
+
+        let profile = create_csv_import_profile(
+            &pool,
+            NewCsvImportProfile {
+                account_id,
+                name: "My bank".to_string(),
+                encoding: "UTF-8".to_string(),
+                delimiter: ",".to_string(),
+                has_header: true,
+                skip_rows: 0,
+                date_column: "Date".to_string(),
+                date_format: "%Y-%m-%d".to_string(),
+                description_column: Some("Description".to_string()),
+                sign_convention: SignConvention::NegativeIsExpense,
+                amount_column: Some("Amount".to_string()),
+                debit_column: None,
+                credit_column: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_csv_import_profiles_by_account_id(&pool, account_id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Two identical coffees on the same day are two real purchases
+        let june = "Date,Description,Amount\n\
+                    2025-06-29,Coffee,-50\n\
+                    2025-06-29,Coffee,-50\n\
+                    2025-06-30,Salary,3000\n";
+        let lines = parse_csv_statement(&profile, june.as_bytes()).unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        let preview = preview_statement_lines(&mut conn, asset_id, lines.clone())
+            .await
+            .unwrap();
+        assert_eq!(preview.new_count, 3);
+        assert!(preview.balance_check.is_none());
+
+        let result = import(&pool, asset_id, lines).await;
+        assert_eq!(result.transaction_ids.len(), 3);
+
+        // The next statement overlaps by one day and adds a third coffee
+        let july = "Date,Description,Amount\n\
+                    2025-06-29,Coffee,-50\n\
+                    2025-06-29,Coffee,-50\n\
+                    2025-06-29,Coffee,-50\n\
+                    2025-06-30,Salary,3000\n\
+                    2025-07-01,Rent,-1500\n\
+                    not a date,Broken,-1\n";
+        let lines = parse_csv_statement(&profile, july.as_bytes()).unwrap();
+        let result = import(&pool, asset_id, lines).await;
+        assert_eq!(result.transaction_ids.len(), 2);
+        assert_eq!(result.duplicate_count, 3);
+        assert_eq!(result.invalid_count, 1);
+
+        // 1000 - 3 x 50 + 3000 - 1500
+        let balance: Decimal = sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(balance, Decimal::new(235000, 2));
+
+        let time: chrono::DateTime<Utc> =
+            sqlx::query_scalar("SELECT transaction_time FROM transactions WHERE id = $1")
+                .bind(result.transaction_ids[1])
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            time.date_naive(),
+            NaiveDate::from_ymd_opt(2025, 7, 1).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ofx_reimport_is_idempotent() {
+        let pool = setup_test_db().await;
+        let (_, asset_id) = insert_account_and_asset(&pool).await;
+
+        let ofx = "OFXHEADER:100\nDATA:OFXSGML\nVERSION:102\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS>\
+                   <BANKTRANLIST>\
+                   <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20250702<TRNAMT>-50.00<FITID>A1<NAME>Coffee</STMTTRN>\
+                   <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20250702<TRNAMT>-50.00<FITID>A2<NAME>Coffee</STMTTRN>\
+                   <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20250705<TRNAMT>200.00<FITID>A3<NAME>Refund</STMTTRN>\
+                   </BANKTRANLIST>\
+                   <LEDGERBAL><BALAMT>1100.00<DTASOF>20250731</LEDGERBAL>\
+                   </STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let statement = parse_ofx_statement(ofx.as_bytes()).unwrap();
+
+        // 1000 - 2 x 50 + 200 is what the bank reports
+        let mut conn = pool.acquire().await.unwrap();
+        let preview = preview_statement_lines(&mut conn, asset_id, statement.clone())
+            .await
+            .unwrap();
+        assert_eq!(preview.new_count, 3);
+        let check = preview.balance_check.unwrap();
+        assert_eq!(check.asset_balance, Decimal::new(110000, 2));
+        assert!(check.difference.is_zero());
+        drop(conn);
+
+        let result = import(&pool, asset_id, statement.clone()).await;
+        assert_eq!(result.transaction_ids.len(), 3);
+        assert!(result.balance_check.unwrap().difference.is_zero());
+
+        // Importing the same file again changes nothing
+        let result = import(&pool, asset_id, statement).await;
+        assert!(result.transaction_ids.is_empty());
+        assert_eq!(result.duplicate_count, 3);
+        assert!(result.balance_check.unwrap().difference.is_zero());
+
+        let balance: Decimal = sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(balance, Decimal::new(110000, 2));
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_invalid_transactions() {
+        let pool = setup_test_db().await;
+        let (_, asset_id) = insert_account_and_asset(&pool).await;
+
+        let line = |amount| StatementLine {
+            date: NaiveDate::from_ymd_opt(2025, 7, 3).unwrap(),
+            amount: Decimal::new(amount, 2),
+            transaction_type: TransactionType::Expense,
+            notes: Some("Card fee".to_string()),
+            external_id: None,
+        };
+        // A zero amount can't be read from a file, but breaks the rules of every type
+        let statement = ParsedStatement {
+            lines: vec![(1, Ok(line(0))), (2, Ok(line(2500)))],
+            balance: None,
+        };
+
+        let result = import(&pool, asset_id, statement).await;
+        assert_eq!(result.transaction_ids.len(), 1);
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].line, 1);
+        assert_eq!(result.rejected[0].status, ImportRowStatus::Rejected);
+        assert!(result.rejected[0].error.is_some());
+
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM transactions WHERE from_asset_id = $1 OR to_asset_id = $1",
+        )
+        .bind(asset_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+        let balance: Decimal = sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(balance, Decimal::new(97500, 2));
+    }
+}
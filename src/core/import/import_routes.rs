@@ -0,0 +1,44 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::import::import_handler::*, models::Backend};
+
+/// Defines routes for importing bank statements
+pub fn import_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // POST /imports/csv/profiles -> Save a column mapping profile
+        .route(
+            "/imports/csv/profiles",
+            post(add_csv_import_profile_handler),
+        )
+        // GET    /imports/csv/profiles/{id} -> Fetch one profile
+        // DELETE /imports/csv/profiles/{id} -> Delete a profile
+        .route(
+            "/imports/csv/profiles/{id}",
+            get(get_csv_import_profile_handler).delete(delete_csv_import_profile_handler),
+        )
+        // GET /imports/csv/profiles/account/{id} -> Profiles of an account
+        .route(
+            "/imports/csv/profiles/account/{id}",
+            get(get_csv_import_profiles_handler),
+        )
+        // POST /imports/csv/preview?profile_id=&asset_id= (CSV file as body)
+        // -> Parse and classify lines without writing anything
+        .route("/imports/csv/preview", post(preview_csv_import_handler))
+        // POST /imports/csv/commit?profile_id=&asset_id= (CSV file as body)
+        // -> Create transactions for the new lines
+        .route("/imports/csv/commit", post(commit_csv_import_handler))
+        // POST /imports/ofx/preview?asset_id= (OFX/QFX file as body)
+        // -> Classify transactions and compare the ledger balance, without writing anything
+        .route("/imports/ofx/preview", post(preview_ofx_import_handler))
+        // POST /imports/ofx/commit?asset_id= (OFX/QFX file as body)
+        // -> Create transactions for FITIDs not imported yet
+        .route("/imports/ofx/commit", post(commit_ofx_import_handler))
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
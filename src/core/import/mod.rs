@@ -0,0 +1,6 @@
+pub mod csv_import;
+pub mod import;
+pub mod import_handler;
+pub mod import_repository;
+pub mod import_routes;
+pub mod ofx_import;
@@ -0,0 +1,295 @@
+use chrono::NaiveDate;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::models::{ParsedStatement, StatementBalance, StatementLine, TransactionType};
+
+/// One piece of an OFX document
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    Text(&'a str),
+}
+
+/// Parse an OFX or QFX statement, either 1.x (SGML, leaf elements left unclosed)
+/// or 2.x (XML).
+///
+/// Every `STMTTRN` record becomes a statement line keyed by its `FITID`;
+/// `LEDGERBAL` becomes the statement balance.
+pub fn parse_ofx_statement(bytes: &[u8]) -> Result<ParsedStatement, String> {
+    let text = decode(bytes)?;
+    let body = text
+        .find("<OFX>")
+        .map(|start| &text[start..])
+        .ok_or("not an OFX document")?;
+
+    let mut lines = Vec::new();
+    let mut balance = None;
+
+    // Leaf values of the aggregate being read, if it is one we care about
+    let mut record: Option<(&str, HashMap<&str, String>)> = None;
+    let mut open_leaf: Option<&str> = None;
+
+    for token in tokenize(body) {
+        match token {
+            Token::Open(name) => {
+                if name == "STMTTRN" || name == "LEDGERBAL" {
+                    record = Some((name, HashMap::new()));
+                }
+                open_leaf = Some(name);
+            }
+            Token::Text(value) => {
+                if let (Some(leaf), Some((_, fields))) = (open_leaf, record.as_mut()) {
+                    fields.insert(leaf, unescape(value));
+                }
+                open_leaf = None;
+            }
+            Token::Close(name) => {
+                open_leaf = None;
+                match record.take() {
+                    Some((aggregate, fields)) if aggregate == name => {
+                        if name == "STMTTRN" {
+                            lines.push((lines.len() as u64 + 1, parse_transaction(&fields)));
+                        } else {
+                            balance = Some(parse_balance(&fields)?);
+                        }
+                    }
+                    other => record = other,
+                }
+            }
+        }
+    }
+
+    Ok(ParsedStatement { lines, balance })
+}
+
+/// Decode the document using the charset announced by its header
+fn decode(bytes: &[u8]) -> Result<String, String> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]).to_ascii_uppercase();
+
+    let encoding = if head.contains("CHARSET:1252") {
+        WINDOWS_1252
+    } else if let Some(label) = xml_encoding_label(&head) {
+        Encoding::for_label(label.as_bytes()).unwrap_or(UTF_8)
+    } else {
+        UTF_8
+    };
+
+    let (text, _, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(format!("file is not valid {}", encoding.name()));
+    }
+    Ok(text.into_owned())
+}
+
+/// The `encoding="..."` label of an XML declaration, if any
+fn xml_encoding_label(head: &str) -> Option<&str> {
+    let start = head.find("ENCODING=\"")? + "ENCODING=\"".len();
+    let end = head[start..].find('"')?;
+    Some(&head[start..start + end])
+}
+
+/// Split the document into tags and the text between them
+fn tokenize(body: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find('<') {
+        let text = rest[..start].trim();
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim();
+        rest = &rest[start + end + 1..];
+
+        // Skip processing instructions, comments and self-closing tags
+        if tag.starts_with('?') || tag.starts_with('!') || tag.ends_with('/') {
+            continue;
+        }
+        match tag.strip_prefix('/') {
+            Some(name) => tokens.push(Token::Close(name.trim())),
+            None => tokens.push(Token::Open(tag.split_whitespace().next().unwrap_or(tag))),
+        }
+    }
+
+    let text = rest.trim();
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+/// Turn the fields of one `STMTTRN` into a statement line
+fn parse_transaction(fields: &HashMap<&str, String>) -> Result<StatementLine, String> {
+    let date = parse_ofx_date(fields.get("DTPOSTED").ok_or("missing DTPOSTED")?)?;
+    let amount = parse_ofx_amount(fields.get("TRNAMT").ok_or("missing TRNAMT")?)?;
+    if amount.is_zero() {
+        return Err("amount is zero".to_string());
+    }
+
+    // Signed from the account holder's side: credits are positive, debits negative
+    let transaction_type = if amount > Decimal::ZERO {
+        TransactionType::Income
+    } else {
+        TransactionType::Expense
+    };
+
+    let name = fields.get("NAME").filter(|name| !name.is_empty());
+    let memo = fields.get("MEMO").filter(|memo| !memo.is_empty());
+    let notes = match (name, memo) {
+        (Some(name), Some(memo)) if name != memo => Some(format!("{} - {}", name, memo)),
+        (Some(text), _) | (None, Some(text)) => Some(text.clone()),
+        (None, None) => None,
+    };
+
+    Ok(StatementLine {
+        date,
+        amount: amount.abs(),
+        transaction_type,
+        notes,
+        external_id: fields.get("FITID").filter(|id| !id.is_empty()).cloned(),
+    })
+}
+
+/// Read `LEDGERBAL`
+fn parse_balance(fields: &HashMap<&str, String>) -> Result<StatementBalance, String> {
+    Ok(StatementBalance {
+        amount: parse_ofx_amount(fields.get("BALAMT").ok_or("LEDGERBAL without BALAMT")?)?,
+        as_of: parse_ofx_date(fields.get("DTASOF").ok_or("LEDGERBAL without DTASOF")?)?,
+    })
+}
+
+/// Parse an OFX datetime (`YYYYMMDD[HHMMSS[.XXX]][[offset:TZ]]`) down to its date
+fn parse_ofx_date(value: &str) -> Result<NaiveDate, String> {
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .ok_or_else(|| format!("invalid date {:?}", value))
+}
+
+/// Parse an OFX amount; some banks use a decimal comma
+fn parse_ofx_amount(value: &str) -> Result<Decimal, String> {
+    Decimal::from_str(&value.trim().replace(',', "."))
+        .map_err(|_| format!("invalid amount {:?}", value))
+}
+
+/// Resolve the entities SGML and XML exports use in text
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+ENCODING:USASCII
+CHARSET:1252
+
+<OFX>
+<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS></SONRS></SIGNONMSGSRSV1>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>USD
+<BANKTRANLIST>
+<DTSTART>20250701<DTEND>20250731
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20250703120000[-5:EST]
+<TRNAMT>-42.10
+<FITID>2025070301
+<NAME>Corner Caf&amp;eacute;
+<MEMO>Card purchase
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20250715
+<TRNAMT>2500.00
+<FITID>2025071501
+<NAME>PAYROLL
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>2025
+<TRNAMT>-1.00
+<FITID>broken
+</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>3457.90<DTASOF>20250731</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>
+<OFX>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20250702000000.000[+8:CST]</DTPOSTED>
+        <TRNAMT>-350.00</TRNAMT>
+        <FITID>CC-0001</FITID>
+        <NAME>全聯福利中心</NAME>
+      </STMTTRN>
+    </BANKTRANLIST>
+    <LEDGERBAL><BALAMT>-350.00</BALAMT><DTASOF>20250731</DTASOF></LEDGERBAL>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>
+"#;
+
+    #[test]
+    fn test_parse_sgml_statement() {
+        let statement = parse_ofx_statement(SGML.as_bytes()).unwrap();
+        assert_eq!(statement.lines.len(), 3);
+
+        let coffee = statement.lines[0].1.as_ref().unwrap();
+        assert_eq!(coffee.date, NaiveDate::from_ymd_opt(2025, 7, 3).unwrap());
+        assert_eq!(coffee.amount, Decimal::new(4210, 2));
+        assert_eq!(coffee.transaction_type, TransactionType::Expense);
+        assert_eq!(coffee.external_id.as_deref(), Some("2025070301"));
+        assert_eq!(
+            coffee.notes.as_deref(),
+            Some("Corner Caf&eacute; - Card purchase")
+        );
+
+        let salary = statement.lines[1].1.as_ref().unwrap();
+        assert_eq!(salary.transaction_type, TransactionType::Income);
+        assert_eq!(salary.notes.as_deref(), Some("PAYROLL"));
+
+        assert!(statement.lines[2].1.is_err());
+
+        let balance = statement.balance.unwrap();
+        assert_eq!(balance.amount, Decimal::new(345790, 2));
+        assert_eq!(balance.as_of, NaiveDate::from_ymd_opt(2025, 7, 31).unwrap());
+    }
+
+    #[test]
+    fn test_parse_xml_statement() {
+        let statement = parse_ofx_statement(XML.as_bytes()).unwrap();
+        assert_eq!(statement.lines.len(), 1);
+
+        let line = statement.lines[0].1.as_ref().unwrap();
+        assert_eq!(line.amount, Decimal::new(35000, 2));
+        assert_eq!(line.transaction_type, TransactionType::Expense);
+        assert_eq!(line.notes.as_deref(), Some("全聯福利中心"));
+        assert_eq!(line.external_id.as_deref(), Some("CC-0001"));
+        assert_eq!(statement.balance.unwrap().amount, Decimal::new(-35000, 2));
+
+        assert!(parse_ofx_statement(b"Date,Amount\n").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Ledger a posting is booked against
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum LedgerKind {
+    /// A concrete asset (cash, bank, ...); postings carry the `asset_id`
+    Asset,
+    /// Money arriving from outside the books
+    Income,
+    /// Money leaving the books
+    Expense,
+    /// Fees charged on transactions
+    Fee,
+    /// Opening balances, manual adjustments, transfers between accounts and currency exchange
+    Equity,
+}
+
+/// A balanced journal entry, optionally produced by a `Transaction`
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct JournalEntry {
+    /// Unique entry ID
+    pub id: Uuid,
+
+    /// Transaction that produced this entry (None for opening balances and adjustments)
+    pub transaction_id: Option<Uuid>,
+
+    /// Free-text description shown in the general ledger
+    pub description: Option<String>,
+
+    /// Accounting date of the entry
+    pub entry_time: DateTime<Utc>,
+
+    /// When the entry was written
+    pub created_at: DateTime<Utc>,
+}
+
+/// A single debit (positive) or credit (negative) line, before it is written to the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPosting {
+    /// Account whose books this posting belongs to
+    pub account_id: Uuid,
+
+    pub ledger: LedgerKind,
+
+    /// Set only for `LedgerKind::Asset` postings
+    pub asset_id: Option<Uuid>,
+
+    /// Signed amount: debit > 0, credit < 0
+    pub amount: Decimal,
+}
+
+/// One line of a trial balance: the net balance of a single ledger
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrialBalanceRow {
+    pub ledger: LedgerKind,
+    pub asset_id: Option<Uuid>,
+
+    /// Asset type of the asset ledger (e.g., "cash", "bank")
+    pub asset_type: Option<String>,
+
+    pub debit: Decimal,
+    pub credit: Decimal,
+}
+
+/// Trial balance of an account; `total_debit` equals `total_credit` when the books balance
+#[derive(Debug, Serialize)]
+pub struct TrialBalance {
+    pub account_id: Uuid,
+    pub as_of: DateTime<Utc>,
+    pub rows: Vec<TrialBalanceRow>,
+    pub total_debit: Decimal,
+    pub total_credit: Decimal,
+}
+
+/// Allows `TrialBalance` to be returned as a JSON response
+impl IntoResponse for TrialBalance {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// One posting in the general ledger view, with the ledger's running balance
+#[derive(Debug, Serialize, FromRow)]
+pub struct LedgerLine {
+    pub entry_id: Uuid,
+    pub transaction_id: Option<Uuid>,
+    pub entry_time: DateTime<Utc>,
+    pub description: Option<String>,
+    pub amount: Decimal,
+    pub running_balance: Decimal,
+}
+
+/// Wrapper for returning a general ledger
+#[derive(Debug, Serialize)]
+pub struct LedgerLineList(pub Vec<LedgerLine>);
+
+/// Enables `LedgerLineList` to be returned as a JSON response
+impl IntoResponse for LedgerLineList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Stored `assets.balance` compared with the balance derived from the journal
+#[derive(Debug, Serialize, FromRow)]
+pub struct AssetBalanceCheck {
+    pub asset_id: Uuid,
+    pub asset_type: String,
+    pub stored_balance: Decimal,
+    pub journal_balance: Decimal,
+
+    /// `stored_balance - journal_balance`; anything but zero means the two disagree
+    pub difference: Decimal,
+}
+
+/// Wrapper for returning balance checks of all assets in an account
+#[derive(Debug, Serialize)]
+pub struct AssetBalanceCheckList(pub Vec<AssetBalanceCheck>);
+
+/// Enables `AssetBalanceCheckList` to be returned as a JSON response
+impl IntoResponse for AssetBalanceCheckList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
@@ -0,0 +1,100 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{AssetBalanceCheckList, LedgerKind, LedgerLineList, TrialBalance};
+use crate::repository::{get_asset_balance_checks, get_general_ledger, get_trial_balance};
+
+/// Query parameters for the trial balance
+#[derive(Deserialize)]
+pub struct TrialBalanceQuery {
+    /// Only include entries up to this time (defaults to now)
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+/// Query parameters selecting one ledger for the general ledger view
+#[derive(Deserialize)]
+pub struct GeneralLedgerQuery {
+    pub ledger: LedgerKind,
+
+    /// Required when `ledger` is `Asset`
+    pub asset_id: Option<Uuid>,
+}
+
+/// Handler: Get the trial balance of an account
+pub async fn get_trial_balance_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<TrialBalanceQuery>,
+) -> impl IntoResponse {
+    let as_of = query.as_of.unwrap_or_else(Utc::now);
+
+    match get_trial_balance(&pool, account_id, as_of).await {
+        Ok(rows) => {
+            let total_debit = rows.iter().map(|row| row.debit).sum();
+            let total_credit = rows.iter().map(|row| row.credit).sum();
+            TrialBalance {
+                account_id,
+                as_of,
+                rows,
+                total_debit,
+                total_credit,
+            }
+            .into_response()
+        }
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch trial balance for account {}: {:?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Get the general ledger of one ledger of an account
+pub async fn get_general_ledger_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<GeneralLedgerQuery>,
+) -> impl IntoResponse {
+    // Asset ledgers are per asset; the others never carry an asset ID
+    if (query.ledger == LedgerKind::Asset) != query.asset_id.is_some() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    match get_general_ledger(&pool, account_id, query.ledger, query.asset_id).await {
+        Ok(lines) => LedgerLineList(lines).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch general ledger for account {}: {:?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Compare stored asset balances of an account with the journal
+pub async fn get_asset_balance_check_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_asset_balance_checks(&pool, account_id).await {
+        Ok(checks) => AssetBalanceCheckList(checks).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to check asset balances for account {}: {:?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
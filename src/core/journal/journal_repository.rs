@@ -0,0 +1,580 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::models::{
+    AssetBalanceCheck, JournalEntry, LedgerKind, LedgerLine, NewPosting, Transaction,
+    TrialBalanceRow,
+};
+
+// SQL insert query for the entry header; postings are inserted in bulk afterwards
+const QUERY_INSERT_ENTRY: &str = "
+    INSERT INTO journal_entries (id, transaction_id, description, entry_time, created_at)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING *
+";
+
+// SQL query to resolve which account an asset belongs to
+const QUERY_SELECT_ASSET_ACCOUNT_ID: &str = "SELECT account_id FROM assets WHERE id = $1";
+
+// SQL query for the net balance of every ledger of an account up to a point in time
+const QUERY_TRIAL_BALANCE: &str = "
+    SELECT
+        p.ledger,
+        p.asset_id,
+        a.asset_type,
+        GREATEST(SUM(p.amount), 0) AS debit,
+        GREATEST(-SUM(p.amount), 0) AS credit
+    FROM journal_postings p
+    JOIN journal_entries e ON e.id = p.entry_id
+    LEFT JOIN assets a ON a.id = p.asset_id
+    WHERE p.account_id = $1 AND e.entry_time <= $2
+    GROUP BY p.ledger, p.asset_id, a.asset_type
+    HAVING SUM(p.amount) <> 0
+    ORDER BY p.ledger, a.asset_type
+";
+
+// SQL query for every posting of one ledger with its running balance
+const QUERY_GENERAL_LEDGER: &str = "
+    SELECT
+        e.id AS entry_id,
+        e.transaction_id,
+        e.entry_time,
+        e.description,
+        p.amount,
+        SUM(p.amount) OVER (ORDER BY e.entry_time, e.created_at, p.id) AS running_balance
+    FROM journal_postings p
+    JOIN journal_entries e ON e.id = p.entry_id
+    WHERE p.account_id = $1 AND p.ledger = $2 AND p.asset_id IS NOT DISTINCT FROM $3
+    ORDER BY e.entry_time, e.created_at, p.id
+";
+
+// SQL query comparing stored asset balances with the journal
+const QUERY_ASSET_BALANCE_CHECK: &str = "
+    SELECT
+        a.id AS asset_id,
+        a.asset_type,
+        a.balance AS stored_balance,
+        COALESCE(SUM(p.amount), 0) AS journal_balance,
+        a.balance - COALESCE(SUM(p.amount), 0) AS difference
+    FROM assets a
+    LEFT JOIN journal_postings p ON p.asset_id = a.id
+    WHERE a.account_id = $1
+    GROUP BY a.id, a.asset_type, a.balance
+    ORDER BY a.asset_type
+";
+
+/// Write a journal entry and its postings.
+///
+/// The database refuses to commit an entry whose postings don't sum to zero per account.
+pub async fn create_journal_entry(
+    conn: &mut PgConnection,
+    transaction_id: Option<Uuid>,
+    description: Option<String>,
+    entry_time: DateTime<Utc>,
+    postings: &[NewPosting],
+) -> Result<JournalEntry, sqlx::Error> {
+    let entry = sqlx::query_as::<_, JournalEntry>(QUERY_INSERT_ENTRY)
+        .bind(Uuid::new_v4())
+        .bind(transaction_id)
+        .bind(description)
+        .bind(entry_time)
+        .bind(Utc::now())
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if postings.is_empty() {
+        return Ok(entry);
+    }
+
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "INSERT INTO journal_postings (id, entry_id, account_id, ledger, asset_id, amount) ",
+    );
+    builder.push_values(postings, |mut b, posting| {
+        b.push_bind(Uuid::new_v4())
+            .push_bind(entry.id)
+            .push_bind(posting.account_id)
+            .push_bind(posting.ledger)
+            .push_bind(posting.asset_id)
+            .push_bind(posting.amount);
+    });
+    builder.build().execute(&mut *conn).await?;
+
+    Ok(entry)
+}
+
+/// Journal a transaction's balance effect.
+/// `direction` is `1` when the transaction is applied and `-1` when it is reverted.
+pub async fn post_transaction_entry(
+    conn: &mut PgConnection,
+    transaction: &Transaction,
+    direction: Decimal,
+) -> Result<(), sqlx::Error> {
+    let from_account_id = match transaction.from_asset_id {
+        Some(asset_id) => Some(get_asset_account_id(conn, asset_id).await?),
+        None => None,
+    };
+    let to_account_id = match transaction.to_asset_id {
+        Some(asset_id) => Some(get_asset_account_id(conn, asset_id).await?),
+        None => None,
+    };
+
+    let postings: Vec<NewPosting> =
+        transaction_postings(transaction, from_account_id, to_account_id)
+            .into_iter()
+            .map(|posting| NewPosting {
+                amount: posting.amount * direction,
+                ..posting
+            })
+            .collect();
+
+    if postings.is_empty() {
+        return Ok(());
+    }
+
+    let (description, entry_time) = if direction.is_sign_negative() {
+        (Some("Reversal".to_string()), Utc::now())
+    } else {
+        (
+            transaction.notes.clone(),
+            transaction
+                .transaction_time
+                .unwrap_or(transaction.created_at),
+        )
+    };
+
+    create_journal_entry(
+        conn,
+        Some(transaction.id),
+        description,
+        entry_time,
+        &postings,
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Journal a change to an asset balance that doesn't come from a transaction
+/// (opening balance or manual correction); the other side is booked to equity
+pub async fn post_asset_adjustment(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    asset_id: Uuid,
+    amount: Decimal,
+    description: &str,
+) -> Result<(), sqlx::Error> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+
+    let postings = [
+        NewPosting {
+            account_id,
+            ledger: LedgerKind::Asset,
+            asset_id: Some(asset_id),
+            amount,
+        },
+        NewPosting {
+            account_id,
+            ledger: LedgerKind::Equity,
+            asset_id: None,
+            amount: -amount,
+        },
+    ];
+
+    create_journal_entry(
+        conn,
+        None,
+        Some(description.to_string()),
+        Utc::now(),
+        &postings,
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Build the balanced postings for a transaction, mirroring how it moves asset balances:
+/// - `to_asset` is debited with the received amount, in its own currency
+/// - `from_asset` is credited with the amount plus fee, and the fee is debited to the fee ledger
+/// - a one-sided transaction is balanced against income or expense
+/// - a transfer between two accounts is balanced through equity in each account
+/// - a currency exchange within one account is balanced through equity
+pub fn transaction_postings(
+    transaction: &Transaction,
+    from_account_id: Option<Uuid>,
+    to_account_id: Option<Uuid>,
+) -> Vec<NewPosting> {
+    let mut postings = Vec::new();
+    let amount = transaction.amount;
+    let received = transaction.received_amount();
+    let fee = transaction.fee;
+
+    let from = transaction.from_asset_id.zip(from_account_id);
+    let to = transaction.to_asset_id.zip(to_account_id);
+
+    if let Some((asset_id, account_id)) = to {
+        postings.push(NewPosting {
+            account_id,
+            ledger: LedgerKind::Asset,
+            asset_id: Some(asset_id),
+            amount: received,
+        });
+    }
+
+    if let Some((asset_id, account_id)) = from {
+        postings.push(NewPosting {
+            account_id,
+            ledger: LedgerKind::Asset,
+            asset_id: Some(asset_id),
+            amount: -(amount + fee),
+        });
+        if !fee.is_zero() {
+            postings.push(NewPosting {
+                account_id,
+                ledger: LedgerKind::Fee,
+                asset_id: None,
+                amount: fee,
+            });
+        }
+    }
+
+    match (from, to) {
+        (Some((_, from_account_id)), Some((_, to_account_id))) => {
+            if from_account_id != to_account_id {
+                postings.push(NewPosting {
+                    account_id: from_account_id,
+                    ledger: LedgerKind::Equity,
+                    asset_id: None,
+                    amount,
+                });
+                postings.push(NewPosting {
+                    account_id: to_account_id,
+                    ledger: LedgerKind::Equity,
+                    asset_id: None,
+                    amount: -received,
+                });
+            } else if received != amount {
+                postings.push(NewPosting {
+                    account_id: from_account_id,
+                    ledger: LedgerKind::Equity,
+                    asset_id: None,
+                    amount: amount - received,
+                });
+            }
+        }
+        (None, Some((_, to_account_id))) => postings.push(NewPosting {
+            account_id: to_account_id,
+            ledger: LedgerKind::Income,
+            asset_id: None,
+            amount: -received,
+        }),
+        (Some((_, from_account_id)), None) => postings.push(NewPosting {
+            account_id: from_account_id,
+            ledger: LedgerKind::Expense,
+            asset_id: None,
+            amount,
+        }),
+        (None, None) => {}
+    }
+
+    postings
+}
+
+/// Get the net balance of every ledger of an account as of the given time
+pub async fn get_trial_balance(
+    pool: &PgPool,
+    account_id: Uuid,
+    as_of: DateTime<Utc>,
+) -> Result<Vec<TrialBalanceRow>, sqlx::Error> {
+    sqlx::query_as::<_, TrialBalanceRow>(QUERY_TRIAL_BALANCE)
+        .bind(account_id)
+        .bind(as_of)
+        .fetch_all(pool)
+        .await
+}
+
+/// Get every posting of one ledger of an account, oldest first, with a running balance
+pub async fn get_general_ledger(
+    pool: &PgPool,
+    account_id: Uuid,
+    ledger: LedgerKind,
+    asset_id: Option<Uuid>,
+) -> Result<Vec<LedgerLine>, sqlx::Error> {
+    sqlx::query_as::<_, LedgerLine>(QUERY_GENERAL_LEDGER)
+        .bind(account_id)
+        .bind(ledger)
+        .bind(asset_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Compare each asset's stored balance with the balance derived from its postings
+pub async fn get_asset_balance_checks(
+    pool: &PgPool,
+    account_id: Uuid,
+) -> Result<Vec<AssetBalanceCheck>, sqlx::Error> {
+    sqlx::query_as::<_, AssetBalanceCheck>(QUERY_ASSET_BALANCE_CHECK)
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Resolve the account an asset belongs to
+async fn get_asset_account_id(
+    conn: &mut PgConnection,
+    asset_id: Uuid,
+) -> Result<Uuid, sqlx::Error> {
+    let row = sqlx::query(QUERY_SELECT_ASSET_ACCOUNT_ID)
+        .bind(asset_id)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(row.get("account_id"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionType;
+    use crate::repository::{
+        apply_transaction_balance, create_transaction, revert_transaction_balance,
+    };
+    use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
+    use std::env;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Migrations failed");
+
+        pool
+    }
+
+    async fn insert_user_and_account(pool: &PgPool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, 0, now(), now())")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        user_id
+    }
+
+    async fn insert_asset(pool: &PgPool, account_id: Uuid, asset_type: &str) -> Uuid {
+        let asset = crate::repository::create_asset(
+            pool,
+            account_id,
+            asset_type.to_string(),
+            Decimal::new(5000, 2),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        post_asset_adjustment(
+            &mut conn,
+            account_id,
+            asset.id,
+            asset.balance,
+            "Opening balance",
+        )
+        .await
+        .unwrap();
+
+        asset.id
+    }
+
+    fn sum(postings: &[NewPosting]) -> Decimal {
+        postings.iter().map(|posting| posting.amount).sum()
+    }
+
+    #[test]
+    fn test_transaction_postings_balance() {
+        let account_a = Uuid::new_v4();
+        let account_b = Uuid::new_v4();
+
+        let mut tx = Transaction {
+            from_asset_id: Some(Uuid::new_v4()),
+            to_asset_id: Some(Uuid::new_v4()),
+            amount: Decimal::new(1000, 2),
+            fee: Decimal::new(50, 2),
+            ..Transaction::default()
+        };
+
+        // Internal transfer with fee: no income/expense, fee booked to the fee ledger
+        let postings = transaction_postings(&tx, Some(account_a), Some(account_a));
+        assert_eq!(postings.len(), 3);
+        assert_eq!(sum(&postings), Decimal::ZERO);
+        assert!(postings
+            .iter()
+            .any(|p| p.ledger == LedgerKind::Fee && p.amount == Decimal::new(50, 2)));
+
+        // Transfer between accounts balances within each account through equity
+        let postings = transaction_postings(&tx, Some(account_a), Some(account_b));
+        for account_id in [account_a, account_b] {
+            let own: Vec<NewPosting> = postings
+                .iter()
+                .filter(|p| p.account_id == account_id)
+                .cloned()
+                .collect();
+            assert_eq!(sum(&own), Decimal::ZERO);
+        }
+
+        // Income: destination asset against income
+        tx.from_asset_id = None;
+        tx.fee = Decimal::ZERO;
+        let postings = transaction_postings(&tx, None, Some(account_a));
+        assert_eq!(postings.len(), 2);
+        assert_eq!(postings[1].ledger, LedgerKind::Income);
+        assert_eq!(sum(&postings), Decimal::ZERO);
+
+        // Expense: source asset against expense
+        tx.from_asset_id = tx.to_asset_id.take();
+        let postings = transaction_postings(&tx, Some(account_a), None);
+        assert_eq!(postings[1].ledger, LedgerKind::Expense);
+        assert_eq!(sum(&postings), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_transaction_postings_across_currencies() {
+        let account_a = Uuid::new_v4();
+        let account_b = Uuid::new_v4();
+
+        // 1000 TWD exchanged into 32 USD
+        let tx = Transaction {
+            from_asset_id: Some(Uuid::new_v4()),
+            to_asset_id: Some(Uuid::new_v4()),
+            amount: Decimal::new(1000, 0),
+            to_currency_code: "USD".to_string(),
+            exchange_rate: Some(Decimal::new(32, 3)),
+            to_amount: Some(Decimal::new(32, 0)),
+            ..Transaction::default()
+        };
+
+        // Within one account the exchange difference is booked to equity
+        let postings = transaction_postings(&tx, Some(account_a), Some(account_a));
+        assert_eq!(postings[0].amount, Decimal::new(32, 0));
+        assert!(postings
+            .iter()
+            .any(|p| p.ledger == LedgerKind::Equity && p.amount == Decimal::new(968, 0)));
+        assert_eq!(sum(&postings), Decimal::ZERO);
+
+        // Between accounts each side balances in its own currency
+        let postings = transaction_postings(&tx, Some(account_a), Some(account_b));
+        for account_id in [account_a, account_b] {
+            let own: Vec<NewPosting> = postings
+                .iter()
+                .filter(|p| p.account_id == account_id)
+                .cloned()
+                .collect();
+            assert_eq!(sum(&own), Decimal::ZERO);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_journal_matches_asset_balances() {
+        let pool = setup_test_db().await;
+
+        let account_id = insert_user_and_account(&pool).await;
+        let cash_id = insert_asset(&pool, account_id, "cash").await;
+        let bank_id = insert_asset(&pool, account_id, "bank").await;
+
+        let mut db_tx = pool.begin().await.unwrap();
+        let tx = create_transaction(
+            &mut *db_tx,
+            Some(cash_id),
+            Some(bank_id),
+            TransactionType::InternalTransfer,
+            Decimal::new(2000, 2),
+            Some(Decimal::new(100, 2)),
+            Some(account_id),
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        db_tx.commit().await.unwrap();
+
+        let checks = get_asset_balance_checks(&pool, account_id).await.unwrap();
+        assert_eq!(checks.len(), 2);
+        assert!(checks.iter().all(|c| c.difference.is_zero()));
+
+        let rows = get_trial_balance(&pool, account_id, Utc::now())
+            .await
+            .unwrap();
+        let debit: Decimal = rows.iter().map(|row| row.debit).sum();
+        let credit: Decimal = rows.iter().map(|row| row.credit).sum();
+        assert_eq!(debit, credit);
+        assert!(rows
+            .iter()
+            .any(|row| row.ledger == LedgerKind::Fee && row.debit == Decimal::new(100, 2)));
+
+        // Reverting writes a reversing entry rather than deleting history
+        let mut conn = pool.acquire().await.unwrap();
+        revert_transaction_balance(&mut conn, &tx).await.unwrap();
+
+        let lines = get_general_ledger(&pool, account_id, LedgerKind::Asset, Some(cash_id))
+            .await
+            .unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines.last().unwrap().running_balance, Decimal::new(5000, 2));
+
+        let checks = get_asset_balance_checks(&pool, account_id).await.unwrap();
+        assert!(checks.iter().all(|c| c.difference.is_zero()));
+
+        // Unbalanced entries are rejected at commit
+        let mut db_tx = pool.begin().await.unwrap();
+        create_journal_entry(
+            &mut db_tx,
+            None,
+            None,
+            Utc::now(),
+            &[NewPosting {
+                account_id,
+                ledger: LedgerKind::Equity,
+                asset_id: None,
+                amount: Decimal::ONE,
+            }],
+        )
+        .await
+        .unwrap();
+        assert!(db_tx.commit().await.is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use axum::{routing::get, Router};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::journal::journal_handler::*, models::Backend};
+
+/// Defines read-only routes over the double-entry journal
+pub fn journal_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // GET /journal/account/{id}/trial-balance?as_of=
+        // -> Net balance of every ledger of the account
+        .route(
+            "/journal/account/{id}/trial-balance",
+            get(get_trial_balance_handler),
+        )
+        // GET /journal/account/{id}/ledger?ledger=Asset&asset_id=
+        // -> Postings of one ledger with a running balance
+        .route(
+            "/journal/account/{id}/ledger",
+            get(get_general_ledger_handler),
+        )
+        // GET /journal/account/{id}/balance-check
+        // -> Stored asset balances compared with the journal
+        .route(
+            "/journal/account/{id}/balance-check",
+            get(get_asset_balance_check_handler),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
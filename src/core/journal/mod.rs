@@ -0,0 +1,4 @@
+pub mod journal;
+pub mod journal_handler;
+pub mod journal_repository;
+pub mod journal_routes;
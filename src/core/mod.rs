@@ -1,8 +1,17 @@
 pub mod account;
 pub mod asset;
+pub mod attachment;
+pub mod category;
 pub mod country;
 pub mod currency;
+pub mod forecast;
+pub mod import;
+pub mod journal;
+pub mod net_worth;
+pub mod payee;
+pub mod reconciliation;
 pub mod recurring_transaction;
+pub mod rule;
 pub mod stock;
 pub mod transaction;
 pub mod user;
@@ -0,0 +1,4 @@
+pub mod net_worth;
+pub mod net_worth_handler;
+pub mod net_worth_repository;
+pub mod net_worth_routes;
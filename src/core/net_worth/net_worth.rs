@@ -0,0 +1,361 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{Datelike, Days, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Kind of holding a balance snapshot or valuation is about
+#[derive(
+    Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, PartialOrd, Ord, Clone, Copy,
+)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum HoldingType {
+    /// Cash, bank and other assets
+    Asset,
+    /// Credit cards, loans and mortgages; valued below zero when money is owed
+    Liability,
+    /// Shares, valued at the latest closing price
+    StockHolding,
+    /// Foreign currency, valued at the latest rate
+    CurrencyHolding,
+}
+
+/// Length of the periods a net-worth history is reported in
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Day,
+    /// Weeks starting on Monday
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// First day of the period `date` falls in
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date
+                .checked_sub_days(Days::new(date.weekday().num_days_from_monday().into()))
+                .unwrap_or(date),
+            Granularity::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+/// Value of one type of holding of an account on one day, in its base currency
+#[derive(Debug, FromRow, Clone)]
+pub struct DailyHoldingValue {
+    pub snapshot_date: NaiveDate,
+    pub holding_type: HoldingType,
+    pub value: Decimal,
+
+    /// Holdings left out of `value` for lack of a price or rate
+    pub unvalued: i64,
+}
+
+/// Net worth of an account at the end of a period
+#[derive(Debug, Serialize)]
+pub struct NetWorthPoint {
+    /// Day of the snapshot the point is taken from: the last one of the period
+    pub date: NaiveDate,
+
+    /// Value in the history's currency
+    pub total: Decimal,
+
+    pub by_type: BTreeMap<HoldingType, Decimal>,
+
+    /// Whether some holdings couldn't be valued and are missing from `total`
+    pub incomplete: bool,
+}
+
+/// Net worth of an account over time, built from its daily balance snapshots
+#[derive(Debug, Serialize)]
+pub struct NetWorthHistory {
+    pub account_id: Uuid,
+
+    /// The account's base currency, which all points are valued in
+    pub currency: String,
+
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub granularity: Granularity,
+
+    /// Oldest first; periods without snapshots are left out
+    pub points: Vec<NetWorthPoint>,
+}
+
+/// Allows a NetWorthHistory to be returned directly as a JSON HTTP response
+impl IntoResponse for NetWorthHistory {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+impl NetWorthHistory {
+    /// Groups daily values into points, keeping the last snapshot day of each period
+    pub fn from_daily_values(
+        account_id: Uuid,
+        currency: String,
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+        values: &[DailyHoldingValue],
+    ) -> Self {
+        let mut days: BTreeMap<NaiveDate, NetWorthPoint> = BTreeMap::new();
+        for value in values {
+            let point = days
+                .entry(value.snapshot_date)
+                .or_insert_with(|| NetWorthPoint {
+                    date: value.snapshot_date,
+                    total: Decimal::ZERO,
+                    by_type: BTreeMap::new(),
+                    incomplete: false,
+                });
+            point.total += value.value;
+            *point.by_type.entry(value.holding_type).or_default() += value.value;
+            point.incomplete |= value.unvalued > 0;
+        }
+
+        // Days are in order, so the last one of each period wins
+        let mut periods: BTreeMap<NaiveDate, NetWorthPoint> = BTreeMap::new();
+        for (date, point) in days {
+            periods.insert(granularity.period_start(date), point);
+        }
+
+        Self {
+            account_id,
+            currency,
+            from,
+            to,
+            granularity,
+            points: periods.into_values().collect(),
+        }
+    }
+}
+
+/// Current worth of one holding of an account
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct HoldingValuation {
+    pub holding_type: HoldingType,
+    pub holding_id: Uuid,
+
+    /// Asset type, ticker symbol or currency code
+    pub name: String,
+
+    /// Currency `amount` is in; unknown for stocks of exchanges we don't price
+    pub currency_code: Option<String>,
+
+    /// Balance, number of shares or amount of foreign currency
+    pub quantity: Decimal,
+
+    /// Worth in `currency_code`, unless the stock has no closing price
+    pub amount: Option<Decimal>,
+
+    /// Worth in the reporting currency, unless a price or rate is unknown
+    pub value: Option<Decimal>,
+}
+
+/// Current net worth of an account, all holdings valued in one reporting currency
+#[derive(Debug, Serialize)]
+pub struct NetWorth {
+    pub account_id: Uuid,
+
+    /// Reporting currency `total`, `by_type` and holding values are in
+    pub currency: String,
+
+    pub total: Decimal,
+
+    /// Every holding type, including those the account has none of; liabilities are
+    /// negative and come off the total
+    pub by_type: BTreeMap<HoldingType, Decimal>,
+
+    /// Whether some holdings couldn't be valued and are missing from `total`
+    pub incomplete: bool,
+
+    pub holdings: Vec<HoldingValuation>,
+}
+
+/// Allows a NetWorth to be returned directly as a JSON HTTP response
+impl IntoResponse for NetWorth {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+impl NetWorth {
+    /// Adds up the holdings that could be valued, per type and in total
+    pub fn from_valuations(
+        account_id: Uuid,
+        currency: String,
+        holdings: Vec<HoldingValuation>,
+    ) -> Self {
+        let mut by_type: BTreeMap<HoldingType, Decimal> = [
+            HoldingType::Asset,
+            HoldingType::Liability,
+            HoldingType::StockHolding,
+            HoldingType::CurrencyHolding,
+        ]
+        .into_iter()
+        .map(|holding_type| (holding_type, Decimal::ZERO))
+        .collect();
+        let mut incomplete = false;
+        for holding in &holdings {
+            match holding.value {
+                Some(value) => *by_type.entry(holding.holding_type).or_default() += value,
+                None => incomplete = true,
+            }
+        }
+
+        Self {
+            account_id,
+            currency,
+            total: by_type.values().sum(),
+            by_type,
+            incomplete,
+            holdings,
+        }
+    }
+}
+
+/// End-of-day balances of an asset from `from` to `to`, worked back from its `current`
+/// balance by undoing the `changes` (date and signed amount) made after each day
+pub fn daily_balances(
+    current: Decimal,
+    changes: &[(NaiveDate, Decimal)],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(NaiveDate, Decimal)> {
+    let mut changes = changes.to_vec();
+    changes.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+    let mut pending = changes.into_iter().peekable();
+
+    let mut balance = current;
+    let mut balances = Vec::new();
+    let mut date = to;
+    while date >= from {
+        while let Some((_, amount)) = pending.next_if(|(changed_on, _)| *changed_on > date) {
+            balance -= amount;
+        }
+        balances.push((date, balance));
+        match date.pred_opt() {
+            Some(previous) => date = previous,
+            None => break,
+        }
+    }
+    balances.reverse();
+    balances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_daily_balances() {
+        let changes = [
+            (date(3, 2), Decimal::new(100, 0)),
+            (date(3, 4), Decimal::new(-30, 0)),
+            (date(3, 4), Decimal::new(-20, 0)),
+            // Dated in the future, yet already in the current balance
+            (date(3, 9), Decimal::new(-10, 0)),
+        ];
+
+        let balances = daily_balances(Decimal::new(40, 0), &changes, date(3, 1), date(3, 5));
+
+        let expected = [0, 100, 100, 50, 50].map(|balance| Decimal::new(balance, 0));
+        assert_eq!(balances.len(), 5);
+        for (i, (day, balance)) in balances.into_iter().enumerate() {
+            assert_eq!(day, date(3, 1 + i as u32));
+            assert_eq!(balance, expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_net_worth_adds_up_valued_holdings() {
+        let holding = |holding_type, value: Option<i64>| HoldingValuation {
+            holding_type,
+            holding_id: Uuid::new_v4(),
+            name: "holding".to_string(),
+            currency_code: Some("TWD".to_string()),
+            quantity: Decimal::ONE,
+            amount: value.map(|value| Decimal::new(value, 0)),
+            value: value.map(|value| Decimal::new(value, 0)),
+        };
+        let holdings = vec![
+            holding(HoldingType::Asset, Some(1000)),
+            holding(HoldingType::Asset, Some(-200)),
+            holding(HoldingType::StockHolding, Some(500)),
+            // A stock without a closing price
+            holding(HoldingType::StockHolding, None),
+        ];
+
+        let net_worth = NetWorth::from_valuations(Uuid::new_v4(), "TWD".to_string(), holdings);
+
+        assert_eq!(net_worth.total, Decimal::new(1300, 0));
+        assert_eq!(net_worth.by_type[&HoldingType::Asset], Decimal::new(800, 0));
+        assert_eq!(
+            net_worth.by_type[&HoldingType::StockHolding],
+            Decimal::new(500, 0)
+        );
+        assert_eq!(
+            net_worth.by_type[&HoldingType::CurrencyHolding],
+            Decimal::ZERO
+        );
+        assert!(net_worth.incomplete);
+        assert_eq!(net_worth.holdings.len(), 4);
+    }
+
+    #[test]
+    fn test_history_keeps_last_snapshot_of_each_period() {
+        let value = |m, d, holding_type, value, unvalued| DailyHoldingValue {
+            snapshot_date: date(m, d),
+            holding_type,
+            value: Decimal::new(value, 0),
+            unvalued,
+        };
+        let values = [
+            value(1, 30, HoldingType::Asset, 100, 0),
+            value(1, 31, HoldingType::Asset, 120, 0),
+            value(1, 31, HoldingType::StockHolding, 50, 1),
+            value(2, 3, HoldingType::Asset, 90, 0),
+        ];
+
+        let monthly = NetWorthHistory::from_daily_values(
+            Uuid::new_v4(),
+            "TWD".to_string(),
+            date(1, 1),
+            date(2, 28),
+            Granularity::Month,
+            &values,
+        );
+        assert_eq!(monthly.points.len(), 2);
+        assert_eq!(monthly.points[0].date, date(1, 31));
+        assert_eq!(monthly.points[0].total, Decimal::new(170, 0));
+        assert_eq!(
+            monthly.points[0].by_type[&HoldingType::StockHolding],
+            Decimal::new(50, 0)
+        );
+        assert!(monthly.points[0].incomplete);
+        assert_eq!(monthly.points[1].total, Decimal::new(90, 0));
+
+        // Jan 30 (Thursday) and Feb 3 (Monday) are in different weeks
+        let weekly = NetWorthHistory::from_daily_values(
+            Uuid::new_v4(),
+            "TWD".to_string(),
+            date(1, 1),
+            date(2, 28),
+            Granularity::Week,
+            &values,
+        );
+        assert_eq!(weekly.points.len(), 2);
+        assert_eq!(Granularity::Week.period_start(date(2, 5)), date(2, 3));
+    }
+}
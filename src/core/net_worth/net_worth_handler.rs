@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{Months, NaiveDate, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::Granularity;
+use crate::repository::{get_net_worth, get_net_worth_history};
+
+/// Query parameters for the current net worth
+#[derive(Deserialize)]
+pub struct NetWorthQuery {
+    /// Code of the reporting currency; defaults to the account's base currency
+    pub currency: Option<String>,
+}
+
+/// Query parameters for the net-worth history
+#[derive(Deserialize)]
+pub struct NetWorthHistoryQuery {
+    /// First day included (defaults to a year before `to`)
+    pub from: Option<NaiveDate>,
+
+    /// Last day included (defaults to today)
+    pub to: Option<NaiveDate>,
+
+    /// `day`, `week` or `month`; defaults to `day`
+    #[serde(default)]
+    pub granularity: Granularity,
+}
+
+/// Handler: Current net worth of an account across all its holdings, in one currency
+pub async fn get_net_worth_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<NetWorthQuery>,
+) -> impl IntoResponse {
+    let currency = query.currency.map(|code| code.trim().to_uppercase());
+
+    match get_net_worth(&pool, account_id, currency.as_deref()).await {
+        Ok(Some(net_worth)) => net_worth.into_response(),
+        Ok(None) => {
+            eprintln!(
+                "No exchange rate for the reporting currency of account {}",
+                account_id
+            );
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch net worth of account {}: {:?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Net worth of an account over time in its base currency, from its daily balance
+/// snapshots
+pub async fn get_net_worth_history_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<NetWorthHistoryQuery>,
+) -> impl IntoResponse {
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query
+        .from
+        .unwrap_or_else(|| to.checked_sub_months(Months::new(12)).unwrap_or(to));
+    if from > to {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    match get_net_worth_history(&pool, account_id, from, to, query.granularity).await {
+        Ok(history) => history.into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch net-worth history of account {}: {:?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
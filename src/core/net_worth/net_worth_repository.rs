@@ -0,0 +1,764 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::net_worth::{daily_balances, HoldingType};
+use crate::models::{
+    DailyHoldingValue, Granularity, HoldingValuation, NetWorth, NetWorthHistory, Transaction,
+};
+use crate::repository::{get_account_by_id, get_asset_by_user_id, get_assets};
+
+// Copies the current valuation of every holding into the snapshots of $1; a second run on
+// the same day replaces that day's snapshots
+const QUERY_SNAPSHOT_ALL: &str = "
+    INSERT INTO balance_snapshots (
+        account_id, snapshot_date, holding_type, holding_id, currency_code, quantity, amount,
+        value, value_currency
+    )
+    SELECT
+        account_id, $1, holding_type, holding_id, currency_code, quantity, amount, value,
+        value_currency
+    FROM holding_valuations
+    ON CONFLICT (holding_type, holding_id, snapshot_date) DO UPDATE SET
+        currency_code = EXCLUDED.currency_code,
+        quantity = EXCLUDED.quantity,
+        amount = EXCLUDED.amount,
+        value = EXCLUDED.value,
+        value_currency = EXCLUDED.value_currency
+";
+// Snapshots taken in another base currency are converted into the account's current one
+// at the latest rate
+const QUERY_SELECT_DAILY_VALUES: &str = "
+    SELECT
+        snapshot_date,
+        holding_type,
+        COALESCE(SUM(value), 0) AS value,
+        COUNT(*) FILTER (WHERE value IS NULL) AS unvalued
+    FROM (
+        SELECT
+            s.snapshot_date,
+            s.holding_type,
+            CASE
+                WHEN s.value_currency = a.base_currency THEN s.value
+                ELSE ROUND(s.value * currency_exchange_rate(s.value_currency, a.base_currency), 4)
+            END AS value
+        FROM balance_snapshots s
+        JOIN accounts a ON a.account_id = s.account_id
+        WHERE s.account_id = $1 AND s.snapshot_date BETWEEN $2 AND $3
+    ) converted
+    GROUP BY snapshot_date, holding_type
+    ORDER BY snapshot_date
+";
+// Converts from each holding's own currency rather than from its base-currency value, so
+// holdings already in the reporting currency are taken as they are
+const QUERY_SELECT_VALUATIONS: &str = "
+    SELECT
+        holding_type,
+        holding_id,
+        name,
+        currency_code,
+        quantity,
+        amount,
+        ROUND(amount * currency_exchange_rate(currency_code, $2), 4) AS value
+    FROM holding_valuations
+    WHERE account_id = $1
+    ORDER BY holding_type, name
+";
+const QUERY_SELECT_REPORTING_RATE: &str = "SELECT currency_exchange_rate('TWD', $1)";
+const QUERY_SELECT_ASSET_TRANSACTIONS: &str =
+    "SELECT * FROM transactions WHERE from_asset_id = $1 OR to_asset_id = $1";
+// Rebuilt days are valued in the account's base currency at today's rate, except those whose
+// balance and base currency didn't change: they keep the value of the rate they were
+// snapshotted at
+const QUERY_UPSERT_ASSET_HISTORY: &str = "
+    INSERT INTO balance_snapshots (
+        account_id, snapshot_date, holding_type, holding_id, currency_code, quantity, amount,
+        value, value_currency
+    )
+    SELECT
+        $1, h.day, $2, $3, $4, h.balance, h.balance,
+        h.balance * currency_exchange_rate($4, a.base_currency), a.base_currency
+    FROM UNNEST($5::DATE[], $6::NUMERIC[]) AS h(day, balance)
+    JOIN accounts a ON a.account_id = $1
+    ON CONFLICT (holding_type, holding_id, snapshot_date) DO UPDATE SET
+        quantity = EXCLUDED.quantity,
+        amount = EXCLUDED.amount,
+        value = CASE
+            WHEN (balance_snapshots.amount, balance_snapshots.value_currency)
+                = (EXCLUDED.amount, EXCLUDED.value_currency)
+            THEN balance_snapshots.value
+            ELSE EXCLUDED.value
+        END,
+        value_currency = EXCLUDED.value_currency
+";
+
+/// Snapshot every asset, stock holding and currency holding of every account for `date`,
+/// valued in the account's base currency. Returns the number of snapshots written.
+pub async fn snapshot_balances(pool: &PgPool, date: NaiveDate) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(QUERY_SNAPSHOT_ALL)
+        .bind(date)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Current net worth of an account in `currency` (default its base currency): stock
+/// holdings at their latest closing price, everything else at the latest rates.
+/// `None` when the currency has no known rate.
+pub async fn get_net_worth(
+    pool: &PgPool,
+    account_id: Uuid,
+    currency: Option<&str>,
+) -> Result<Option<NetWorth>, sqlx::Error> {
+    let account = get_account_by_id(pool, account_id).await?;
+    let currency = currency.unwrap_or(&account.base_currency);
+
+    let rate: Option<Decimal> = sqlx::query_scalar(QUERY_SELECT_REPORTING_RATE)
+        .bind(currency)
+        .fetch_one(pool)
+        .await?;
+    if rate.is_none() {
+        return Ok(None);
+    }
+
+    let holdings = sqlx::query_as::<_, HoldingValuation>(QUERY_SELECT_VALUATIONS)
+        .bind(account_id)
+        .bind(currency)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Some(NetWorth::from_valuations(
+        account_id,
+        currency.to_string(),
+        holdings,
+    )))
+}
+
+/// Net worth of an account from `from` to `to`, one point per period of `granularity`,
+/// in its base currency
+pub async fn get_net_worth_history(
+    pool: &PgPool,
+    account_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+    granularity: Granularity,
+) -> Result<NetWorthHistory, sqlx::Error> {
+    let account = get_account_by_id(pool, account_id).await?;
+
+    let values = sqlx::query_as::<_, DailyHoldingValue>(QUERY_SELECT_DAILY_VALUES)
+        .bind(account_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(NetWorthHistory::from_daily_values(
+        account_id,
+        account.base_currency,
+        from,
+        to,
+        granularity,
+        &values,
+    ))
+}
+
+/// Rebuild the daily snapshots of assets and liabilities up to the day before `today` from their
+/// transactions, for one account or all of them. Each asset's history starts on its
+/// creation or its first transaction, whichever is earlier. Stock and currency holdings
+/// keep no history to rebuild from and are left alone.
+/// Returns the number of snapshots written.
+pub async fn backfill_balance_snapshots(
+    pool: &PgPool,
+    account_id: Option<Uuid>,
+    today: NaiveDate,
+) -> Result<u64, sqlx::Error> {
+    let assets = match account_id {
+        Some(account_id) => get_asset_by_user_id(pool, account_id).await?,
+        None => get_assets(pool).await?,
+    };
+    let Some(yesterday) = today.pred_opt() else {
+        return Ok(0);
+    };
+
+    let mut written = 0;
+    for asset in assets {
+        let transactions = sqlx::query_as::<_, Transaction>(QUERY_SELECT_ASSET_TRANSACTIONS)
+            .bind(asset.id)
+            .fetch_all(pool)
+            .await?;
+        let changes: Vec<(NaiveDate, Decimal)> = transactions
+            .iter()
+            .flat_map(|tx| {
+                let date = tx.transaction_time.unwrap_or(tx.created_at).date_naive();
+                tx.balance_effects()
+                    .into_iter()
+                    .filter(|(asset_id, _)| *asset_id == asset.id)
+                    .map(move |(_, amount)| (date, amount))
+            })
+            .collect();
+
+        let first_day = changes
+            .iter()
+            .map(|(date, _)| *date)
+            .chain([asset.created_at.date_naive()])
+            .min()
+            .unwrap_or(today);
+        let (days, balances): (Vec<NaiveDate>, Vec<Decimal>) =
+            daily_balances(asset.balance, &changes, first_day, yesterday)
+                .into_iter()
+                .unzip();
+        if days.is_empty() {
+            continue;
+        }
+
+        let holding_type = if asset.kind.is_liability() {
+            HoldingType::Liability
+        } else {
+            HoldingType::Asset
+        };
+        let result = sqlx::query(QUERY_UPSERT_ASSET_HISTORY)
+            .bind(asset.account_id)
+            .bind(holding_type)
+            .bind(asset.id)
+            .bind(&asset.currency_code)
+            .bind(&days)
+            .bind(&balances)
+            .execute(pool)
+            .await?;
+        written += result.rows_affected();
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::asset::asset_handler::UpdateAssetRequest;
+    use crate::core::currency::currency_holding_repository::create_currency_holding;
+    use crate::models::TransactionType;
+    use crate::models::{AssetKind, LiabilityTerms, LiabilityTermsUpdate};
+    use crate::repository::{
+        apply_transaction_balance, create_asset, create_liability, create_transaction,
+        get_asset_by_id, update_account_info, update_liability_terms,
+    };
+    use chrono::{Duration, Utc};
+    use sqlx::{migrate::MigrateDatabase, Postgres};
+    use std::env;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Migrations failed");
+
+        pool
+    }
+
+    async fn insert_user_and_account(pool: &PgPool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, 0, now(), now())")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        user_id
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_backfill_and_history() {
+        let pool = setup_test_db().await;
+        let account_id = insert_user_and_account(&pool).await;
+        let cash = create_asset(
+            &pool,
+            account_id,
+            "cash".to_string(),
+            Decimal::new(1000, 0),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+
+        let today = Utc::now().date_naive();
+        let mut conn = pool.acquire().await.unwrap();
+        for (days_ago, amount) in [(10, 200), (3, 50)] {
+            let tx = create_transaction(
+                &mut *conn,
+                Some(cash.id),
+                None,
+                TransactionType::Expense,
+                Decimal::new(amount, 0),
+                None,
+                Some(account_id),
+                None,
+                Some(Utc::now() - Duration::days(days_ago)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+            apply_transaction_balance(&mut conn, &tx).await.unwrap();
+        }
+        drop(conn);
+
+        // Today's snapshot holds the current balance
+        assert!(snapshot_balances(&pool, today).await.unwrap() >= 1);
+        let written = backfill_balance_snapshots(&pool, Some(account_id), today)
+            .await
+            .unwrap();
+        assert_eq!(written, 10);
+
+        let history = get_net_worth_history(
+            &pool,
+            account_id,
+            today - Duration::days(30),
+            today,
+            Granularity::Day,
+        )
+        .await
+        .unwrap();
+        let totals: Vec<Decimal> = history.points.iter().map(|point| point.total).collect();
+        let expected: Vec<Decimal> = [800, 800, 800, 800, 800, 800, 800, 750, 750, 750, 750]
+            .map(|total| Decimal::new(total, 0))
+            .to_vec();
+        assert_eq!(totals, expected);
+        assert_eq!(history.points[0].date, today - Duration::days(10));
+        assert_eq!(
+            history.points[10].by_type[&HoldingType::Asset],
+            Decimal::new(750, 0)
+        );
+        assert!(!history.points[10].incomplete);
+    }
+
+    #[tokio::test]
+    async fn test_history_in_account_base_currency() {
+        let pool = setup_test_db().await;
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate) VALUES ('XTS', 'Test currency', '32.0')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let account_id = insert_user_and_account(&pool).await;
+        let account = update_account_info(&pool, account_id, None, Some("XTS".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(account.base_currency, "XTS");
+        create_asset(
+            &pool,
+            account_id,
+            "cash".to_string(),
+            Decimal::new(320, 0),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+
+        let today = Utc::now().date_naive();
+        snapshot_balances(&pool, today).await.unwrap();
+        let history = |pool: PgPool| async move {
+            get_net_worth_history(&pool, account_id, today, today, Granularity::Day)
+                .await
+                .unwrap()
+        };
+
+        // Snapshots and the current net worth are valued in the base currency
+        let in_base = history(pool.clone()).await;
+        assert_eq!(in_base.currency, "XTS");
+        assert_eq!(in_base.points[0].total, Decimal::new(10, 0));
+        let net_worth = get_net_worth(&pool, account_id, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(net_worth.currency, "XTS");
+        assert_eq!(net_worth.total, Decimal::new(10, 0));
+
+        // Snapshots taken before the base currency changed are converted into the new one
+        update_account_info(&pool, account_id, None, Some("TWD".to_string()))
+            .await
+            .unwrap();
+        let converted = history(pool.clone()).await;
+        assert_eq!(converted.currency, "TWD");
+        assert_eq!(converted.points[0].total, Decimal::new(320, 0));
+
+        let refused = update_account_info(&pool, account_id, None, Some("xts".to_string())).await;
+        assert!(matches!(refused, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+    }
+
+    #[tokio::test]
+    async fn test_valuations_of_usd_holdings() {
+        let pool = setup_test_db().await;
+        let account_id = insert_user_and_account(&pool).await;
+
+        // Seeded inside the transaction, so other tests writing the USD rate can't interfere
+        let mut db_tx = pool.begin().await.unwrap();
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate) VALUES ('USD', 'US Dollar', '32.5')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&mut *db_tx)
+        .await
+        .unwrap();
+        let stock_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO stock_metadata (country, ticker_symbol, name) VALUES ('US', 'VITOTEST', 'Test')
+             ON CONFLICT (country, ticker_symbol) DO UPDATE SET name = EXCLUDED.name
+             RETURNING id",
+        )
+        .fetch_one(&mut *db_tx)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO stock_infos (
+                 country, ticker_symbol, company_name, trade_volume, trade_value, opening_price,
+                 highest_price, lowest_price, closing_price, change, transaction
+             ) VALUES ('US', 'VITOTEST', 'Test', '0', '0', '0', '0', '0', '1,150.00', '0', '0')
+             ON CONFLICT (country, ticker_symbol) DO UPDATE SET closing_price = EXCLUDED.closing_price",
+        )
+        .execute(&mut *db_tx)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO stock_holdings (account_id, stock_id, quantity) VALUES ($1, $2, 2)",
+        )
+        .bind(account_id)
+        .bind(stock_id)
+        .execute(&mut *db_tx)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO currency_holding (account_id, country, currency_code, amount_held)
+             VALUES ($1, 'US', 'USD', 10)",
+        )
+        .bind(account_id)
+        .execute(&mut *db_tx)
+        .await
+        .unwrap();
+        create_asset(
+            &mut *db_tx,
+            account_id,
+            "us bank".to_string(),
+            Decimal::new(100, 0),
+            "USD",
+        )
+        .await
+        .unwrap();
+
+        let values: Vec<(HoldingType, Option<Decimal>, Option<Decimal>)> = sqlx::query_as(
+            "SELECT holding_type, amount, value FROM holding_valuations
+             WHERE account_id = $1 ORDER BY holding_type",
+        )
+        .bind(account_id)
+        .fetch_all(&mut *db_tx)
+        .await
+        .unwrap();
+        let value_of = |holding_type| {
+            values
+                .iter()
+                .find(|(kind, _, _)| *kind == holding_type)
+                .map(|(_, amount, value)| (amount.unwrap(), value.unwrap()))
+                .unwrap()
+        };
+
+        // Snapshots copy these values: 32.5 TWD per USD
+        assert_eq!(
+            value_of(HoldingType::Asset),
+            (Decimal::new(100, 0), Decimal::new(3250, 0))
+        );
+        assert_eq!(
+            value_of(HoldingType::CurrencyHolding),
+            (Decimal::new(10, 0), Decimal::new(325, 0))
+        );
+        assert_eq!(
+            value_of(HoldingType::StockHolding),
+            (Decimal::new(2300, 0), Decimal::new(74750, 0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_net_worth_in_reporting_currency() {
+        let pool = setup_test_db().await;
+
+        // XTS is reserved for testing, so the currency scheduler never touches its rate
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate) VALUES ('XTS', 'Test currency', '32.0')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let account_id = insert_user_and_account(&pool).await;
+        create_asset(
+            &pool,
+            account_id,
+            "cash".to_string(),
+            Decimal::new(1000, 0),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+        create_currency_holding(
+            &pool,
+            account_id,
+            &"XT".to_string(),
+            &"XTS".to_string(),
+            Decimal::new(100, 0),
+            Decimal::new(30, 0),
+        )
+        .await
+        .unwrap();
+
+        let in_base = get_net_worth(&pool, account_id, Some("TWD"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(in_base.total, Decimal::new(4200, 0));
+        assert_eq!(in_base.by_type[&HoldingType::Asset], Decimal::new(1000, 0));
+        assert_eq!(
+            in_base.by_type[&HoldingType::CurrencyHolding],
+            Decimal::new(3200, 0)
+        );
+        assert_eq!(in_base.by_type[&HoldingType::StockHolding], Decimal::ZERO);
+        assert_eq!(in_base.holdings.len(), 2);
+        assert!(!in_base.incomplete);
+
+        let in_test_currency = get_net_worth(&pool, account_id, Some("XTS"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(in_test_currency.total, Decimal::new(13125, 2));
+        assert_eq!(in_test_currency.currency, "XTS");
+
+        assert!(get_net_worth(&pool, account_id, Some("ZZZ"))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            get_net_worth(&pool, Uuid::new_v4(), Some("TWD")).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_net_worth_across_foreign_currencies() {
+        let pool = setup_test_db().await;
+
+        // Neither is a real currency, so the currency scheduler never touches their rates
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate)
+             VALUES ('XTS', 'Test currency', '32.0'), ('XTT', 'Second test currency', '16.0')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let account_id = insert_user_and_account(&pool).await;
+        create_asset(
+            &pool,
+            account_id,
+            "foreign bank".to_string(),
+            Decimal::new(10, 0),
+            "XTS",
+        )
+        .await
+        .unwrap();
+        create_currency_holding(
+            &pool,
+            account_id,
+            &"XT".to_string(),
+            &"XTS".to_string(),
+            Decimal::new(5, 0),
+            Decimal::new(32, 0),
+        )
+        .await
+        .unwrap();
+
+        // 1 XTS is 32 TWD, which is 2 XTT
+        let net_worth = get_net_worth(&pool, account_id, Some("XTT"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(net_worth.total, Decimal::new(30, 0));
+        assert_eq!(net_worth.by_type[&HoldingType::Asset], Decimal::new(20, 0));
+        assert_eq!(
+            net_worth.by_type[&HoldingType::CurrencyHolding],
+            Decimal::new(10, 0)
+        );
+        let bank = net_worth
+            .holdings
+            .iter()
+            .find(|holding| holding.holding_type == HoldingType::Asset)
+            .unwrap();
+        assert_eq!(bank.amount, Some(Decimal::new(10, 0)));
+        assert_eq!(bank.value, Some(Decimal::new(20, 0)));
+    }
+
+    #[tokio::test]
+    async fn test_liabilities_come_off_net_worth() {
+        let pool = setup_test_db().await;
+        let account_id = insert_user_and_account(&pool).await;
+        create_asset(
+            &pool,
+            account_id,
+            "bank".to_string(),
+            Decimal::new(1000, 0),
+            crate::models::BASE_CURRENCY,
+        )
+        .await
+        .unwrap();
+        let terms = LiabilityTerms {
+            credit_limit: Some(Decimal::new(5000, 0)),
+            apr: Some(Decimal::new(155, 1)),
+            statement_day: Some(25),
+            payment_due_day: Some(10),
+        };
+        let card = create_liability(
+            &pool,
+            account_id,
+            "credit card".to_string(),
+            AssetKind::CreditCard,
+            Decimal::new(100, 0),
+            crate::models::BASE_CURRENCY,
+            &terms,
+        )
+        .await
+        .unwrap();
+        assert_eq!(card.balance, Decimal::new(-100, 0));
+        assert_eq!(card.balance_owed, Some(Decimal::new(100, 0)));
+        assert_eq!(card.lowest_allowed_balance(), Some(Decimal::new(-5000, 0)));
+
+        // A charge on the card is an expense paid from it
+        let mut conn = pool.acquire().await.unwrap();
+        let charge = create_transaction(
+            &mut *conn,
+            Some(card.id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(250, 0),
+            None,
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut conn, &charge).await.unwrap();
+        drop(conn);
+
+        let raised = LiabilityTermsUpdate {
+            credit_limit: Some(Some(Decimal::new(8000, 0))),
+            ..LiabilityTermsUpdate::default()
+        };
+        let card = update_liability_terms(&pool, card.id, &raised)
+            .await
+            .unwrap();
+        assert_eq!(card.balance_owed, Some(Decimal::new(350, 0)));
+        assert_eq!(card.terms.credit_limit, Some(Decimal::new(8000, 0)));
+        assert_eq!(card.terms.payment_due_day, Some(10));
+
+        // Terms set to null in an update are cleared; those left out are kept
+        let request: UpdateAssetRequest =
+            serde_json::from_str(r#"{"apr": null, "statement_day": null}"#).unwrap();
+        let card = update_liability_terms(&pool, card.id, &request.terms)
+            .await
+            .unwrap();
+        assert_eq!(card.terms.apr, None);
+        assert_eq!(card.terms.statement_day, None);
+        assert_eq!(card.terms.credit_limit, Some(Decimal::new(8000, 0)));
+        assert_eq!(card.terms.payment_due_day, Some(10));
+
+        // A card billed in a foreign currency comes off at the current rate
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate) VALUES ('XTS', 'Test currency', '32.0')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        create_liability(
+            &pool,
+            account_id,
+            "travel card".to_string(),
+            AssetKind::CreditCard,
+            Decimal::new(10, 0),
+            "XTS",
+            &LiabilityTerms::default(),
+        )
+        .await
+        .unwrap();
+
+        let net_worth = get_net_worth(&pool, account_id, Some("TWD"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(net_worth.total, Decimal::new(330, 0));
+        assert_eq!(
+            net_worth.by_type[&HoldingType::Liability],
+            Decimal::new(-670, 0)
+        );
+        assert_eq!(
+            net_worth.by_type[&HoldingType::Asset],
+            Decimal::new(1000, 0)
+        );
+
+        let today = Utc::now().date_naive();
+        snapshot_balances(&pool, today).await.unwrap();
+        let history = get_net_worth_history(&pool, account_id, today, today, Granularity::Day)
+            .await
+            .unwrap();
+        assert_eq!(history.points[0].total, Decimal::new(330, 0));
+
+        // Terms are only for liabilities
+        let bank = get_asset_by_user_id(&pool, account_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|asset| asset.kind == AssetKind::Asset)
+            .unwrap();
+        assert!(update_liability_terms(&pool, bank.id, &raised)
+            .await
+            .is_err());
+        let bank = get_asset_by_id(&pool, bank.id).await.unwrap();
+        assert_eq!(bank.balance_owed, None);
+        assert!(bank.terms.is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+use axum::{routing::get, Router};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::net_worth::net_worth_handler::*, models::Backend};
+
+/// Defines routes reporting an account's net worth
+pub fn net_worth_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // GET /accounts/{id}/net-worth?currency=
+        // -> Current value of every holding, per holding type and in total
+        .route("/accounts/{id}/net-worth", get(get_net_worth_handler))
+        // GET /accounts/{id}/net-worth/history?from=&to=&granularity=day|week|month
+        // -> Net worth at the end of each period, from the daily balance snapshots
+        .route(
+            "/accounts/{id}/net-worth/history",
+            get(get_net_worth_history_handler),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
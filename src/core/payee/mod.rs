@@ -0,0 +1,4 @@
+pub mod payee;
+pub mod payee_handler;
+pub mod payee_repository;
+pub mod payee_routes;
@@ -0,0 +1,163 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Who money was paid to or received from (e.g., a shop or an employer).
+/// All spellings of the payee resolve to it through its name and aliases.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Payee {
+    pub id: Uuid,
+
+    /// Account the payee belongs to
+    pub account_id: Uuid,
+
+    /// Display name, unique within the account ignoring case
+    pub name: String,
+
+    /// Other spellings, e.g. "STARBUCKS" for statement lines like "STARBUCKS #123"
+    pub aliases: Vec<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Payee {
+    /// Number of words of the longest name or alias found in the text; None when none is.
+    /// Both sides are compared after `normalize_payee_name`.
+    pub fn match_length(&self, text: &str) -> Option<usize> {
+        let text = normalize_payee_name(text);
+        let words: Vec<&str> = text.split(' ').collect();
+
+        std::iter::once(&self.name)
+            .chain(self.aliases.iter())
+            .filter_map(|alias| {
+                let alias = normalize_payee_name(alias);
+                let alias: Vec<&str> = alias.split(' ').filter(|w| !w.is_empty()).collect();
+                let found = !alias.is_empty()
+                    && words
+                        .windows(alias.len())
+                        .any(|window| window == alias.as_slice());
+                found.then_some(alias.len())
+            })
+            .max()
+    }
+}
+
+/// Reduce a payee name to the words that identify it: lowercase, punctuation dropped,
+/// and words containing digits (store numbers, branch codes) removed.
+/// "STARBUCKS #123" and "Starbucks Taipei 101" become "starbucks" and "starbucks taipei".
+pub fn normalize_payee_name(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !word.chars().any(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The payee a text (e.g. a statement description) refers to.
+/// The most specific match wins: the one with the longest matching name or alias.
+pub fn resolve_payee<'a>(payees: &'a [Payee], text: &str) -> Option<&'a Payee> {
+    payees
+        .iter()
+        .filter_map(|payee| payee.match_length(text).map(|length| (length, payee)))
+        .fold(
+            None,
+            |best: Option<(usize, &Payee)>, (length, payee)| match best {
+                Some((best_length, _)) if best_length >= length => best,
+                _ => Some((length, payee)),
+            },
+        )
+        .map(|(_, payee)| payee)
+}
+
+/// Allows a `Payee` to be returned as a JSON response
+impl IntoResponse for Payee {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Wrapper for returning the payees of an account
+#[derive(Debug, Serialize)]
+pub struct PayeeList(pub Vec<Payee>);
+
+/// Enables `PayeeList` to be returned as a JSON response
+impl IntoResponse for PayeeList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Money an account paid to and received from one payee over a period
+#[derive(Debug, Serialize, FromRow)]
+pub struct PayeeSummary {
+    pub payee_id: Uuid,
+    pub name: String,
+    pub transaction_count: i64,
+
+    /// Paid out of the account, fees included
+    pub spent: Decimal,
+
+    /// Paid into the account
+    pub received: Decimal,
+
+    pub last_transaction_at: DateTime<Utc>,
+}
+
+/// Wrapper for returning per-payee summaries
+#[derive(Debug, Serialize)]
+pub struct PayeeSummaryList(pub Vec<PayeeSummary>);
+
+/// Enables `PayeeSummaryList` to be returned as a JSON response
+impl IntoResponse for PayeeSummaryList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payee(name: &str, aliases: &[&str]) -> Payee {
+        Payee {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            name: name.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_payee_name() {
+        assert_eq!(normalize_payee_name("STARBUCKS #123"), "starbucks");
+        assert_eq!(
+            normalize_payee_name("Starbucks Taipei 101"),
+            "starbucks taipei"
+        );
+        assert_eq!(normalize_payee_name("  7-ELEVEN  Store  "), "eleven store");
+        assert_eq!(normalize_payee_name("全家 FamilyMart"), "全家 familymart");
+    }
+
+    #[test]
+    fn test_resolve_payee_by_alias() {
+        let starbucks = payee("Starbucks", &[]);
+        let starbucks_reserve = payee("Starbucks Reserve", &["STARBUCKS RSV"]);
+        let family_mart = payee("FamilyMart", &["全家"]);
+        let payees = vec![starbucks, starbucks_reserve, family_mart];
+
+        let resolve = |text: &str| resolve_payee(&payees, text).map(|payee| payee.name.as_str());
+        assert_eq!(resolve("STARBUCKS #123"), Some("Starbucks"));
+        assert_eq!(resolve("Starbucks Taipei 101"), Some("Starbucks"));
+        assert_eq!(resolve("POS STARBUCKS RSV 0042"), Some("Starbucks Reserve"));
+        assert_eq!(resolve("全家便利商店"), None);
+        assert_eq!(resolve("全家 中山店"), Some("FamilyMart"));
+        // Whole words only
+        assert_eq!(resolve("STARBUCKSX"), None);
+    }
+}
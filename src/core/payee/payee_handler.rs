@@ -0,0 +1,180 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{PayeeList, PayeeSummaryList};
+use crate::repository::{
+    create_payee, delete_payee, find_payee_by_alias, get_payee_by_id, get_payee_summaries,
+    get_payees_by_account_id, update_payee_info,
+};
+
+/// Request payload for creating a payee
+#[derive(Deserialize)]
+pub struct CreatePayeeRequest {
+    pub account_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// Request payload for renaming a payee or replacing its aliases
+#[derive(Deserialize)]
+pub struct UpdatePayeeRequest {
+    pub name: Option<String>,
+    pub aliases: Option<Vec<String>>,
+}
+
+/// Query parameter naming the text to resolve, e.g. a statement description
+#[derive(Deserialize)]
+pub struct ResolvePayeeQuery {
+    pub text: String,
+}
+
+/// Query parameters for per-payee summaries
+#[derive(Deserialize)]
+pub struct PayeeSummaryQuery {
+    /// Start of the period (defaults to 30 days before `to`)
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the period, exclusive (defaults to now)
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Map a failed payee write to a response status: duplicate names conflict
+fn payee_error_status(err: &sqlx::Error) -> StatusCode {
+    match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => StatusCode::CONFLICT,
+        sqlx::Error::Database(db_err)
+            if db_err.is_check_violation() || db_err.is_foreign_key_violation() =>
+        {
+            StatusCode::BAD_REQUEST
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Handler: Fetch the payees of an account
+pub async fn get_payees_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_payees_by_account_id(&*pool, account_id).await {
+        Ok(payees) => PayeeList(payees).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch payees for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Fetch a single payee by ID
+pub async fn get_payee_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(payee_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_payee_by_id(&*pool, payee_id).await {
+        Ok(payee) => payee.into_response(),
+        Err(err) => {
+            eprintln!("Failed to fetch payee {}: {:#?}", payee_id, err);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// Handler: Create a payee
+pub async fn add_payee_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<CreatePayeeRequest>,
+) -> impl IntoResponse {
+    match create_payee(&pool, payload.account_id, &payload.name, &payload.aliases).await {
+        Ok(payee) => (StatusCode::CREATED, payee).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to create payee for account {}: {:#?}",
+                payload.account_id, err
+            );
+            payee_error_status(&err).into_response()
+        }
+    }
+}
+
+/// Handler: Rename a payee or replace its aliases
+pub async fn update_payee_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(payee_id): Path<Uuid>,
+    Json(payload): Json<UpdatePayeeRequest>,
+) -> impl IntoResponse {
+    match update_payee_info(&pool, payee_id, payload.name, payload.aliases).await {
+        Ok(payee) => payee.into_response(),
+        Err(err) => {
+            eprintln!("Failed to update payee {}: {:#?}", payee_id, err);
+            payee_error_status(&err).into_response()
+        }
+    }
+}
+
+/// Handler: Delete a payee; its transactions are kept without one
+pub async fn delete_payee_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(payee_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_payee(&pool, payee_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            eprintln!("Failed to delete payee {}: {:#?}", payee_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Find the payee of an account a text refers to by its name and aliases
+pub async fn resolve_payee_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<ResolvePayeeQuery>,
+) -> impl IntoResponse {
+    match find_payee_by_alias(&*pool, account_id, &query.text).await {
+        Ok(Some(payee)) => payee.into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to resolve payee for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Money paid to and received from each payee of an account
+pub async fn get_payee_summaries_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Query(query): Query<PayeeSummaryQuery>,
+) -> impl IntoResponse {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+
+    match get_payee_summaries(&pool, account_id, from, to).await {
+        Ok(summaries) => PayeeSummaryList(summaries).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch payee summaries for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::{resolve_payee, Payee, PayeeSummary};
+
+// SQL query to fetch an account's payees by name
+const QUERY_SELECT_BY_ACCOUNT_ID: &str = "SELECT * FROM payees WHERE account_id = $1 ORDER BY name";
+const QUERY_SELECT_BY_ID: &str = "SELECT * FROM payees WHERE id = $1";
+const QUERY_INSERT: &str = "
+    INSERT INTO payees (account_id, name, aliases)
+    VALUES ($1, $2, $3)
+    RETURNING *
+";
+const QUERY_DELETE: &str = "DELETE FROM payees WHERE id = $1";
+
+// SQL query summing what an account paid to and received from each payee over a period.
+// Transfers between the account's own assets move nothing in or out.
+const QUERY_SELECT_SUMMARIES: &str = "
+    SELECT
+        p.id AS payee_id,
+        p.name,
+        COUNT(*) AS transaction_count,
+        COALESCE(SUM(t.amount + t.fee) FILTER (
+            WHERE t.from_account_id = $1 AND t.to_account_id IS DISTINCT FROM $1
+        ), 0) AS spent,
+        COALESCE(SUM(COALESCE(t.to_amount, t.amount)) FILTER (
+            WHERE t.to_account_id = $1 AND t.from_account_id IS DISTINCT FROM $1
+        ), 0) AS received,
+        MAX(COALESCE(t.transaction_time, t.created_at)) AS last_transaction_at
+    FROM transactions t
+    JOIN payees p ON p.id = t.payee_id
+    WHERE p.account_id = $1
+      AND (t.from_account_id = $1 OR t.to_account_id = $1)
+      AND COALESCE(t.transaction_time, t.created_at) >= $2
+      AND COALESCE(t.transaction_time, t.created_at) < $3
+    GROUP BY p.id, p.name
+    ORDER BY spent DESC, received DESC, p.name
+";
+
+/// Fetch all payees of an account, by name
+pub async fn get_payees_by_account_id<'e, E>(
+    executor: E,
+    account_id: Uuid,
+) -> Result<Vec<Payee>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Payee>(QUERY_SELECT_BY_ACCOUNT_ID)
+        .bind(account_id)
+        .fetch_all(executor)
+        .await
+}
+
+/// Fetch a single payee by its ID
+pub async fn get_payee_by_id<'e, E>(executor: E, payee_id: Uuid) -> Result<Payee, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Payee>(QUERY_SELECT_BY_ID)
+        .bind(payee_id)
+        .fetch_one(executor)
+        .await
+}
+
+/// Find the payee of an account that a text (e.g. transaction notes) refers to
+pub async fn find_payee_by_alias<'e, E>(
+    executor: E,
+    account_id: Uuid,
+    text: &str,
+) -> Result<Option<Payee>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let payees = get_payees_by_account_id(executor, account_id).await?;
+    Ok(resolve_payee(&payees, text).cloned())
+}
+
+/// Create a payee with its aliases
+pub async fn create_payee(
+    pool: &PgPool,
+    account_id: Uuid,
+    name: &str,
+    aliases: &[String],
+) -> Result<Payee, sqlx::Error> {
+    sqlx::query_as::<_, Payee>(QUERY_INSERT)
+        .bind(account_id)
+        .bind(name.trim())
+        .bind(normalized_aliases(aliases))
+        .fetch_one(pool)
+        .await
+}
+
+/// Rename a payee and/or replace its aliases
+pub async fn update_payee_info(
+    pool: &PgPool,
+    payee_id: Uuid,
+    name: Option<String>,
+    aliases: Option<Vec<String>>,
+) -> Result<Payee, sqlx::Error> {
+    // If no fields are provided to update, return an error
+    if name.is_none() && aliases.is_none() {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("UPDATE payees SET ");
+
+    if let Some(name) = name {
+        builder
+            .push("name = ")
+            .push_bind(name.trim().to_string())
+            .push(", ");
+    }
+    if let Some(aliases) = aliases {
+        builder
+            .push("aliases = ")
+            .push_bind(normalized_aliases(&aliases))
+            .push(", ");
+    }
+
+    // Always update the timestamp
+    builder.push("updated_at = ").push_bind(Utc::now());
+    builder.push(" WHERE id = ").push_bind(payee_id);
+    builder.push(" RETURNING *");
+
+    builder.build_query_as::<Payee>().fetch_one(pool).await
+}
+
+/// Delete a payee; its transactions are kept without a payee
+pub async fn delete_payee(pool: &PgPool, payee_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(QUERY_DELETE)
+        .bind(payee_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Sum what an account paid to and received from each payee between `from` (inclusive)
+/// and `to` (exclusive)
+pub async fn get_payee_summaries(
+    pool: &PgPool,
+    account_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<PayeeSummary>, sqlx::Error> {
+    sqlx::query_as::<_, PayeeSummary>(QUERY_SELECT_SUMMARIES)
+        .bind(account_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+}
+
+/// Aliases trimmed, without empties and duplicates
+fn normalized_aliases(aliases: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(aliases.len());
+    for alias in aliases.iter().map(|alias| alias.trim()) {
+        if !alias.is_empty() && !normalized.iter().any(|seen| seen == alias) {
+            normalized.push(alias.to_string());
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionType;
+    use crate::repository::create_transaction;
+    use rust_decimal::Decimal;
+    use sqlx::migrate::MigrateDatabase;
+    use std::env;
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url =
+            env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env.test");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to test DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn insert_user_and_account(pool: &PgPool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, $2, now(), now())")
+            .bind(user_id)
+            .bind(Decimal::new(0, 2))
+            .execute(pool)
+            .await
+            .unwrap();
+
+        user_id
+    }
+
+    async fn insert_asset(pool: &PgPool, account_id: Uuid) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO assets (account_id, asset_type, balance, created_at, updated_at)
+             VALUES ($1, 'cash', 0, now(), now()) RETURNING id",
+        )
+        .bind(account_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_expense(pool: &PgPool, account_id: Uuid, asset_id: Uuid, payee_id: Uuid) {
+        create_transaction(
+            pool,
+            Some(asset_id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(15000, 2),
+            Some(Decimal::new(100, 2)),
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(payee_id),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_payee_aliases_and_summary() {
+        let pool = setup_test_db().await;
+        let account_id = insert_user_and_account(&pool).await;
+        let other_account_id = insert_user_and_account(&pool).await;
+        let asset_id = insert_asset(&pool, account_id).await;
+
+        let starbucks = create_payee(
+            &pool,
+            account_id,
+            " Starbucks ",
+            &[
+                "STARBUCKS".to_string(),
+                " ".to_string(),
+                "STARBUCKS".to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(starbucks.name, "Starbucks");
+        assert_eq!(starbucks.aliases, vec!["STARBUCKS".to_string()]);
+
+        // Names are unique per account, ignoring case
+        let duplicate = create_payee(&pool, account_id, "STARBUCKS", &[]).await;
+        assert!(matches!(duplicate, Err(sqlx::Error::Database(ref e)) if e.is_unique_violation()));
+        create_payee(&pool, other_account_id, "Starbucks", &[])
+            .await
+            .unwrap();
+
+        // Statement spellings resolve to the account's payee
+        for text in ["STARBUCKS #123", "Starbucks Taipei 101"] {
+            let found = find_payee_by_alias(&pool, account_id, text).await.unwrap();
+            assert_eq!(found.map(|payee| payee.id), Some(starbucks.id));
+        }
+        assert!(find_payee_by_alias(&pool, account_id, "Costco")
+            .await
+            .unwrap()
+            .is_none());
+
+        insert_expense(&pool, account_id, asset_id, starbucks.id).await;
+        insert_expense(&pool, account_id, asset_id, starbucks.id).await;
+
+        let to = Utc::now() + chrono::Duration::minutes(1);
+        let summaries = get_payee_summaries(&pool, account_id, to - chrono::Duration::days(1), to)
+            .await
+            .unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].payee_id, starbucks.id);
+        assert_eq!(summaries[0].transaction_count, 2);
+        assert_eq!(summaries[0].spent, Decimal::new(30200, 2));
+        assert_eq!(summaries[0].received, Decimal::ZERO);
+
+        // Deleting the payee keeps its transactions
+        delete_payee(&pool, starbucks.id).await.unwrap();
+        let remaining: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE from_asset_id = $1")
+                .bind(asset_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(remaining, 2);
+    }
+}
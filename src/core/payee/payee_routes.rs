@@ -0,0 +1,36 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::payee::payee_handler::*, models::Backend};
+
+/// Defines routes for managing the payees of an account
+pub fn payee_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // POST /payees -> Create a payee with its aliases
+        .route("/payees", post(add_payee_handler))
+        // GET    /payees/{id} -> Fetch one by ID
+        // PATCH  /payees/{id} -> Rename a payee or replace its aliases
+        // DELETE /payees/{id} -> Delete a payee, keeping its transactions
+        .route(
+            "/payees/{id}",
+            get(get_payee_handler)
+                .patch(update_payee_handler)
+                .delete(delete_payee_handler),
+        )
+        // GET /payees/account/{id} -> Payees of an account
+        .route("/payees/account/{id}", get(get_payees_handler))
+        // GET /payees/account/{id}/resolve?text= -> Payee a text such as a statement line refers to
+        .route("/payees/account/{id}/resolve", get(resolve_payee_handler))
+        // GET /payees/account/{id}/summary?from=&to= -> Spent and received per payee
+        .route(
+            "/payees/account/{id}/summary",
+            get(get_payee_summaries_handler),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
@@ -0,0 +1,4 @@
+pub mod reconciliation;
+pub mod reconciliation_handler;
+pub mod reconciliation_repository;
+pub mod reconciliation_routes;
@@ -0,0 +1,117 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::error::DatabaseError;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::Transaction;
+
+/// SQLSTATE raised by the database when a reconciled transaction is changed (`object_in_use`)
+const RECONCILED_LOCK_SQLSTATE: &str = "55006";
+
+/// Whether a database error means a reconciled, and therefore locked, transaction was touched
+pub fn is_reconciled_lock(err: &dyn DatabaseError) -> bool {
+    err.code().as_deref() == Some(RECONCILED_LOCK_SQLSTATE)
+}
+
+/// State of a reconciliation session
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum ReconciliationStatus {
+    /// Transactions are still being ticked off against the statement
+    Open,
+    /// The cleared balance matched the statement and its transactions were locked
+    Completed,
+}
+
+/// Reconciliation of one asset against a bank statement
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Reconciliation {
+    pub id: Uuid,
+
+    /// Asset being reconciled (e.g., a checking account)
+    pub asset_id: Uuid,
+
+    /// Closing date of the statement; transactions up to the end of this day (UTC) count
+    pub statement_date: NaiveDate,
+
+    /// Ending balance printed on the statement
+    pub statement_balance: Decimal,
+
+    pub status: ReconciliationStatus,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl Reconciliation {
+    /// Start of the day after the statement date; earlier transactions are on the statement
+    pub fn cutoff(&self) -> DateTime<Utc> {
+        (self.statement_date + Days::new(1))
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    }
+}
+
+/// Allows a `Reconciliation` to be returned as a JSON response
+impl IntoResponse for Reconciliation {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Wrapper for returning the reconciliations of an asset
+#[derive(Debug, Serialize)]
+pub struct ReconciliationList(pub Vec<Reconciliation>);
+
+/// Enables `ReconciliationList` to be returned as a JSON response
+impl IntoResponse for ReconciliationList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Progress of a reconciliation: how far the cleared transactions are from the statement
+#[derive(Debug, Serialize)]
+pub struct ReconciliationSummary {
+    #[serde(flatten)]
+    pub reconciliation: Reconciliation,
+
+    /// Balance of the asset counting only cleared and reconciled transactions
+    /// up to the statement date
+    pub cleared_balance: Decimal,
+
+    /// `statement_balance - cleared_balance`; the session can be completed at zero
+    pub difference: Decimal,
+
+    /// Pending transactions up to the statement date, not yet matched to the statement
+    pub unmatched: Vec<Transaction>,
+}
+
+impl ReconciliationSummary {
+    pub fn new(
+        reconciliation: Reconciliation,
+        cleared_balance: Decimal,
+        unmatched: Vec<Transaction>,
+    ) -> Self {
+        Self {
+            difference: reconciliation.statement_balance - cleared_balance,
+            reconciliation,
+            cleared_balance,
+            unmatched,
+        }
+    }
+
+    /// Whether the cleared transactions add up to the statement
+    pub fn is_balanced(&self) -> bool {
+        self.difference.is_zero()
+    }
+}
+
+/// Enables `ReconciliationSummary` to be returned as a JSON response
+impl IntoResponse for ReconciliationSummary {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{
+    Reconciliation, ReconciliationList, ReconciliationStatus, ReconciliationSummary,
+};
+use crate::repository::{
+    complete_reconciliation, create_reconciliation, delete_open_reconciliation,
+    get_reconciliation_by_id, get_reconciliation_summary, get_reconciliations_by_asset_id,
+    lock_asset_by_id, lock_reconciliation_by_id,
+};
+
+/// Request payload for opening a reconciliation
+#[derive(Deserialize)]
+pub struct CreateReconciliationRequest {
+    pub asset_id: Uuid,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Decimal,
+}
+
+/// Why a reconciliation couldn't be completed
+enum CompleteError {
+    /// Already completed
+    NotOpen,
+    /// The cleared transactions don't add up to the statement yet
+    Unbalanced(ReconciliationSummary),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CompleteError {
+    fn from(err: sqlx::Error) -> Self {
+        CompleteError::Database(err)
+    }
+}
+
+/// Handler: Open a reconciliation of an asset against a statement
+pub async fn add_reconciliation_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<CreateReconciliationRequest>,
+) -> impl IntoResponse {
+    match create_reconciliation(
+        &pool,
+        payload.asset_id,
+        payload.statement_date,
+        payload.statement_balance,
+    )
+    .await
+    {
+        Ok(reconciliation) => (StatusCode::CREATED, reconciliation).into_response(),
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            eprintln!(
+                "Asset {} already has an open reconciliation",
+                payload.asset_id
+            );
+            StatusCode::CONFLICT.into_response()
+        }
+        Err(sqlx::Error::Database(err)) if err.is_foreign_key_violation() => {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Err(err) => {
+            eprintln!(
+                "Failed to open reconciliation for asset {}: {:#?}",
+                payload.asset_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Fetch the reconciliations of an asset
+pub async fn get_reconciliations_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(asset_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_reconciliations_by_asset_id(&pool, asset_id).await {
+        Ok(reconciliations) => ReconciliationList(reconciliations).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch reconciliations for asset {}: {:#?}",
+                asset_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Show a reconciliation with its cleared balance, the difference to the
+/// statement and the transactions not matched yet
+pub async fn get_reconciliation_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(reconciliation_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match reconciliation_summary(&pool, reconciliation_id).await {
+        Ok(summary) => summary.into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to summarise reconciliation {}: {:#?}",
+                reconciliation_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Complete a reconciliation, locking its cleared transactions.
+/// Refused with the summary while the cleared balance differs from the statement.
+pub async fn complete_reconciliation_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(reconciliation_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match complete_reconciliation_atomically(&pool, reconciliation_id).await {
+        Ok(reconciliation) => reconciliation.into_response(),
+        Err(CompleteError::NotOpen) => StatusCode::CONFLICT.into_response(),
+        Err(CompleteError::Unbalanced(summary)) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, summary).into_response()
+        }
+        Err(CompleteError::Database(sqlx::Error::RowNotFound)) => {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Err(CompleteError::Database(err)) => {
+            eprintln!(
+                "Failed to complete reconciliation {}: {:#?}",
+                reconciliation_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Discard an open reconciliation; completed ones stay as the record of the lock
+pub async fn delete_reconciliation_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(reconciliation_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_open_reconciliation(&pool, reconciliation_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(sqlx::Error::RowNotFound) => {
+            match get_reconciliation_by_id(&*pool, reconciliation_id).await {
+                Ok(_) => StatusCode::CONFLICT.into_response(),
+                Err(_) => StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+        Err(err) => {
+            eprintln!(
+                "Failed to delete reconciliation {}: {:#?}",
+                reconciliation_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Summarise a reconciliation on one connection
+async fn reconciliation_summary(
+    pool: &PgPool,
+    reconciliation_id: Uuid,
+) -> Result<ReconciliationSummary, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let reconciliation = get_reconciliation_by_id(&mut *conn, reconciliation_id).await?;
+    get_reconciliation_summary(&mut conn, reconciliation).await
+}
+
+/// Check the balance and lock the transactions in one database transaction.
+/// The asset row is locked first so no transaction can move its balance in between.
+async fn complete_reconciliation_atomically(
+    pool: &PgPool,
+    reconciliation_id: Uuid,
+) -> Result<Reconciliation, CompleteError> {
+    let mut db_tx = pool.begin().await?;
+
+    let reconciliation = lock_reconciliation_by_id(&mut db_tx, reconciliation_id).await?;
+    if reconciliation.status != ReconciliationStatus::Open {
+        return Err(CompleteError::NotOpen);
+    }
+    lock_asset_by_id(&mut db_tx, reconciliation.asset_id).await?;
+
+    let summary = get_reconciliation_summary(&mut db_tx, reconciliation).await?;
+    if !summary.is_balanced() {
+        return Err(CompleteError::Unbalanced(summary));
+    }
+
+    let completed = complete_reconciliation(&mut db_tx, &summary.reconciliation).await?;
+    db_tx.commit().await?;
+    Ok(completed)
+}
@@ -0,0 +1,404 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::{Executor, PgConnection, PgPool, Postgres};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::{Reconciliation, ReconciliationSummary, Transaction, TransactionSplit};
+use crate::repository::get_transaction_splits;
+
+// SQL query to fetch a single reconciliation by ID
+const QUERY_SELECT_BY_ID: &str = "SELECT * FROM reconciliations WHERE id = $1";
+
+// SQL query to fetch and lock a reconciliation for the rest of the transaction
+const QUERY_SELECT_BY_ID_FOR_UPDATE: &str =
+    "SELECT * FROM reconciliations WHERE id = $1 FOR UPDATE";
+
+// SQL query to fetch the reconciliations of an asset, latest statement first
+const QUERY_SELECT_BY_ASSET_ID: &str = "
+    SELECT * FROM reconciliations
+    WHERE asset_id = $1
+    ORDER BY statement_date DESC, created_at DESC
+";
+
+// SQL insert query for opening a reconciliation
+const QUERY_INSERT: &str = "
+    INSERT INTO reconciliations (asset_id, statement_date, statement_balance)
+    VALUES ($1, $2, $3)
+    RETURNING *
+";
+
+// SQL query to delete a reconciliation that is still open
+const QUERY_DELETE_OPEN: &str = "DELETE FROM reconciliations WHERE id = $1 AND status = 'Open'";
+
+// SQL query summing the balance effects of the asset's transactions that are
+// not on the statement: pending ones, and anything after the cutoff
+const QUERY_SUM_UNCLEARED_EFFECTS: &str = "
+    SELECT COALESCE(SUM(
+        CASE WHEN to_asset_id = $1 THEN COALESCE(to_amount, amount) ELSE 0 END
+        - CASE WHEN from_asset_id = $1 THEN amount + fee ELSE 0 END
+    ), 0)
+    FROM transactions
+    WHERE (from_asset_id = $1 OR to_asset_id = $1)
+      AND NOT (status IN ('Cleared', 'Reconciled') AND COALESCE(transaction_time, created_at) < $2)
+";
+
+// SQL query to fetch the pending transactions of the asset up to the cutoff
+const QUERY_SELECT_UNMATCHED: &str = "
+    SELECT * FROM transactions
+    WHERE (from_asset_id = $1 OR to_asset_id = $1)
+      AND status = 'Pending'
+      AND COALESCE(transaction_time, created_at) < $2
+    ORDER BY COALESCE(transaction_time, created_at), id
+";
+
+// SQL query locking the cleared transactions of the asset up to the cutoff
+const QUERY_RECONCILE_TRANSACTIONS: &str = "
+    UPDATE transactions SET status = 'Reconciled', reconciliation_id = $3, updated_at = now()
+    WHERE (from_asset_id = $1 OR to_asset_id = $1)
+      AND status = 'Cleared'
+      AND COALESCE(transaction_time, created_at) < $2
+";
+
+// SQL query closing a reconciliation
+const QUERY_COMPLETE: &str = "
+    UPDATE reconciliations SET status = 'Completed', completed_at = now()
+    WHERE id = $1
+    RETURNING *
+";
+
+/// Fetch a reconciliation by ID
+pub async fn get_reconciliation_by_id<'e, E>(
+    executor: E,
+    reconciliation_id: Uuid,
+) -> Result<Reconciliation, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Reconciliation>(QUERY_SELECT_BY_ID)
+        .bind(reconciliation_id)
+        .fetch_one(executor)
+        .await
+}
+
+/// Fetch a reconciliation and lock it until the surrounding transaction ends
+pub async fn lock_reconciliation_by_id(
+    conn: &mut PgConnection,
+    reconciliation_id: Uuid,
+) -> Result<Reconciliation, sqlx::Error> {
+    sqlx::query_as::<_, Reconciliation>(QUERY_SELECT_BY_ID_FOR_UPDATE)
+        .bind(reconciliation_id)
+        .fetch_one(conn)
+        .await
+}
+
+/// Fetch all reconciliations of an asset
+pub async fn get_reconciliations_by_asset_id(
+    pool: &PgPool,
+    asset_id: Uuid,
+) -> Result<Vec<Reconciliation>, sqlx::Error> {
+    sqlx::query_as::<_, Reconciliation>(QUERY_SELECT_BY_ASSET_ID)
+        .bind(asset_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Open a reconciliation of an asset against a statement.
+/// Fails with a unique violation when the asset already has an open one.
+pub async fn create_reconciliation(
+    pool: &PgPool,
+    asset_id: Uuid,
+    statement_date: NaiveDate,
+    statement_balance: Decimal,
+) -> Result<Reconciliation, sqlx::Error> {
+    sqlx::query_as::<_, Reconciliation>(QUERY_INSERT)
+        .bind(asset_id)
+        .bind(statement_date)
+        .bind(statement_balance)
+        .fetch_one(pool)
+        .await
+}
+
+/// Delete a reconciliation that hasn't been completed; `RowNotFound` otherwise
+pub async fn delete_open_reconciliation(
+    pool: &PgPool,
+    reconciliation_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(QUERY_DELETE_OPEN)
+        .bind(reconciliation_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+/// Work out the cleared balance of the reconciled asset and the transactions
+/// that haven't been matched to the statement yet
+pub async fn get_reconciliation_summary(
+    conn: &mut PgConnection,
+    reconciliation: Reconciliation,
+) -> Result<ReconciliationSummary, sqlx::Error> {
+    let cutoff = reconciliation.cutoff();
+
+    let balance: Decimal = sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+        .bind(reconciliation.asset_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let uncleared: Decimal = sqlx::query_scalar(QUERY_SUM_UNCLEARED_EFFECTS)
+        .bind(reconciliation.asset_id)
+        .bind(cutoff)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let mut unmatched = sqlx::query_as::<_, Transaction>(QUERY_SELECT_UNMATCHED)
+        .bind(reconciliation.asset_id)
+        .bind(cutoff)
+        .fetch_all(&mut *conn)
+        .await?;
+    let ids: Vec<Uuid> = unmatched.iter().map(|tx| tx.id).collect();
+    let mut splits_by_transaction: HashMap<Uuid, Vec<TransactionSplit>> = HashMap::new();
+    for split in get_transaction_splits(&mut *conn, &ids).await? {
+        splits_by_transaction
+            .entry(split.transaction_id)
+            .or_default()
+            .push(split);
+    }
+    for transaction in unmatched.iter_mut() {
+        transaction.splits = splits_by_transaction
+            .remove(&transaction.id)
+            .unwrap_or_default();
+    }
+
+    Ok(ReconciliationSummary::new(
+        reconciliation,
+        balance - uncleared,
+        unmatched,
+    ))
+}
+
+/// Lock the cleared transactions covered by the reconciliation and mark it completed
+pub async fn complete_reconciliation(
+    conn: &mut PgConnection,
+    reconciliation: &Reconciliation,
+) -> Result<Reconciliation, sqlx::Error> {
+    sqlx::query(QUERY_RECONCILE_TRANSACTIONS)
+        .bind(reconciliation.asset_id)
+        .bind(reconciliation.cutoff())
+        .bind(reconciliation.id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query_as::<_, Reconciliation>(QUERY_COMPLETE)
+        .bind(reconciliation.id)
+        .fetch_one(&mut *conn)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use sqlx::{migrate::MigrateDatabase, Postgres};
+    use std::env;
+
+    use crate::models::{
+        is_reconciled_lock, ReconciliationStatus, TransactionStatus, TransactionType,
+    };
+    use crate::repository::{
+        apply_transaction_balance, create_category, create_transaction, delete_transaction,
+        update_transaction_status,
+    };
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to test DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn insert_account_and_asset(pool: &PgPool) -> (Uuid, Uuid) {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, $2, now(), now())")
+            .bind(user_id)
+            .bind(Decimal::ZERO)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let asset_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO assets (id, account_id, asset_type, balance, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, now(), now())",
+        )
+        .bind(asset_id)
+        .bind(user_id)
+        .bind("bank")
+        .bind(Decimal::new(1000, 0))
+        .execute(pool)
+        .await
+        .unwrap();
+
+        (user_id, asset_id)
+    }
+
+    async fn add_expense(
+        pool: &PgPool,
+        account_id: Uuid,
+        asset_id: Uuid,
+        amount: i64,
+        day: u32,
+    ) -> Transaction {
+        let mut db_tx = pool.begin().await.unwrap();
+        let transaction = create_transaction(
+            &mut *db_tx,
+            Some(asset_id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(amount, 0),
+            None,
+            Some(account_id),
+            None,
+            Some(Utc.with_ymd_and_hms(2025, 7, day, 12, 0, 0).unwrap()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &transaction)
+            .await
+            .unwrap();
+        db_tx.commit().await.unwrap();
+        transaction
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_and_lock() {
+        let pool = setup_test_db().await;
+        let (account_id, asset_id) = insert_account_and_asset(&pool).await;
+
+        let rent = add_expense(&pool, account_id, asset_id, 100, 1).await;
+        let groceries = add_expense(&pool, account_id, asset_id, 50, 2).await;
+        add_expense(&pool, account_id, asset_id, 30, 10).await;
+
+        // Only the rent made it onto the statement of July 5th
+        let reconciliation = create_reconciliation(
+            &pool,
+            asset_id,
+            NaiveDate::from_ymd_opt(2025, 7, 5).unwrap(),
+            Decimal::new(900, 0),
+        )
+        .await
+        .unwrap();
+        assert!(create_reconciliation(
+            &pool,
+            asset_id,
+            reconciliation.statement_date,
+            Decimal::ZERO
+        )
+        .await
+        .is_err());
+
+        let mut conn = pool.acquire().await.unwrap();
+        let summary = get_reconciliation_summary(&mut conn, reconciliation)
+            .await
+            .unwrap();
+        assert_eq!(summary.cleared_balance, Decimal::new(1000, 0));
+        assert_eq!(summary.difference, Decimal::new(-100, 0));
+        assert_eq!(summary.unmatched.len(), 2);
+
+        update_transaction_status(&pool, rent.id, TransactionStatus::Cleared)
+            .await
+            .unwrap();
+        let reconciliation = get_reconciliation_by_id(&pool, summary.reconciliation.id)
+            .await
+            .unwrap();
+        let summary = get_reconciliation_summary(&mut conn, reconciliation)
+            .await
+            .unwrap();
+        assert!(summary.is_balanced());
+        let unmatched: Vec<Uuid> = summary.unmatched.iter().map(|tx| tx.id).collect();
+        assert_eq!(unmatched, vec![groceries.id]);
+
+        let completed = complete_reconciliation(&mut conn, &summary.reconciliation)
+            .await
+            .unwrap();
+        assert_eq!(completed.status, ReconciliationStatus::Completed);
+
+        let (status, reconciliation_id): (TransactionStatus, Option<Uuid>) =
+            sqlx::query_as("SELECT status, reconciliation_id FROM transactions WHERE id = $1")
+                .bind(rent.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(status, TransactionStatus::Reconciled);
+        assert_eq!(reconciliation_id, Some(completed.id));
+
+        // The reconciled row can be categorised but not changed, un-cleared or deleted
+        let housing = create_category(&pool, account_id, None, "Housing")
+            .await
+            .unwrap();
+        sqlx::query("UPDATE transactions SET category_id = $2 WHERE id = $1")
+            .bind(rent.id)
+            .bind(housing.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let locked = |result: Result<_, sqlx::Error>| match result {
+            Err(sqlx::Error::Database(err)) => is_reconciled_lock(&*err),
+            _ => false,
+        };
+        assert!(locked(
+            sqlx::query("UPDATE transactions SET amount = 120 WHERE id = $1")
+                .bind(rent.id)
+                .execute(&pool)
+                .await
+                .map(|_| ())
+        ));
+        assert!(locked(
+            update_transaction_status(&pool, rent.id, TransactionStatus::Pending)
+                .await
+                .map(|_| ())
+        ));
+        assert!(locked(delete_transaction(&pool, rent.id).await));
+
+        // Pending rows stay editable
+        update_transaction_status(&pool, groceries.id, TransactionStatus::Cleared)
+            .await
+            .unwrap();
+    }
+}
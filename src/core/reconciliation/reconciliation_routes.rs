@@ -0,0 +1,34 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::reconciliation::reconciliation_handler::*, models::Backend};
+
+/// Defines routes for reconciling assets against bank statements
+pub fn reconciliation_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // POST /reconciliations -> Open a reconciliation with the statement date and ending balance
+        .route("/reconciliations", post(add_reconciliation_handler))
+        // GET    /reconciliations/{id} -> Cleared balance, difference and unmatched transactions
+        // DELETE /reconciliations/{id} -> Discard an open reconciliation
+        .route(
+            "/reconciliations/{id}",
+            get(get_reconciliation_handler).delete(delete_reconciliation_handler),
+        )
+        // POST /reconciliations/{id}/complete -> Lock the cleared transactions once balanced
+        .route(
+            "/reconciliations/{id}/complete",
+            post(complete_reconciliation_handler),
+        )
+        // GET /reconciliations/asset/{id} -> Reconciliations of an asset, latest first
+        .route(
+            "/reconciliations/asset/{id}",
+            get(get_reconciliations_handler),
+        )
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
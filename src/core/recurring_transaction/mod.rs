@@ -1,4 +1,6 @@
+pub mod recurrence_rule;
 pub mod recurring_transaction;
 pub mod recurring_transaction_repository;
 pub mod recurring_transaction_routes;
 pub mod recurringtransaction_handler;
+pub mod subscription;
@@ -0,0 +1,568 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+// Periods in a row without an occurrence after which a rule is taken to have none left,
+// e.g. FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30
+const MAX_EMPTY_PERIODS: u32 = 1000;
+
+/// How often a recurrence rule repeats (the `FREQ` of an RRULE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Allows Frequency to be written back in RRULE syntax
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A `BYDAY` entry: a weekday, or with an ordinal the n-th such weekday of the month
+/// (negative ordinals count from the end, e.g. `-1FR` is the last Friday)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+/// A recurrence rule in the syntax of RFC 5545 RRULEs, e.g.
+/// `FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1` for the last business day of each month.
+///
+/// Supports FREQ (DAILY, WEEKLY, MONTHLY, YEARLY), INTERVAL, BYDAY, BYMONTHDAY, BYMONTH and
+/// BYSETPOS, with weeks starting on Monday. The start, end and number of occurrences are
+/// kept on the recurring transaction rather than in DTSTART, UNTIL and COUNT.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+
+    /// Number of periods between two periods with occurrences, e.g. 2 for every other week
+    pub interval: u32,
+
+    pub by_day: Vec<WeekdayNum>,
+
+    /// Days of the month; negative ones count from the end, e.g. -1 for the last day
+    pub by_month_day: Vec<i32>,
+
+    pub by_month: Vec<u32>,
+
+    /// Which of a period's candidate dates occur, e.g. -1 for the last one
+    pub by_set_pos: Vec<i32>,
+}
+
+/// Why a recurrence rule could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRuleError(pub String);
+
+impl fmt::Display for RecurrenceRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid recurrence rule: {}", self.0)
+    }
+}
+
+impl std::error::Error for RecurrenceRuleError {}
+
+impl RecurrenceRule {
+    /// Occurrences at or after `start`, in order and at the time of day of `start`.
+    /// Periods (days, weeks, months or years) are counted from the one containing `start`.
+    pub fn occurrences(
+        &self,
+        start: DateTime<Utc>,
+    ) -> impl Iterator<Item = DateTime<Utc>> + Send + 'static {
+        let rule = self.clone();
+        let time = start.time();
+        let start_date = start.date_naive();
+        let mut period = 0;
+        let mut empty_periods = 0;
+        let mut pending: VecDeque<NaiveDate> = VecDeque::new();
+
+        std::iter::from_fn(move || loop {
+            if let Some(date) = pending.pop_front() {
+                return Some(date.and_time(time).and_utc());
+            }
+            if empty_periods >= MAX_EMPTY_PERIODS {
+                return None;
+            }
+
+            // Runs out past the end of the calendar
+            let dates = rule.period_dates(start_date, period)?;
+            period += 1;
+            pending.extend(dates.into_iter().filter(|date| *date >= start_date));
+            empty_periods = if pending.is_empty() {
+                empty_periods + 1
+            } else {
+                0
+            };
+        })
+    }
+
+    /// Dates of the `period`-th period with occurrences, counted from the one containing `start`
+    fn period_dates(&self, start: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let step = period.checked_mul(self.interval)?;
+
+        let candidates = match self.frequency {
+            Frequency::Daily => {
+                let day = start.checked_add_days(Days::new(step.into()))?;
+                vec![day]
+                    .into_iter()
+                    .filter(|day| self.in_months(*day) && self.matches_day(*day))
+                    .collect()
+            }
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub_days(Days::new(start.weekday().num_days_from_monday().into()))?
+                    .checked_add_days(Days::new(u64::from(step) * 7))?;
+                let mut days: Vec<NaiveDate> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|day| day.weekday).collect()
+                }
+                .into_iter()
+                .filter_map(|weekday| {
+                    monday.checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+                })
+                .filter(|day| self.in_months(*day))
+                .collect();
+                days.sort();
+                days.dedup();
+                days
+            }
+            Frequency::Monthly => {
+                let first = start.with_day(1)?.checked_add_months(Months::new(step))?;
+                if self.in_months(first) {
+                    self.month_dates(first, start.day())
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Yearly => {
+                let year = start.year().checked_add(i32::try_from(step).ok()?)?;
+                // Day rules without months pick days across the whole year
+                let mut months = if !self.by_month.is_empty() {
+                    self.by_month.clone()
+                } else if !self.by_day.is_empty() || !self.by_month_day.is_empty() {
+                    (1..=12).collect()
+                } else {
+                    vec![start.month()]
+                };
+                months.sort();
+                months.dedup();
+                months
+                    .into_iter()
+                    .filter_map(|month| NaiveDate::from_ymd_opt(year, month, 1))
+                    .flat_map(|first| self.month_dates(first, start.day()))
+                    .collect()
+            }
+        };
+
+        Some(self.select_set_positions(candidates))
+    }
+
+    /// Days of the month starting at `first` that match the day rules, or the
+    /// `default_day` of it when there are none (nothing when the month is too short)
+    fn month_dates(&self, first: NaiveDate, default_day: u32) -> Vec<NaiveDate> {
+        if self.by_day.is_empty() && self.by_month_day.is_empty() {
+            return first.with_day(default_day).into_iter().collect();
+        }
+
+        first
+            .iter_days()
+            .take_while(|day| day.month() == first.month())
+            .filter(|day| self.matches_day(*day))
+            .collect()
+    }
+
+    /// Whether a day matches `BYMONTHDAY` and `BYDAY`
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        let days_in_month = days_in_month(day);
+        let day_of_month = day.day() as i32;
+
+        let month_day_matches = self.by_month_day.is_empty()
+            || self.by_month_day.iter().any(|&n| {
+                if n > 0 {
+                    day_of_month == n
+                } else {
+                    day_of_month == days_in_month + 1 + n
+                }
+            });
+        let weekday_matches = self.by_day.is_empty()
+            || self.by_day.iter().any(|rule| {
+                rule.weekday == day.weekday()
+                    && match rule.ordinal {
+                        None => true,
+                        Some(n) if n > 0 => (day_of_month - 1) / 7 + 1 == n,
+                        Some(n) => (days_in_month - day_of_month) / 7 + 1 == -n,
+                    }
+            });
+
+        month_day_matches && weekday_matches
+    }
+
+    fn in_months(&self, day: NaiveDate) -> bool {
+        self.by_month.is_empty() || self.by_month.contains(&day.month())
+    }
+
+    /// Keep the candidates at the `BYSETPOS` positions, or all of them without any
+    fn select_set_positions(&self, candidates: Vec<NaiveDate>) -> Vec<NaiveDate> {
+        if self.by_set_pos.is_empty() {
+            return candidates;
+        }
+
+        let count = candidates.len() as i32;
+        let mut selected: Vec<NaiveDate> = self
+            .by_set_pos
+            .iter()
+            .map(|&position| {
+                if position > 0 {
+                    position - 1
+                } else {
+                    count + position
+                }
+            })
+            .filter(|index| (0..count).contains(index))
+            .map(|index| candidates[index as usize])
+            .collect();
+        selected.sort();
+        selected.dedup();
+        selected
+    }
+}
+
+/// Parses rules like `FREQ=WEEKLY;INTERVAL=2`, with or without a leading `RRULE:`
+impl FromStr for RecurrenceRule {
+    type Err = RecurrenceRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut frequency = None;
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+        };
+
+        for part in s.split(';').filter(|part| !part.trim().is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected NAME=VALUE, got \"{}\"", part)))?;
+            let name = name.trim().to_ascii_uppercase();
+            let value = value.trim();
+
+            match name.as_str() {
+                "FREQ" => frequency = Some(parse_frequency(value)?),
+                "INTERVAL" => rule.interval = parse_number(&name, value, 1, i32::MAX)? as u32,
+                "BYDAY" => rule.by_day = parse_list(value, parse_weekday_num)?,
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_list(value, |v| parse_signed(&name, v, 31))?
+                }
+                "BYMONTH" => {
+                    rule.by_month =
+                        parse_list(value, |v| Ok(parse_number(&name, v, 1, 12)? as u32))?
+                }
+                "BYSETPOS" => rule.by_set_pos = parse_list(value, |v| parse_signed(&name, v, 366))?,
+                "COUNT" | "UNTIL" => {
+                    return Err(invalid(format!(
+                        "{} is not supported, use max_occurrences and ends_at",
+                        name
+                    )))
+                }
+                _ => return Err(invalid(format!("unsupported part {}", name))),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| invalid("FREQ is required".to_string()))?;
+
+        if rule.frequency == Frequency::Weekly && !rule.by_month_day.is_empty() {
+            return Err(invalid(
+                "BYMONTHDAY can't be used with FREQ=WEEKLY".to_string(),
+            ));
+        }
+        let ordinals_allowed = rule.frequency == Frequency::Monthly
+            || (rule.frequency == Frequency::Yearly && !rule.by_month.is_empty());
+        if !ordinals_allowed && rule.by_day.iter().any(|day| day.ordinal.is_some()) {
+            return Err(invalid(
+                "numbered BYDAY entries need FREQ=MONTHLY, or FREQ=YEARLY with BYMONTH".to_string(),
+            ));
+        }
+        if !rule.by_set_pos.is_empty()
+            && rule.by_day.is_empty()
+            && rule.by_month_day.is_empty()
+            && rule.by_month.is_empty()
+        {
+            return Err(invalid(
+                "BYSETPOS needs BYDAY, BYMONTHDAY or BYMONTH".to_string(),
+            ));
+        }
+
+        Ok(rule)
+    }
+}
+
+impl TryFrom<String> for RecurrenceRule {
+    type Error = RecurrenceRuleError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RecurrenceRule> for String {
+    fn from(rule: RecurrenceRule) -> Self {
+        rule.to_string()
+    }
+}
+
+/// Writes the rule back in RRULE syntax, e.g. as stored in the database
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|day| match day.ordinal {
+                    Some(n) => format!("{}{}", n, weekday_code(day.weekday)),
+                    None => weekday_code(day.weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            write!(f, ";BYMONTHDAY={}", join(&self.by_month_day))?;
+        }
+        if !self.by_month.is_empty() {
+            write!(f, ";BYMONTH={}", join(&self.by_month))?;
+        }
+        if !self.by_set_pos.is_empty() {
+            write!(f, ";BYSETPOS={}", join(&self.by_set_pos))?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> RecurrenceRuleError {
+    RecurrenceRuleError(message)
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn days_in_month(day: NaiveDate) -> i32 {
+    let first = day.with_day(1).unwrap_or(day);
+    first
+        .checked_add_months(Months::new(1))
+        .map(|next| next.signed_duration_since(first).num_days() as i32)
+        .unwrap_or(31)
+}
+
+fn parse_frequency(value: &str) -> Result<Frequency, RecurrenceRuleError> {
+    match value.to_ascii_uppercase().as_str() {
+        "DAILY" => Ok(Frequency::Daily),
+        "WEEKLY" => Ok(Frequency::Weekly),
+        "MONTHLY" => Ok(Frequency::Monthly),
+        "YEARLY" => Ok(Frequency::Yearly),
+        _ => Err(invalid(format!("unsupported FREQ {}", value))),
+    }
+}
+
+fn parse_list<T>(
+    value: &str,
+    parse: impl Fn(&str) -> Result<T, RecurrenceRuleError>,
+) -> Result<Vec<T>, RecurrenceRuleError> {
+    value.split(',').map(|item| parse(item.trim())).collect()
+}
+
+/// A number between `min` and `max`
+fn parse_number(name: &str, value: &str, min: i32, max: i32) -> Result<i32, RecurrenceRuleError> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|n| (min..=max).contains(n))
+        .ok_or_else(|| {
+            invalid(format!(
+                "{} must be between {} and {}, got {}",
+                name, min, max, value
+            ))
+        })
+}
+
+/// A non-zero number between `-max` and `max`
+fn parse_signed(name: &str, value: &str, max: i32) -> Result<i32, RecurrenceRuleError> {
+    match parse_number(name, value, -max, max)? {
+        0 => Err(invalid(format!("{} can't be 0", name))),
+        n => Ok(n),
+    }
+}
+
+/// A `BYDAY` entry such as `MO`, `2TU` or `-1FR`
+fn parse_weekday_num(value: &str) -> Result<WeekdayNum, RecurrenceRuleError> {
+    let code_start = value.len().saturating_sub(2);
+    let (ordinal, code) = value.split_at(code_start);
+    let weekday = match code.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return Err(invalid(format!("unknown BYDAY entry {}", value))),
+    };
+    let ordinal = match ordinal.trim_start_matches('+') {
+        "" => None,
+        n => Some(parse_signed("BYDAY ordinal", n, 5)?),
+    };
+    Ok(WeekdayNum { ordinal, weekday })
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 9, 0, 0).unwrap()
+    }
+
+    fn first(rule: &str, start: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+        rule.parse::<RecurrenceRule>()
+            .unwrap()
+            .occurrences(start)
+            .take(n)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let rule: RecurrenceRule = "RRULE:freq=monthly;interval=1;byday=mo,tu,we,th,fr;bysetpos=-1"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.frequency, Frequency::Monthly);
+        assert_eq!(rule.by_day.len(), 5);
+        assert_eq!(
+            rule.to_string(),
+            "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1"
+        );
+
+        let rule: RecurrenceRule = "FREQ=YEARLY;BYMONTH=11;BYDAY=4TH".parse().unwrap();
+        assert_eq!(rule.to_string(), "FREQ=YEARLY;BYDAY=4TH;BYMONTH=11");
+
+        for invalid in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYDAY=XX",
+            "FREQ=MONTHLY;BYSETPOS=1",
+            "FREQ=DAILY;COUNT=3",
+            "FREQ=DAILY;WKST=SU",
+        ] {
+            assert!(invalid.parse::<RecurrenceRule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_occurrences_of_common_bills() {
+        // Every 2 weeks
+        assert_eq!(
+            first("FREQ=WEEKLY;INTERVAL=2", at(2025, 1, 3), 3),
+            vec![at(2025, 1, 3), at(2025, 1, 17), at(2025, 1, 31)]
+        );
+        // Quarterly, from the start's day of month
+        assert_eq!(
+            first("FREQ=MONTHLY;INTERVAL=3", at(2025, 1, 15), 3),
+            vec![at(2025, 1, 15), at(2025, 4, 15), at(2025, 7, 15)]
+        );
+        // Yearly, skipping years without the date
+        assert_eq!(
+            first("FREQ=YEARLY", at(2024, 2, 29), 2),
+            vec![at(2024, 2, 29), at(2028, 2, 29)]
+        );
+        // Months without a 31st are skipped
+        assert_eq!(
+            first("FREQ=MONTHLY", at(2025, 1, 31), 3),
+            vec![at(2025, 1, 31), at(2025, 3, 31), at(2025, 5, 31)]
+        );
+        // Last business day of the month
+        assert_eq!(
+            first(
+                "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+                at(2025, 5, 1),
+                3
+            ),
+            vec![at(2025, 5, 30), at(2025, 6, 30), at(2025, 7, 31)]
+        );
+        // The 25th, or the Friday before when it falls on a weekend
+        assert_eq!(
+            first(
+                "FREQ=MONTHLY;BYMONTHDAY=23,24,25;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+                at(2025, 1, 1),
+                6
+            ),
+            vec![
+                at(2025, 1, 24),
+                at(2025, 2, 25),
+                at(2025, 3, 25),
+                at(2025, 4, 25),
+                at(2025, 5, 23),
+                at(2025, 6, 25)
+            ]
+        );
+        // Last Friday and second Monday of the month
+        assert_eq!(
+            first("FREQ=MONTHLY;BYDAY=-1FR,2MO", at(2025, 1, 1), 3),
+            vec![at(2025, 1, 13), at(2025, 1, 31), at(2025, 2, 10)]
+        );
+        // Weekdays, Monday to Friday
+        assert_eq!(
+            first("FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR", at(2025, 1, 3), 2),
+            vec![at(2025, 1, 3), at(2025, 1, 6)]
+        );
+    }
+
+    #[test]
+    fn test_rule_without_occurrences_ends() {
+        let rule: RecurrenceRule = "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30".parse().unwrap();
+        assert_eq!(rule.occurrences(at(2025, 1, 1)).next(), None);
+    }
+}
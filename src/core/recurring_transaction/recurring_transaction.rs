@@ -1,12 +1,16 @@
-use chrono::{DateTime, Utc};
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Duration, Months, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::fmt;
 use uuid::Uuid;
 
+use super::recurrence_rule::{Frequency, RecurrenceRule, RecurrenceRuleError};
+use crate::models::{Transaction, TransactionType};
+
 /// Defines the interval at which a recurring transaction is executed
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
 #[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
 pub enum IntervalChoices {
     /// Transaction recurs daily
@@ -29,14 +33,75 @@ impl fmt::Display for IntervalChoices {
     }
 }
 
-/// Specifies whether a recurring transaction is income or expense
-#[derive(Debug, Serialize, Deserialize, sqlx::Type)]
+impl IntervalChoices {
+    /// The execution following one at `from`.
+    /// Monthly runs fall on the same day of the next month, or on its last day when it is shorter.
+    pub fn advance(&self, from: DateTime<Utc>) -> DateTime<Utc> {
+        self.nth_from(from, 1).unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// The `n`-th execution after one at `anchor`; None past the representable range.
+    /// Monthly runs keep the anchor's day, or fall on the month's last day when it is shorter.
+    pub fn nth_from(&self, anchor: DateTime<Utc>, n: u32) -> Option<DateTime<Utc>> {
+        match self {
+            IntervalChoices::Daily => anchor.checked_add_signed(Duration::days(n.into())),
+            IntervalChoices::Weekly => anchor.checked_add_signed(Duration::weeks(n.into())),
+            IntervalChoices::Monthly => anchor.checked_add_months(Months::new(n)),
+        }
+    }
+}
+
+/// How long after its scheduled time an occurrence still counts as on time rather than missed
+pub const MISSED_RUN_GRACE: Duration = Duration::hours(1);
+
+/// What to do with occurrences missed while the server was down
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy, Default)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum MissedRunPolicy {
+    /// Post every missed occurrence at its scheduled time
+    #[default]
+    CatchUpAll,
+    /// Post only the most recent due occurrence and skip the older ones
+    LatestOnly,
+    /// Skip missed occurrences and wait for the next one
+    Skip,
+}
+
+impl MissedRunPolicy {
+    /// Whether a due occurrence is posted at `now`; `latest` tells if it is the last one due
+    pub fn posts(&self, scheduled_at: DateTime<Utc>, latest: bool, now: DateTime<Utc>) -> bool {
+        match self {
+            MissedRunPolicy::CatchUpAll => true,
+            MissedRunPolicy::LatestOnly => latest,
+            MissedRunPolicy::Skip => now - scheduled_at <= MISSED_RUN_GRACE,
+        }
+    }
+}
+
+/// Specifies whether a recurring transaction is an income, an expense or a transfer
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
 #[repr(i32)] // Stores the enum as an integer in the database
 pub enum RecurringTransactionType {
     /// Incoming funds (e.g., salary)
     Income = 1,
     /// Outgoing funds (e.g., subscription fee)
     Expense = 2,
+    /// Standing order to an asset of another account
+    Transfer = 3,
+    /// Standing order between two assets of the same account (e.g., to savings)
+    InternalTransfer = 4,
+}
+
+/// The type of the transactions a recurring transaction posts
+impl From<RecurringTransactionType> for TransactionType {
+    fn from(kind: RecurringTransactionType) -> Self {
+        match kind {
+            RecurringTransactionType::Income => TransactionType::Income,
+            RecurringTransactionType::Expense => TransactionType::Expense,
+            RecurringTransactionType::Transfer => TransactionType::Transfer,
+            RecurringTransactionType::InternalTransfer => TransactionType::InternalTransfer,
+        }
+    }
 }
 
 /// Represents a recurring financial transaction tied to an account and asset
@@ -48,27 +113,333 @@ pub struct RecurringTransaction {
     /// ID of the account associated with the transaction
     pub account_id: Uuid,
 
-    /// ID of the asset (e.g., cash, bank) associated with the transaction
+    /// ID of the asset (e.g., cash, bank) associated with the transaction;
+    /// the source of expenses and transfers, the destination of income
     pub asset_id: Uuid,
 
+    /// Destination asset of a transfer
+    pub to_asset_id: Option<Uuid>,
+
+    /// Account of the destination asset of a transfer, when not `account_id`
+    pub to_account_id: Option<Uuid>,
+
     /// Amount of the transaction
     pub amount: Decimal,
 
+    /// Fee paid from the source on each execution
+    pub fee: Decimal,
+
     /// Interval at which the transaction recurs (daily, weekly, monthly)
     pub interval: IntervalChoices,
 
     /// The next scheduled execution datetime of the transaction
     pub next_execution: DateTime<Utc>,
 
-    /// Whether the transaction is an income or an expense
+    /// Whether the transaction is an income, an expense or a transfer
     pub transaction_type: RecurringTransactionType,
 
     /// Indicates if the transaction is currently active
     pub is_active: bool,
 
+    /// Category given to the transactions it produces
+    pub category_id: Option<Uuid>,
+
+    /// Payee given to the transactions it produces
+    pub payee_id: Option<Uuid>,
+
+    /// Notes copied onto the transactions it produces
+    pub notes: Option<String>,
+
+    /// RRULE-style rule the executions follow, in place of `interval`
+    pub recurrence_rule: Option<String>,
+
+    /// When the recurrence starts; the rule's periods are counted from here
+    pub starts_at: DateTime<Utc>,
+
+    /// No executions are scheduled after this time
+    pub ends_at: Option<DateTime<Utc>>,
+
+    /// Total number of executions, after which the transaction is deactivated
+    pub max_occurrences: Option<i32>,
+
+    /// What to do with occurrences missed while the server was down
+    pub missed_run_policy: MissedRunPolicy,
+
     /// When the transaction record was created
     pub created_at: DateTime<Utc>,
 
     /// When the transaction record was last updated
     pub updated_at: DateTime<Utc>,
 }
+
+impl RecurringTransaction {
+    /// The transaction an execution at `scheduled_at` posts
+    pub fn transaction_at(&self, scheduled_at: DateTime<Utc>) -> Transaction {
+        let (from_asset_id, to_asset_id, from_account_id, to_account_id) = match self
+            .transaction_type
+        {
+            RecurringTransactionType::Income => {
+                (None, Some(self.asset_id), None, Some(self.account_id))
+            }
+            RecurringTransactionType::Expense => {
+                (Some(self.asset_id), None, Some(self.account_id), None)
+            }
+            RecurringTransactionType::Transfer | RecurringTransactionType::InternalTransfer => (
+                Some(self.asset_id),
+                self.to_asset_id,
+                Some(self.account_id),
+                Some(self.to_account_id.unwrap_or(self.account_id)),
+            ),
+        };
+
+        Transaction {
+            from_asset_id,
+            to_asset_id,
+            transaction_type: self.transaction_type.into(),
+            amount: self.amount,
+            fee: self.fee,
+            from_account_id,
+            to_account_id,
+            transaction_time: Some(scheduled_at),
+            category_id: self.category_id,
+            payee_id: self.payee_id,
+            notes: self.notes.clone(),
+            ..Transaction::default()
+        }
+    }
+
+    /// Scheduled executions from `next_execution` on, up to `ends_at`
+    pub fn upcoming_executions(
+        &self,
+    ) -> Result<Box<dyn Iterator<Item = DateTime<Utc>> + Send>, RecurrenceRuleError> {
+        self.executions_from(self.next_execution)
+    }
+
+    /// Scheduled executions from `from` on, up to `ends_at`
+    pub fn executions_from(
+        &self,
+        from: DateTime<Utc>,
+    ) -> Result<Box<dyn Iterator<Item = DateTime<Utc>> + Send>, RecurrenceRuleError> {
+        let rule = self
+            .recurrence_rule
+            .as_deref()
+            .map(str::parse::<RecurrenceRule>)
+            .transpose()?;
+        Ok(executions(
+            self.interval,
+            rule.as_ref(),
+            self.starts_at,
+            self.ends_at,
+            from,
+        ))
+    }
+}
+
+/// A recurring transaction as submitted by the client
+#[derive(Debug, Deserialize)]
+pub struct NewRecurringTransaction {
+    pub account_id: Uuid,
+    pub asset_id: Uuid,
+    pub to_asset_id: Option<Uuid>,
+    pub to_account_id: Option<Uuid>,
+    pub amount: Decimal,
+    #[serde(default)]
+    pub fee: Decimal,
+    pub transaction_type: RecurringTransactionType,
+    pub category_id: Option<Uuid>,
+    pub payee_id: Option<Uuid>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub missed_run_policy: MissedRunPolicy,
+    /// `interval` and/or `recurrence_rule`, with optional start, end and occurrence limit
+    #[serde(flatten)]
+    pub schedule: RecurrenceSchedule,
+}
+
+/// Fields of a recurring transaction to change; those left out are kept
+#[derive(Debug, Deserialize, Default)]
+pub struct RecurringTransactionUpdate {
+    pub amount: Option<Decimal>,
+    pub fee: Option<Decimal>,
+    pub next_execution: Option<DateTime<Utc>>,
+    pub is_active: Option<bool>,
+    pub category_id: Option<Uuid>,
+    pub missed_run_policy: Option<MissedRunPolicy>,
+    #[serde(flatten)]
+    pub schedule: RecurrenceSchedule,
+}
+
+/// Outcome of one scheduled occurrence of a recurring transaction
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum RunOutcome {
+    /// The occurrence's transaction was posted
+    Posted,
+    /// Missed and not posted, following the item's `MissedRunPolicy`
+    Skipped,
+    /// Posting failed; the occurrence is retried on the next run
+    Failed,
+}
+
+/// An entry of the execution log of a recurring transaction
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RecurringTransactionRun {
+    pub id: Uuid,
+    pub recurring_transaction_id: Uuid,
+
+    /// When the occurrence was due
+    pub scheduled_at: DateTime<Utc>,
+
+    /// When it was handled (for failures, the last attempt)
+    pub executed_at: DateTime<Utc>,
+
+    /// Transaction the occurrence posted; None when skipped, failed or since deleted
+    pub transaction_id: Option<Uuid>,
+
+    pub outcome: RunOutcome,
+
+    /// Why the last attempt failed
+    pub error: Option<String>,
+}
+
+/// Execution log of a recurring transaction together with its missed-run policy
+#[derive(Debug, Serialize)]
+pub struct RecurringTransactionRuns {
+    pub recurring_transaction_id: Uuid,
+    pub missed_run_policy: MissedRunPolicy,
+
+    /// Newest first
+    pub runs: Vec<RecurringTransactionRun>,
+}
+
+/// Allows RecurringTransactionRuns to be returned directly as a JSON HTTP response
+impl IntoResponse for RecurringTransactionRuns {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// When a recurring transaction runs: every `interval`, or following an RRULE-style rule,
+/// from `starts_at` (default now) until `ends_at`, at most `max_occurrences` times
+#[derive(Debug, Deserialize, Default)]
+pub struct RecurrenceSchedule {
+    /// Defaults to the frequency closest to the rule's
+    pub interval: Option<IntervalChoices>,
+    pub recurrence_rule: Option<RecurrenceRule>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub max_occurrences: Option<i32>,
+}
+
+impl RecurrenceSchedule {
+    /// The `interval` stored with the schedule; None when neither it nor a rule is given
+    pub fn interval(&self) -> Option<IntervalChoices> {
+        self.interval.or_else(|| {
+            self.recurrence_rule
+                .as_ref()
+                .map(|rule| match rule.frequency {
+                    Frequency::Daily => IntervalChoices::Daily,
+                    Frequency::Weekly => IntervalChoices::Weekly,
+                    Frequency::Monthly | Frequency::Yearly => IntervalChoices::Monthly,
+                })
+        })
+    }
+
+    /// The first scheduled execution at or after `starts_at`, or `now` when no start is given.
+    /// None when the schedule has none.
+    pub fn first_execution(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let starts_at = self.starts_at.unwrap_or(now);
+        executions(
+            self.interval()?,
+            self.recurrence_rule.as_ref(),
+            starts_at,
+            self.ends_at,
+            starts_at,
+        )
+        .next()
+    }
+}
+
+/// Executions from `from` on: those of `rule` when given, or else every `interval`, both
+/// counted from `starts_at`; none after `ends_at`
+fn executions(
+    interval: IntervalChoices,
+    rule: Option<&RecurrenceRule>,
+    starts_at: DateTime<Utc>,
+    ends_at: Option<DateTime<Utc>>,
+    from: DateTime<Utc>,
+) -> Box<dyn Iterator<Item = DateTime<Utc>> + Send> {
+    let scheduled: Box<dyn Iterator<Item = DateTime<Utc>> + Send> = match rule {
+        Some(rule) => Box::new(rule.occurrences(starts_at).skip_while(move |at| *at < from)),
+        None => {
+            // Counting each run from the start rather than from the one before keeps monthly
+            // runs on its day (Jan 31, Feb 28, Mar 31). A `from` off the schedule, such as a
+            // next execution moved by hand, starts a new one.
+            let runs = move |anchor| (0..).map_while(move |n| interval.nth_from(anchor, n));
+            let anchor = match runs(starts_at).find(|at| *at >= from) {
+                Some(at) if at == from => starts_at,
+                _ => from,
+            };
+            Box::new(runs(anchor).skip_while(move |at| *at < from))
+        }
+    };
+    Box::new(scheduled.take_while(move |at| ends_at.is_none_or(|ends_at| *at <= ends_at)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_interval_advance() {
+        let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 9, 0, 0).unwrap();
+
+        assert_eq!(
+            IntervalChoices::Daily.advance(at(2025, 2, 28)),
+            at(2025, 3, 1)
+        );
+        assert_eq!(
+            IntervalChoices::Weekly.advance(at(2025, 12, 29)),
+            at(2026, 1, 5)
+        );
+        assert_eq!(
+            IntervalChoices::Monthly.advance(at(2025, 1, 15)),
+            at(2025, 2, 15)
+        );
+        assert_eq!(
+            IntervalChoices::Monthly.advance(at(2025, 1, 31)),
+            at(2025, 2, 28)
+        );
+        assert_eq!(
+            IntervalChoices::Monthly.advance(at(2024, 1, 31)),
+            at(2024, 2, 29)
+        );
+    }
+
+    #[test]
+    fn test_monthly_executions_keep_the_start_day() {
+        let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 9, 0, 0).unwrap();
+        let starts_at = at(2025, 1, 31);
+        let monthly = |from| {
+            executions(IntervalChoices::Monthly, None, starts_at, None, from)
+                .take(3)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            monthly(starts_at),
+            vec![at(2025, 1, 31), at(2025, 2, 28), at(2025, 3, 31)]
+        );
+        // Resuming from a stored next execution that fell on a shorter month
+        assert_eq!(
+            monthly(at(2025, 2, 28)),
+            vec![at(2025, 2, 28), at(2025, 3, 31), at(2025, 4, 30)]
+        );
+        // A next execution moved off the schedule starts from there
+        assert_eq!(
+            monthly(at(2025, 2, 10)),
+            vec![at(2025, 2, 10), at(2025, 3, 10), at(2025, 4, 10)]
+        );
+    }
+}
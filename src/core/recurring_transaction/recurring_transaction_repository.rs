@@ -1,9 +1,17 @@
-use chrono::{DateTime, Utc};
-use rust_decimal::Decimal;
-use sqlx::{PgPool, Postgres, QueryBuilder};
+use chrono::{DateTime, Months, Utc};
+use sqlx::{Executor, PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
-use crate::models::{IntervalChoices, RecurringTransaction, RecurringTransactionType};
+use super::subscription::detect_subscriptions;
+use crate::core::transaction::transaction_validation::{check_transaction, TransactionError};
+use crate::models::{
+    NewRecurringTransaction, RecurringTransaction, RecurringTransactionRun,
+    RecurringTransactionRuns, RecurringTransactionUpdate, RunOutcome, SubscriptionSuggestion,
+    Transaction,
+};
+use crate::repository::{
+    apply_rules_to_transaction, apply_transaction_balance, create_transaction,
+};
 
 // SQL queries
 const QUERY_SELECT_ALL: &str = "SELECT * FROM recurring_transactions";
@@ -11,13 +19,72 @@ const QUERY_SELECT_ONE: &str = "SELECT * FROM recurring_transactions WHERE id =
 const QUERY_INSERT: &str = "
     INSERT INTO recurring_transactions (
         id, account_id, asset_id, amount, interval, 
-        next_execution, transaction_type, is_active, created_at, updated_at
+        next_execution, transaction_type, is_active, created_at, updated_at, category_id,
+        recurrence_rule, starts_at, ends_at, max_occurrences, to_asset_id, to_account_id, fee,
+        missed_run_policy, payee_id, notes
     ) VALUES (
-        $1, $2, $3, $4, $5, $6, $7, true, $9, $8
+        $1, $2, $3, $4, $5, $6, $7, $15, $9, $8, $10, $11, $12, $13, $14, $16, $17, $18, $19,
+        $20, $21
     )
     RETURNING *
 ";
 const QUERY_DELETE: &str = "DELETE FROM recurring_transactions WHERE id = $1";
+const QUERY_SELECT_DUE_IDS: &str = "
+    SELECT id FROM recurring_transactions
+    WHERE is_active AND next_execution <= $1
+    ORDER BY next_execution
+";
+// Skips rows another executor is working on; it advances them itself
+const QUERY_LOCK_DUE: &str = "
+    SELECT * FROM recurring_transactions
+    WHERE id = $1 AND is_active AND next_execution <= $2
+    FOR UPDATE SKIP LOCKED
+";
+// Returns nothing when the occurrence was posted or skipped before
+const QUERY_INSERT_RUN: &str = "
+    INSERT INTO recurring_transaction_runs (recurring_transaction_id, scheduled_at, outcome)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (recurring_transaction_id, scheduled_at) WHERE outcome <> 'Failed' DO NOTHING
+    RETURNING id
+";
+// Logs a failed attempt at the item's pending occurrence, keeping one row per occurrence
+const QUERY_UPSERT_FAILED_RUN: &str = "
+    INSERT INTO recurring_transaction_runs (recurring_transaction_id, scheduled_at, outcome, error)
+    SELECT id, next_execution, 'Failed', $2 FROM recurring_transactions WHERE id = $1
+    ON CONFLICT (recurring_transaction_id, scheduled_at) WHERE outcome = 'Failed'
+    DO UPDATE SET executed_at = now(), error = EXCLUDED.error
+";
+const QUERY_SELECT_RUNS: &str = "
+    SELECT * FROM recurring_transaction_runs
+    WHERE recurring_transaction_id = $1
+    ORDER BY scheduled_at DESC, executed_at DESC
+    LIMIT $2
+";
+const QUERY_UPDATE_RUN_TRANSACTION: &str =
+    "UPDATE recurring_transaction_runs SET transaction_id = $2 WHERE id = $1";
+// Only posted occurrences count towards `max_occurrences`
+const QUERY_COUNT_RUNS: &str = "
+    SELECT COUNT(*) FROM recurring_transaction_runs
+    WHERE recurring_transaction_id = $1 AND outcome = 'Posted'
+";
+// Without a next execution the schedule is over and the item is deactivated
+const QUERY_UPDATE_NEXT_EXECUTION: &str = "
+    UPDATE recurring_transactions
+    SET next_execution = COALESCE($2, next_execution),
+        is_active = is_active AND $2 IS NOT NULL,
+        updated_at = now()
+    WHERE id = $1
+    RETURNING *
+";
+const QUERY_SELECT_ACTIVE_BY_ACCOUNT_ID: &str =
+    "SELECT * FROM recurring_transactions WHERE account_id = $1 AND is_active";
+// Income and expenses of an account between $2 and $3
+const QUERY_SELECT_PAYMENT_HISTORY: &str = "
+    SELECT * FROM transactions
+    WHERE (from_account_id = $1 OR to_account_id = $1)
+      AND transaction_type IN (1, 2)
+      AND COALESCE(transaction_time, created_at) BETWEEN $2 AND $3
+";
 
 /// Fetch all recurring transactions from the database
 pub async fn get_recurring_transactions(
@@ -41,42 +108,81 @@ pub async fn get_recurring_transaction_by_id(
     Ok(recurring_transaction)
 }
 
-/// Create a new recurring transaction with the provided details
-pub async fn create_recurring_transaction(
-    pool: &PgPool,
-    account_id: Uuid,
-    asset_id: Uuid,
-    amount: Decimal,
-    interval: IntervalChoices,
-    transaction_type: RecurringTransactionType,
-) -> Result<RecurringTransaction, sqlx::Error> {
+/// Create a new recurring transaction with the provided details.
+/// Its first execution is the schedule's first at or after `starts_at` (default now);
+/// a schedule without any execution is created inactive.
+pub async fn create_recurring_transaction<'e, E>(
+    executor: E,
+    recurring: &NewRecurringTransaction,
+) -> Result<RecurringTransaction, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let now = Utc::now();
+    let schedule = &recurring.schedule;
+    let starts_at = schedule.starts_at.unwrap_or(now);
+    let next_execution = schedule.first_execution(now);
+
     let recurring_transaction = sqlx::query_as::<_, RecurringTransaction>(QUERY_INSERT)
         .bind(Uuid::new_v4()) // id
-        .bind(account_id)
-        .bind(asset_id)
-        .bind(amount)
-        .bind(interval)
-        .bind(Utc::now()) // next_execution (default to now)
-        .bind(transaction_type as i32)
-        .bind(Utc::now()) // updated_at
-        .bind(Utc::now()) // created_at
-        .fetch_one(pool)
+        .bind(recurring.account_id)
+        .bind(recurring.asset_id)
+        .bind(recurring.amount)
+        .bind(schedule.interval())
+        .bind(next_execution.unwrap_or(starts_at))
+        .bind(recurring.transaction_type as i32)
+        .bind(now) // updated_at
+        .bind(now) // created_at
+        .bind(recurring.category_id)
+        .bind(schedule.recurrence_rule.as_ref().map(ToString::to_string))
+        .bind(starts_at)
+        .bind(schedule.ends_at)
+        .bind(schedule.max_occurrences)
+        .bind(next_execution.is_some()) // is_active
+        .bind(recurring.to_asset_id)
+        .bind(recurring.to_account_id)
+        .bind(recurring.fee)
+        .bind(recurring.missed_run_policy)
+        .bind(recurring.payee_id)
+        .bind(&recurring.notes)
+        .fetch_one(executor)
         .await?;
 
     Ok(recurring_transaction)
 }
 
-/// Update fields of a recurring transaction such as amount, interval, execution time, or active status
+/// Update fields of a recurring transaction such as amount, schedule, execution time, or active status.
+/// A new rule or start moves `next_execution` to the new schedule's first execution from now on,
+/// unless one is given.
 pub async fn update_recurring_transaction_info(
     pool: &PgPool,
     transaction_id: Uuid,
-    amount: Option<Decimal>,
-    interval: Option<IntervalChoices>,
-    next_execution: Option<DateTime<Utc>>,
-    is_active: Option<bool>,
+    update: &RecurringTransactionUpdate,
 ) -> Result<RecurringTransaction, sqlx::Error> {
+    let RecurringTransactionUpdate {
+        amount,
+        fee,
+        next_execution,
+        is_active,
+        category_id,
+        missed_run_policy,
+        ref schedule,
+    } = *update;
+    let interval = schedule.interval();
+    let rescheduled = schedule.recurrence_rule.is_some() || schedule.starts_at.is_some();
+
     // If no fields are provided to update, return an error
-    if amount.is_none() && interval.is_none() && next_execution.is_none() && is_active.is_none() {
+    if amount.is_none()
+        && fee.is_none()
+        && interval.is_none()
+        && next_execution.is_none()
+        && is_active.is_none()
+        && category_id.is_none()
+        && missed_run_policy.is_none()
+        && !rescheduled
+        && schedule.ends_at.is_none()
+        && schedule.max_occurrences.is_none()
+    {
         return Err(sqlx::Error::RowNotFound);
     }
 
@@ -88,11 +194,40 @@ pub async fn update_recurring_transaction_info(
         builder.push(", ");
     }
 
+    if let Some(fee) = fee {
+        builder.push("fee = ").push_bind(fee);
+        builder.push(", ");
+    }
+
     if let Some(interval) = interval {
         builder.push("interval = ").push_bind(interval);
         builder.push(", ");
     }
 
+    if let Some(recurrence_rule) = &schedule.recurrence_rule {
+        builder
+            .push("recurrence_rule = ")
+            .push_bind(recurrence_rule.to_string());
+        builder.push(", ");
+    }
+
+    if let Some(starts_at) = schedule.starts_at {
+        builder.push("starts_at = ").push_bind(starts_at);
+        builder.push(", ");
+    }
+
+    if let Some(ends_at) = schedule.ends_at {
+        builder.push("ends_at = ").push_bind(ends_at);
+        builder.push(", ");
+    }
+
+    if let Some(max_occurrences) = schedule.max_occurrences {
+        builder
+            .push("max_occurrences = ")
+            .push_bind(max_occurrences);
+        builder.push(", ");
+    }
+
     if let Some(next_execution) = next_execution {
         builder.push("next_execution = ").push_bind(next_execution);
         builder.push(", ");
@@ -103,15 +238,45 @@ pub async fn update_recurring_transaction_info(
         builder.push(", ");
     }
 
+    if let Some(category_id) = category_id {
+        builder.push("category_id = ").push_bind(category_id);
+        builder.push(", ");
+    }
+
+    if let Some(missed_run_policy) = missed_run_policy {
+        builder
+            .push("missed_run_policy = ")
+            .push_bind(missed_run_policy);
+        builder.push(", ");
+    }
+
     // Always update the timestamp
     builder.push("updated_at = ").push_bind(Utc::now());
 
     builder.push(" WHERE id = ").push_bind(transaction_id);
     builder.push(" RETURNING *");
 
-    let query = builder.build_query_as::<RecurringTransaction>();
-    let recurring_transaction = query.fetch_one(pool).await?;
+    let mut db_tx = pool.begin().await?;
+    let mut recurring_transaction = builder
+        .build_query_as::<RecurringTransaction>()
+        .fetch_one(&mut *db_tx)
+        .await?;
+
+    if rescheduled && next_execution.is_none() {
+        let from = recurring_transaction.starts_at.max(Utc::now());
+        let next_execution = recurring_transaction
+            .executions_from(from)
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?
+            .next();
+        recurring_transaction =
+            sqlx::query_as::<_, RecurringTransaction>(QUERY_UPDATE_NEXT_EXECUTION)
+                .bind(transaction_id)
+                .bind(next_execution)
+                .fetch_one(&mut *db_tx)
+                .await?;
+    }
 
+    db_tx.commit().await?;
     Ok(recurring_transaction)
 }
 
@@ -128,10 +293,273 @@ pub async fn delete_recurring_transaction(
     Ok(())
 }
 
+/// Execute every active recurring transaction due by `now`, each in its own database
+/// transaction so one failing item doesn't hold back the others.
+/// Returns the number of transactions posted.
+pub async fn execute_due_recurring_transactions(
+    pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<usize, sqlx::Error> {
+    let due_ids: Vec<Uuid> = sqlx::query_scalar(QUERY_SELECT_DUE_IDS)
+        .bind(now)
+        .fetch_all(pool)
+        .await?;
+
+    let mut posted = 0;
+    for recurring_transaction_id in due_ids {
+        let mut db_tx = pool.begin().await?;
+        match execute_recurring_transaction(&mut db_tx, recurring_transaction_id, now).await {
+            Ok(transaction_ids) => {
+                db_tx.commit().await?;
+                posted += transaction_ids.len();
+            }
+            Err(err) => {
+                eprintln!(
+                    "Failed to execute recurring transaction {}: {}",
+                    recurring_transaction_id, err
+                );
+                // Roll back before logging, so the failure is kept
+                drop(db_tx);
+                if let Err(log_err) =
+                    log_failed_run(pool, recurring_transaction_id, &err.to_string()).await
+                {
+                    eprintln!(
+                        "Failed to log the failed run of recurring transaction {}: {}",
+                        recurring_transaction_id, log_err
+                    );
+                }
+            }
+        }
+    }
+    Ok(posted)
+}
+
+/// Record a failed attempt at the pending occurrence of a recurring transaction
+async fn log_failed_run(
+    pool: &PgPool,
+    recurring_transaction_id: Uuid,
+    error: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(QUERY_UPSERT_FAILED_RUN)
+        .bind(recurring_transaction_id)
+        .bind(error)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Handle every occurrence of a recurring transaction scheduled up to `now` and move
+/// `next_execution` past `now`. Occurrences missed while the server was down are posted
+/// or skipped following the item's `MissedRunPolicy`.
+/// The item is deactivated once its schedule ends or its occurrences run out.
+///
+/// Each occurrence is recorded in `recurring_transaction_runs` before it is posted, so an
+/// occurrence that was posted or skipped before is never posted again.
+/// Returns the IDs of the posted transactions; nothing is done when the item is inactive,
+/// not yet due, or being executed elsewhere. An occurrence whose transaction fails
+/// validation is refused with `TransactionError::Invalid`.
+pub async fn execute_recurring_transaction(
+    conn: &mut PgConnection,
+    recurring_transaction_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Vec<Uuid>, TransactionError> {
+    let Some(recurring) = sqlx::query_as::<_, RecurringTransaction>(QUERY_LOCK_DUE)
+        .bind(recurring_transaction_id)
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut executed: i64 = sqlx::query_scalar(QUERY_COUNT_RUNS)
+        .bind(recurring.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let schedule = recurring
+        .upcoming_executions()
+        .map_err(|err| sqlx::Error::Decode(Box::new(err)))?;
+
+    let exhausted = |executed: i64| {
+        recurring
+            .max_occurrences
+            .is_some_and(|max| executed >= i64::from(max))
+    };
+
+    let mut due = Vec::new();
+    let mut next_execution = None;
+    for scheduled_at in schedule {
+        if scheduled_at > now {
+            next_execution = Some(scheduled_at);
+            break;
+        }
+        due.push(scheduled_at);
+    }
+
+    let mut transaction_ids = Vec::new();
+    for (i, &scheduled_at) in due.iter().enumerate() {
+        if exhausted(executed) {
+            break;
+        }
+        let posts = recurring
+            .missed_run_policy
+            .posts(scheduled_at, i + 1 == due.len(), now);
+        let outcome = if posts {
+            RunOutcome::Posted
+        } else {
+            RunOutcome::Skipped
+        };
+
+        let run_id: Option<Uuid> = sqlx::query_scalar(QUERY_INSERT_RUN)
+            .bind(recurring.id)
+            .bind(scheduled_at)
+            .bind(outcome)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        if let Some(run_id) = run_id.filter(|_| posts) {
+            let transaction_id = post_occurrence(conn, &recurring, scheduled_at).await?;
+            sqlx::query(QUERY_UPDATE_RUN_TRANSACTION)
+                .bind(run_id)
+                .bind(transaction_id)
+                .execute(&mut *conn)
+                .await?;
+            transaction_ids.push(transaction_id);
+            executed += 1;
+        }
+    }
+    if exhausted(executed) {
+        next_execution = None;
+    }
+
+    sqlx::query(QUERY_UPDATE_NEXT_EXECUTION)
+        .bind(recurring.id)
+        .bind(next_execution)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(transaction_ids)
+}
+
+/// The next `count` scheduled executions of an active recurring transaction,
+/// within its end date and remaining number of occurrences
+pub async fn preview_recurring_transaction(
+    pool: &PgPool,
+    transaction_id: Uuid,
+    count: usize,
+) -> Result<Vec<DateTime<Utc>>, sqlx::Error> {
+    let recurring = get_recurring_transaction_by_id(pool, transaction_id).await?;
+    if !recurring.is_active {
+        return Ok(Vec::new());
+    }
+
+    let count = match recurring.max_occurrences {
+        Some(max) => {
+            let executed: i64 = sqlx::query_scalar(QUERY_COUNT_RUNS)
+                .bind(transaction_id)
+                .fetch_one(pool)
+                .await?;
+            count.min((i64::from(max) - executed).max(0) as usize)
+        }
+        None => count,
+    };
+
+    Ok(recurring
+        .upcoming_executions()
+        .map_err(|err| sqlx::Error::Decode(Box::new(err)))?
+        .take(count)
+        .collect())
+}
+
+/// Create the transaction of one occurrence, dated at its scheduled time, and apply its
+/// balance effects
+async fn post_occurrence(
+    conn: &mut PgConnection,
+    recurring: &RecurringTransaction,
+    scheduled_at: DateTime<Utc>,
+) -> Result<Uuid, TransactionError> {
+    let template = recurring.transaction_at(scheduled_at);
+    let mut transaction = create_transaction(
+        &mut *conn,
+        template.from_asset_id,
+        template.to_asset_id,
+        template.transaction_type,
+        template.amount,
+        Some(template.fee),
+        template.from_account_id,
+        template.to_account_id,
+        template.transaction_time,
+        template.notes,
+        None,
+        template.category_id,
+        None,
+        template.payee_id,
+    )
+    .await?;
+    // The item may predate the transaction rules, or its assets may have moved since
+    check_transaction(conn, &transaction).await?;
+    apply_transaction_balance(conn, &transaction).await?;
+    apply_rules_to_transaction(conn, &mut transaction).await?;
+
+    Ok(transaction.id)
+}
+
+/// Suggest recurring transactions from the repeating payments of the last 13 months
+pub async fn get_subscription_suggestions(
+    pool: &PgPool,
+    account_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Vec<SubscriptionSuggestion>, sqlx::Error> {
+    let since = now
+        .checked_sub_months(Months::new(13))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let transactions = sqlx::query_as::<_, Transaction>(QUERY_SELECT_PAYMENT_HISTORY)
+        .bind(account_id)
+        .bind(since)
+        .bind(now)
+        .fetch_all(pool)
+        .await?;
+    let existing = sqlx::query_as::<_, RecurringTransaction>(QUERY_SELECT_ACTIVE_BY_ACCOUNT_ID)
+        .bind(account_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(detect_subscriptions(
+        account_id,
+        &transactions,
+        &existing,
+        now,
+    ))
+}
+
+/// The execution log of a recurring transaction, newest first, with its missed-run policy
+pub async fn get_recurring_transaction_runs(
+    pool: &PgPool,
+    transaction_id: Uuid,
+    limit: i64,
+) -> Result<RecurringTransactionRuns, sqlx::Error> {
+    let recurring = get_recurring_transaction_by_id(pool, transaction_id).await?;
+    let runs = sqlx::query_as::<_, RecurringTransactionRun>(QUERY_SELECT_RUNS)
+        .bind(transaction_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(RecurringTransactionRuns {
+        recurring_transaction_id: transaction_id,
+        missed_run_policy: recurring.missed_run_policy,
+        runs,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{IntervalChoices, RecurringTransactionType};
+    use crate::core::recurring_transaction::recurrence_rule::RecurrenceRule;
+    use crate::core::recurring_transaction::recurring_transaction::RecurrenceSchedule;
+    use crate::core::transaction::transaction_validation::TransactionValidationError;
+    use crate::models::{IntervalChoices, MissedRunPolicy, RecurringTransactionType};
+    use chrono::{Duration, TimeZone};
     use rust_decimal::Decimal;
     use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
     use std::env;
@@ -197,6 +625,36 @@ mod tests {
         (user_id, asset_id)
     }
 
+    fn every(interval: IntervalChoices) -> RecurrenceSchedule {
+        RecurrenceSchedule {
+            interval: Some(interval),
+            ..RecurrenceSchedule::default()
+        }
+    }
+
+    fn new_recurring(
+        account_id: Uuid,
+        asset_id: Uuid,
+        amount: Decimal,
+        transaction_type: RecurringTransactionType,
+        schedule: RecurrenceSchedule,
+    ) -> NewRecurringTransaction {
+        NewRecurringTransaction {
+            account_id,
+            asset_id,
+            to_asset_id: None,
+            to_account_id: None,
+            amount,
+            fee: Decimal::ZERO,
+            transaction_type,
+            category_id: None,
+            payee_id: None,
+            notes: None,
+            missed_run_policy: MissedRunPolicy::CatchUpAll,
+            schedule,
+        }
+    }
+
     #[tokio::test]
     async fn integration_test_recurring_transaction_crud() {
         let pool = setup_test_db().await;
@@ -205,11 +663,13 @@ mod tests {
 
         let created = create_recurring_transaction(
             &pool,
-            account_id,
-            asset_id,
-            Decimal::new(1500, 2),
-            IntervalChoices::Monthly,
-            RecurringTransactionType::Expense,
+            &new_recurring(
+                account_id,
+                asset_id,
+                Decimal::new(1500, 2),
+                RecurringTransactionType::Expense,
+                every(IntervalChoices::Monthly),
+            ),
         )
         .await
         .unwrap();
@@ -226,10 +686,12 @@ mod tests {
         let updated = update_recurring_transaction_info(
             &pool,
             created.id,
-            Some(Decimal::new(2000, 2)),
-            Some(IntervalChoices::Weekly),
-            None,
-            Some(false),
+            &RecurringTransactionUpdate {
+                amount: Some(Decimal::new(2000, 2)),
+                is_active: Some(false),
+                schedule: every(IntervalChoices::Weekly),
+                ..RecurringTransactionUpdate::default()
+            },
         )
         .await
         .unwrap();
@@ -245,4 +707,459 @@ mod tests {
         let result = get_recurring_transaction_by_id(&pool, created.id).await;
         assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
     }
+
+    #[tokio::test]
+    async fn test_execute_recurring_transaction_catches_up_once() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        let (account_id, asset_id) = setup_account_and_asset(&pool, user_id).await;
+        let now = Utc::now();
+        let first_run = now - chrono::Duration::days(3) + chrono::Duration::hours(1);
+
+        let created = create_recurring_transaction(
+            &pool,
+            &new_recurring(
+                account_id,
+                asset_id,
+                Decimal::new(1500, 2),
+                RecurringTransactionType::Expense,
+                every(IntervalChoices::Daily),
+            ),
+        )
+        .await
+        .unwrap();
+        update_recurring_transaction_info(
+            &pool,
+            created.id,
+            &RecurringTransactionUpdate {
+                next_execution: Some(first_run),
+                ..RecurringTransactionUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // The three runs missed since `first_run` are all posted, at their scheduled times
+        let mut conn = pool.acquire().await.unwrap();
+        let posted = execute_recurring_transaction(&mut conn, created.id, now)
+            .await
+            .unwrap();
+        assert_eq!(posted.len(), 3);
+
+        let times: Vec<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT transaction_time FROM transactions WHERE from_asset_id = $1 ORDER BY transaction_time",
+        )
+        .bind(asset_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(times.len(), 3);
+        assert_eq!(times[0].timestamp(), first_run.timestamp());
+
+        let balance: Decimal = sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(balance, Decimal::new(500, 2));
+
+        let executed = get_recurring_transaction_by_id(&pool, created.id)
+            .await
+            .unwrap();
+        assert!(executed.next_execution > now);
+
+        // Running again, even from a stale `next_execution`, posts nothing twice
+        assert!(execute_recurring_transaction(&mut conn, created.id, now)
+            .await
+            .unwrap()
+            .is_empty());
+        update_recurring_transaction_info(
+            &pool,
+            created.id,
+            &RecurringTransactionUpdate {
+                next_execution: Some(first_run),
+                ..RecurringTransactionUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(execute_recurring_transaction(&mut conn, created.id, now)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let runs: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM recurring_transaction_runs WHERE recurring_transaction_id = $1",
+        )
+        .bind(created.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(runs, 3);
+    }
+
+    #[tokio::test]
+    async fn test_recurrence_rule_schedule_and_limit() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        let (account_id, asset_id) = setup_account_and_asset(&pool, user_id).await;
+        let starts_at = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+
+        let rule: RecurrenceRule = "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1"
+            .parse()
+            .unwrap();
+        let schedule = RecurrenceSchedule {
+            recurrence_rule: Some(rule),
+            starts_at: Some(starts_at),
+            max_occurrences: Some(2),
+            ..RecurrenceSchedule::default()
+        };
+        let created = create_recurring_transaction(
+            &pool,
+            &new_recurring(
+                account_id,
+                asset_id,
+                Decimal::new(1000, 2),
+                RecurringTransactionType::Income,
+                schedule,
+            ),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.interval, IntervalChoices::Monthly);
+        let last_business_days = vec![
+            Utc.with_ymd_and_hms(2025, 1, 31, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2025, 2, 28, 0, 0, 0).unwrap(),
+        ];
+        assert_eq!(created.next_execution, last_business_days[0]);
+
+        // The preview stops at the occurrence limit
+        let preview = preview_recurring_transaction(&pool, created.id, 10)
+            .await
+            .unwrap();
+        assert_eq!(preview, last_business_days);
+
+        let mut conn = pool.acquire().await.unwrap();
+        let posted = execute_recurring_transaction(&mut conn, created.id, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(posted.len(), 2);
+
+        let executed = get_recurring_transaction_by_id(&pool, created.id)
+            .await
+            .unwrap();
+        assert!(!executed.is_active);
+        assert!(preview_recurring_transaction(&pool, created.id, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // An end before the start is refused
+        let refused = create_recurring_transaction(
+            &pool,
+            &new_recurring(
+                account_id,
+                asset_id,
+                Decimal::new(1000, 2),
+                RecurringTransactionType::Income,
+                RecurrenceSchedule {
+                    interval: Some(IntervalChoices::Daily),
+                    starts_at: Some(starts_at),
+                    ends_at: Some(starts_at - chrono::Duration::days(1)),
+                    ..RecurrenceSchedule::default()
+                },
+            ),
+        )
+        .await;
+        assert!(matches!(refused, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+    }
+
+    #[tokio::test]
+    async fn test_recurring_transfer_with_fee() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        let (account_id, asset_id) = setup_account_and_asset(&pool, user_id).await;
+        let savings_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO assets (account_id, asset_type, balance, created_at, updated_at)
+             VALUES ($1, 'savings', 0, now(), now()) RETURNING id",
+        )
+        .bind(account_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let mut standing_order = new_recurring(
+            account_id,
+            asset_id,
+            Decimal::new(2000, 2),
+            RecurringTransactionType::InternalTransfer,
+            every(IntervalChoices::Monthly),
+        );
+        standing_order.to_asset_id = Some(savings_id);
+        standing_order.fee = Decimal::new(50, 2);
+        let created = create_recurring_transaction(&pool, &standing_order)
+            .await
+            .unwrap();
+
+        let transaction = created.transaction_at(created.next_execution);
+        assert_eq!(transaction.from_asset_id, Some(asset_id));
+        assert_eq!(transaction.to_asset_id, Some(savings_id));
+        assert_eq!(transaction.to_account_id, Some(account_id));
+
+        let mut conn = pool.acquire().await.unwrap();
+        let posted = execute_recurring_transaction(&mut conn, created.id, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(posted.len(), 1);
+
+        let balances: Vec<Decimal> =
+            sqlx::query_scalar("SELECT balance FROM assets WHERE id = ANY($1) ORDER BY id = $2")
+                .bind(vec![asset_id, savings_id])
+                .bind(savings_id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(balances, vec![Decimal::new(2950, 2), Decimal::new(2000, 2)]);
+
+        // A transfer needs a destination
+        standing_order.to_asset_id = None;
+        let refused = create_recurring_transaction(&pool, &standing_order).await;
+        assert!(matches!(refused, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_suggestions() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        let (account_id, asset_id) = setup_account_and_asset(&pool, user_id).await;
+        let now = Utc::now();
+        let payee = crate::repository::create_payee(&pool, account_id, "Spotify", &[])
+            .await
+            .unwrap();
+
+        for months_ago in 1..=3 {
+            create_transaction(
+                &pool,
+                Some(asset_id),
+                None,
+                crate::models::TransactionType::Expense,
+                Decimal::new(1290, 2),
+                None,
+                Some(account_id),
+                None,
+                now.checked_sub_months(Months::new(months_ago)),
+                Some(format!("SPOTIFY P{}", months_ago)),
+                None,
+                None,
+                None,
+                Some(payee.id),
+            )
+            .await
+            .unwrap();
+        }
+
+        let suggestions = get_subscription_suggestions(&pool, account_id, now)
+            .await
+            .unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].interval, IntervalChoices::Monthly);
+        assert_eq!(suggestions[0].amount, Decimal::new(1290, 2));
+        assert!(suggestions[0].next_execution > now);
+
+        // Once accepted, the series is covered and no longer suggested
+        let accepted =
+            create_recurring_transaction(&pool, &NewRecurringTransaction::from(&suggestions[0]))
+                .await
+                .unwrap();
+        assert_eq!(accepted.next_execution, suggestions[0].next_execution);
+        assert!(get_subscription_suggestions(&pool, account_id, now)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Its payments keep the payee and notes of the ones it was detected from
+        assert_eq!(accepted.payee_id, Some(payee.id));
+        assert_eq!(accepted.notes.as_deref(), Some("SPOTIFY P1"));
+        let mut conn = pool.acquire().await.unwrap();
+        let posted = execute_recurring_transaction(&mut conn, accepted.id, accepted.next_execution)
+            .await
+            .unwrap();
+        let transaction: Transaction = sqlx::query_as("SELECT * FROM transactions WHERE id = $1")
+            .bind(posted[0])
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(transaction.payee_id, Some(payee.id));
+        assert_eq!(transaction.notes.as_deref(), Some("SPOTIFY P1"));
+    }
+
+    #[tokio::test]
+    async fn test_missed_run_policies_and_run_log() {
+        let pool = setup_test_db().await;
+        let user_id = Uuid::new_v4();
+        let (account_id, asset_id) = setup_account_and_asset(&pool, user_id).await;
+        let now = Utc::now();
+
+        let mut created = Vec::new();
+        for (policy, first_run) in [
+            // Missed about 3, 2 and 1 day ago
+            (
+                MissedRunPolicy::LatestOnly,
+                now - Duration::days(3) + Duration::minutes(10),
+            ),
+            // Missed 2 and 1 day ago; the last one is just due
+            (
+                MissedRunPolicy::Skip,
+                now - Duration::days(2) - Duration::minutes(10),
+            ),
+        ] {
+            let recurring = create_recurring_transaction(
+                &pool,
+                &NewRecurringTransaction {
+                    missed_run_policy: policy,
+                    ..new_recurring(
+                        account_id,
+                        asset_id,
+                        Decimal::new(100, 2),
+                        RecurringTransactionType::Expense,
+                        RecurrenceSchedule {
+                            starts_at: Some(first_run),
+                            ..every(IntervalChoices::Daily)
+                        },
+                    )
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(recurring.missed_run_policy, policy);
+            created.push(recurring);
+        }
+
+        let mut conn = pool.acquire().await.unwrap();
+        for recurring in &created {
+            let posted = execute_recurring_transaction(&mut conn, recurring.id, now)
+                .await
+                .unwrap();
+            assert_eq!(posted.len(), 1);
+
+            let log = get_recurring_transaction_runs(&pool, recurring.id, 100)
+                .await
+                .unwrap();
+            let outcomes: Vec<RunOutcome> = log.runs.iter().map(|run| run.outcome).collect();
+            assert_eq!(
+                outcomes,
+                vec![RunOutcome::Posted, RunOutcome::Skipped, RunOutcome::Skipped]
+            );
+            assert_eq!(log.runs[0].transaction_id, Some(posted[0]));
+            assert!(log.runs[1].transaction_id.is_none());
+        }
+
+        // Skipped occurrences don't count towards `max_occurrences`
+        let limited = update_recurring_transaction_info(
+            &pool,
+            created[0].id,
+            &RecurringTransactionUpdate {
+                missed_run_policy: Some(MissedRunPolicy::CatchUpAll),
+                schedule: RecurrenceSchedule {
+                    max_occurrences: Some(2),
+                    ..RecurrenceSchedule::default()
+                },
+                ..RecurringTransactionUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(limited.is_active);
+        assert_eq!(limited.missed_run_policy, MissedRunPolicy::CatchUpAll);
+
+        // A failed attempt is logged once per occurrence and doesn't block the retry
+        log_failed_run(&pool, limited.id, "first").await.unwrap();
+        log_failed_run(&pool, limited.id, "second").await.unwrap();
+        let posted = execute_recurring_transaction(&mut conn, limited.id, limited.next_execution)
+            .await
+            .unwrap();
+        assert_eq!(posted.len(), 1);
+
+        let log = get_recurring_transaction_runs(&pool, limited.id, 100)
+            .await
+            .unwrap();
+        let failed: Vec<&RecurringTransactionRun> = log
+            .runs
+            .iter()
+            .filter(|run| run.outcome == RunOutcome::Failed)
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error.as_deref(), Some("second"));
+        assert_eq!(failed[0].scheduled_at, limited.next_execution);
+        assert_eq!(log.runs.len(), 5);
+
+        // Two occurrences posted: the schedule is over
+        let done = get_recurring_transaction_by_id(&pool, limited.id)
+            .await
+            .unwrap();
+        assert!(!done.is_active);
+    }
+
+    #[tokio::test]
+    async fn test_execute_refuses_invalid_occurrence() {
+        let pool = setup_test_db().await;
+        let (account_id, asset_id) = setup_account_and_asset(&pool, Uuid::new_v4()).await;
+        let (other_account_id, _) = setup_account_and_asset(&pool, Uuid::new_v4()).await;
+        let now = Utc::now();
+
+        let created = create_recurring_transaction(
+            &pool,
+            &new_recurring(
+                account_id,
+                asset_id,
+                Decimal::new(1500, 2),
+                RecurringTransactionType::Expense,
+                every(IntervalChoices::Monthly),
+            ),
+        )
+        .await
+        .unwrap();
+        update_recurring_transaction_info(
+            &pool,
+            created.id,
+            &RecurringTransactionUpdate {
+                next_execution: Some(now - Duration::minutes(10)),
+                ..RecurringTransactionUpdate::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // The asset moved to another account after the item was set up
+        sqlx::query("UPDATE assets SET account_id = $1, asset_type = 'moved' WHERE id = $2")
+            .bind(other_account_id)
+            .bind(asset_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut db_tx = pool.begin().await.unwrap();
+        let result = execute_recurring_transaction(&mut db_tx, created.id, now).await;
+        assert!(matches!(
+            result,
+            Err(TransactionError::Invalid(
+                TransactionValidationError::AssetNotInAccount { .. }
+            ))
+        ));
+        drop(db_tx);
+
+        let posted: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM transactions WHERE from_asset_id = $1")
+                .bind(asset_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(posted, 0);
+        let balance: Decimal = sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(balance, Decimal::new(5000, 2));
+    }
 }
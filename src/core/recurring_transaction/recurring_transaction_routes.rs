@@ -1,4 +1,7 @@
-use axum::{routing::get, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 use axum_login::login_required;
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -23,6 +26,29 @@ pub fn recurringtransaction_routes(state: Arc<PgPool>) -> Router {
                 .patch(update_recurring_transaction_handler)
                 .delete(delete_recurring_transaction_handler),
         )
+        // GET    /recurring_transactions/{id}/preview?count= -> Next scheduled executions
+        .route(
+            "/recurring_transactions/{id}/preview",
+            get(preview_recurring_transaction_handler),
+        )
+        // GET    /recurring_transactions/{id}/runs?limit= -> Execution log and missed-run policy
+        // PATCH  /recurring_transactions/{id}/runs -> Change the missed-run policy
+        .route(
+            "/recurring_transactions/{id}/runs",
+            get(get_recurring_transaction_runs_handler).patch(update_missed_run_policy_handler),
+        )
+        // GET    /recurring_transactions/suggestions/account/{id}
+        //        -> Repeating payments of an account that could become recurring transactions
+        .route(
+            "/recurring_transactions/suggestions/account/{id}",
+            get(get_subscription_suggestions_handler),
+        )
+        // POST   /recurring_transactions/suggestions/accept
+        //        -> Create the recurring transaction of a suggestion
+        .route(
+            "/recurring_transactions/suggestions/accept",
+            post(accept_subscription_suggestion_handler),
+        )
         // Enable this to protect routes with login middleware
         .route_layer(login_required!(Backend, login_url = "/login"))
         .with_state(state) // Inject database connection into all handlers
@@ -1,38 +1,46 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::models::{IntervalChoices, RecurringTransaction, RecurringTransactionType};
+use crate::core::transaction::transaction_validation::{check_transaction, TransactionError};
+use crate::models::{
+    IntervalChoices, MissedRunPolicy, NewRecurringTransaction, RecurringTransaction,
+    RecurringTransactionType, RecurringTransactionUpdate, SubscriptionSuggestion,
+    SubscriptionSuggestionList,
+};
 use crate::repository::{
     create_recurring_transaction, delete_recurring_transaction, get_recurring_transaction_by_id,
-    get_recurring_transactions, update_recurring_transaction_info,
+    get_recurring_transaction_runs, get_recurring_transactions, get_subscription_suggestions,
+    preview_recurring_transaction, update_recurring_transaction_info,
 };
 
-/// Request body for creating a recurring transaction
+/// Query parameters for previewing upcoming executions
 #[derive(Deserialize)]
-pub struct CreateRecurringTransactionRequest {
-    pub account_id: Uuid,
-    pub asset_id: Uuid,
-    pub amount: Decimal,
-    pub interval: IntervalChoices,
-    pub transaction_type: RecurringTransactionType,
+pub struct PreviewQuery {
+    /// Number of executions to list, 10 by default and at most 366
+    pub count: Option<usize>,
 }
 
-/// Request body for updating a recurring transaction
+/// Query parameters for the execution log
 #[derive(Deserialize)]
-pub struct UpdateRecurringTransactionRequest {
-    pub amount: Option<Decimal>,
-    pub interval: Option<IntervalChoices>,
-    pub next_execution: Option<DateTime<Utc>>,
-    pub is_active: Option<bool>,
+pub struct RunsQuery {
+    /// Number of runs to list, newest first; 100 by default and at most 1000
+    pub limit: Option<i64>,
+}
+
+/// Request body changing what happens to missed occurrences
+#[derive(Deserialize)]
+pub struct MissedRunPolicyUpdate {
+    pub missed_run_policy: MissedRunPolicy,
 }
 
 /// Handler: Fetch all recurring transactions from the database
@@ -54,28 +62,77 @@ pub async fn get_recurring_transaction_handler(
     Json(transaction)
 }
 
-/// Handler: Create a new recurring transaction
+/// Handler: Create a new recurring transaction.
+/// The transactions it would post must follow the rules of their type.
 pub async fn add_recurring_transaction_handler(
     State(pool): State<Arc<PgPool>>,
-    Json(payload): Json<CreateRecurringTransactionRequest>,
-) -> (StatusCode, Json<RecurringTransaction>) {
-    match create_recurring_transaction(
-        &pool,
-        payload.account_id,
-        payload.asset_id,
-        payload.amount,
-        payload.interval,
-        payload.transaction_type,
-    )
-    .await
-    {
-        Ok(transaction) => (StatusCode::CREATED, Json(transaction)),
+    Json(payload): Json<NewRecurringTransaction>,
+) -> impl IntoResponse {
+    create_recurring_transaction_response(&pool, &payload).await
+}
+
+/// Handler: Suggest recurring transactions from an account's repeating payments
+pub async fn get_subscription_suggestions_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_subscription_suggestions(&pool, account_id, Utc::now()).await {
+        Ok(suggestions) => SubscriptionSuggestionList(suggestions).into_response(),
         Err(err) => {
+            eprintln!(
+                "Failed to detect subscriptions of account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Create the recurring transaction a suggestion describes
+pub async fn accept_subscription_suggestion_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(suggestion): Json<SubscriptionSuggestion>,
+) -> impl IntoResponse {
+    create_recurring_transaction_response(&pool, &NewRecurringTransaction::from(&suggestion)).await
+}
+
+/// Create a recurring transaction and answer with it, or with why it was refused
+async fn create_recurring_transaction_response(
+    pool: &PgPool,
+    payload: &NewRecurringTransaction,
+) -> Response {
+    if payload.schedule.interval().is_none() {
+        eprintln!("Recurring transaction needs an interval or a recurrence rule");
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(dummy_recurring_transaction()),
+        )
+            .into_response();
+    }
+
+    match create_recurring_transaction_atomically(pool, payload).await {
+        Ok(transaction) => (StatusCode::CREATED, Json(transaction)).into_response(),
+        Err(TransactionError::Invalid(err)) => {
+            eprintln!("Rejected recurring transaction: {}", err);
+            err.into_response()
+        }
+        Err(TransactionError::Database(sqlx::Error::Database(err)))
+            if err.is_check_violation() || err.is_foreign_key_violation() =>
+        {
+            eprintln!("Rejected recurring transaction: {}", err);
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(dummy_recurring_transaction()),
+            )
+                .into_response()
+        }
+        Err(TransactionError::Database(err)) => {
             eprintln!("Failed to create recurring transaction: {:#?}", err);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(dummy_recurring_transaction()), // fallback dummy object
             )
+                .into_response()
         }
     }
 }
@@ -84,19 +141,20 @@ pub async fn add_recurring_transaction_handler(
 pub async fn update_recurring_transaction_handler(
     State(pool): State<Arc<PgPool>>,
     Path(transaction_id): Path<Uuid>,
-    Json(payload): Json<UpdateRecurringTransactionRequest>,
+    Json(payload): Json<RecurringTransactionUpdate>,
 ) -> (StatusCode, Json<RecurringTransaction>) {
-    match update_recurring_transaction_info(
-        &pool,
-        transaction_id,
-        payload.amount,
-        payload.interval,
-        payload.next_execution,
-        payload.is_active,
-    )
-    .await
-    {
+    match update_recurring_transaction_info(&pool, transaction_id, &payload).await {
         Ok(transaction) => (StatusCode::OK, Json(transaction)),
+        Err(sqlx::Error::Database(err)) if err.is_check_violation() => {
+            eprintln!(
+                "Rejected update of recurring transaction {}: {}",
+                transaction_id, err
+            );
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(dummy_recurring_transaction()),
+            )
+        }
         Err(err) => {
             eprintln!(
                 "Failed to update recurring transaction {}: {:#?}",
@@ -127,17 +185,117 @@ pub async fn delete_recurring_transaction_handler(
     }
 }
 
+/// Handler: List the next scheduled executions of a recurring transaction
+pub async fn preview_recurring_transaction_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+    Query(query): Query<PreviewQuery>,
+) -> impl IntoResponse {
+    let count = query.count.unwrap_or(10).min(366);
+
+    match preview_recurring_transaction(&pool, transaction_id, count).await {
+        Ok(executions) => Json(executions).into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to preview recurring transaction {}: {:#?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: The execution log of a recurring transaction and its missed-run policy
+pub async fn get_recurring_transaction_runs_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+    Query(query): Query<RunsQuery>,
+) -> impl IntoResponse {
+    runs_response(
+        &pool,
+        transaction_id,
+        query.limit.unwrap_or(100).clamp(1, 1000),
+    )
+    .await
+}
+
+/// Handler: Change the missed-run policy of a recurring transaction; answers with its runs
+pub async fn update_missed_run_policy_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+    Json(payload): Json<MissedRunPolicyUpdate>,
+) -> impl IntoResponse {
+    let update = RecurringTransactionUpdate {
+        missed_run_policy: Some(payload.missed_run_policy),
+        ..RecurringTransactionUpdate::default()
+    };
+
+    match update_recurring_transaction_info(&pool, transaction_id, &update).await {
+        Ok(_) => runs_response(&pool, transaction_id, 100).await,
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to update the missed-run policy of recurring transaction {}: {:#?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The latest `limit` runs of a recurring transaction, or why they couldn't be fetched
+async fn runs_response(pool: &PgPool, transaction_id: Uuid, limit: i64) -> Response {
+    match get_recurring_transaction_runs(pool, transaction_id, limit).await {
+        Ok(runs) => runs.into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch runs of recurring transaction {}: {:#?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Insert a recurring transaction and check the transaction its first execution posts.
+/// Dropping `db_tx` on a refusal rolls the insert back.
+async fn create_recurring_transaction_atomically(
+    pool: &PgPool,
+    recurring: &NewRecurringTransaction,
+) -> Result<RecurringTransaction, TransactionError> {
+    let mut db_tx = pool.begin().await?;
+
+    let created = create_recurring_transaction(&mut *db_tx, recurring).await?;
+    check_transaction(&mut db_tx, &created.transaction_at(created.next_execution)).await?;
+
+    db_tx.commit().await?;
+    Ok(created)
+}
+
 /// Dummy object used when transaction creation/update fails
 fn dummy_recurring_transaction() -> RecurringTransaction {
     RecurringTransaction {
         id: Uuid::nil(),
         account_id: Uuid::nil(),
         asset_id: Uuid::nil(),
+        to_asset_id: None,
+        to_account_id: None,
         amount: Decimal::ZERO,
+        fee: Decimal::ZERO,
         interval: IntervalChoices::Daily,
         next_execution: Utc::now(),
         transaction_type: RecurringTransactionType::Income,
         is_active: false,
+        category_id: None,
+        payee_id: None,
+        notes: None,
+        recurrence_rule: None,
+        starts_at: Utc::now(),
+        ends_at: None,
+        max_occurrences: None,
+        missed_run_policy: MissedRunPolicy::CatchUpAll,
         created_at: Utc::now(),
         updated_at: Utc::now(),
     }
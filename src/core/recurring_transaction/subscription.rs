@@ -0,0 +1,314 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use super::recurring_transaction::RecurrenceSchedule;
+use crate::core::payee::payee::normalize_payee_name;
+use crate::models::{
+    IntervalChoices, MissedRunPolicy, NewRecurringTransaction, RecurringTransaction,
+    RecurringTransactionType, Transaction, TransactionType,
+};
+
+/// Fewest payments that make a series
+const MIN_OCCURRENCES: usize = 3;
+
+/// A payment that repeats at a regular cadence, suggested as a recurring transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubscriptionSuggestion {
+    pub account_id: Uuid,
+
+    /// Asset the payments leave (expenses) or arrive in (income)
+    pub asset_id: Uuid,
+
+    pub transaction_type: RecurringTransactionType,
+
+    /// Amount of the most recent payment
+    pub amount: Decimal,
+
+    pub interval: IntervalChoices,
+
+    /// When the next payment is expected
+    pub next_execution: DateTime<Utc>,
+
+    /// Category of the most recent payment
+    #[serde(default)]
+    pub category_id: Option<Uuid>,
+
+    /// Payee the payments were grouped by, if any
+    #[serde(default)]
+    pub payee_id: Option<Uuid>,
+
+    /// Notes of the most recent payment
+    #[serde(default)]
+    pub notes: Option<String>,
+
+    /// Payments the suggestion was detected from, oldest first
+    #[serde(default)]
+    pub transaction_ids: Vec<Uuid>,
+}
+
+/// Wrapper struct for returning a list of suggestions
+#[derive(Debug, Serialize)]
+pub struct SubscriptionSuggestionList(pub Vec<SubscriptionSuggestion>);
+
+/// Allows a SubscriptionSuggestionList to be returned directly as a JSON HTTP response
+impl IntoResponse for SubscriptionSuggestionList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// The recurring transaction accepting a suggestion creates
+impl From<&SubscriptionSuggestion> for NewRecurringTransaction {
+    fn from(suggestion: &SubscriptionSuggestion) -> Self {
+        Self {
+            account_id: suggestion.account_id,
+            asset_id: suggestion.asset_id,
+            to_asset_id: None,
+            to_account_id: None,
+            amount: suggestion.amount,
+            fee: Decimal::ZERO,
+            transaction_type: suggestion.transaction_type,
+            category_id: suggestion.category_id,
+            payee_id: suggestion.payee_id,
+            notes: suggestion.notes.clone(),
+            missed_run_policy: MissedRunPolicy::default(),
+            schedule: RecurrenceSchedule {
+                interval: Some(suggestion.interval),
+                starts_at: Some(suggestion.next_execution),
+                ..RecurrenceSchedule::default()
+            },
+        }
+    }
+}
+
+/// Whether two amounts are close enough to be the same payment (within 10%)
+fn similar_amount(amount: Decimal, typical: Decimal) -> bool {
+    (amount - typical).abs() <= typical.abs() * Decimal::new(1, 1)
+}
+
+/// Range of days between two payments of the interval, with some slack for weekends
+/// and months of different lengths
+fn gap_range(interval: IntervalChoices) -> std::ops::RangeInclusive<i64> {
+    match interval {
+        IntervalChoices::Daily => 1..=1,
+        IntervalChoices::Weekly => 5..=9,
+        IntervalChoices::Monthly => 25..=36,
+    }
+}
+
+/// The interval whose cadence most of the gaps (in days) follow
+fn detect_interval(gaps: &[i64]) -> Option<IntervalChoices> {
+    let mut sorted = gaps.to_vec();
+    sorted.sort_unstable();
+    let median = *sorted.get(sorted.len() / 2)?;
+
+    let interval = [
+        IntervalChoices::Daily,
+        IntervalChoices::Weekly,
+        IntervalChoices::Monthly,
+    ]
+    .into_iter()
+    .find(|interval| gap_range(*interval).contains(&median))?;
+
+    // Tolerate the odd skipped or doubled payment, not an irregular series
+    let regular = gaps
+        .iter()
+        .filter(|gap| gap_range(interval).contains(gap))
+        .count();
+    (regular * 4 >= gaps.len() * 3).then_some(interval)
+}
+
+/// Find series of similar payments at a regular cadence in an account's transactions.
+///
+/// Income and expenses are grouped by asset and by payee, or by their notes reduced with
+/// `normalize_payee_name` when they have no payee. Payments more than 10% off the group's
+/// median amount are left out. Series that lapsed (two payments missed) or that an active
+/// recurring transaction already covers are not suggested.
+pub fn detect_subscriptions(
+    account_id: Uuid,
+    transactions: &[Transaction],
+    existing: &[RecurringTransaction],
+    now: DateTime<Utc>,
+) -> Vec<SubscriptionSuggestion> {
+    let mut groups: BTreeMap<(Uuid, i32, String), Vec<&Transaction>> = BTreeMap::new();
+    for tx in transactions {
+        let asset_id = match tx.transaction_type {
+            TransactionType::Expense if tx.from_account_id == Some(account_id) => tx.from_asset_id,
+            TransactionType::Income if tx.to_account_id == Some(account_id) => tx.to_asset_id,
+            _ => None,
+        };
+        let label = match (tx.payee_id, &tx.notes) {
+            (Some(payee_id), _) => payee_id.to_string(),
+            (None, Some(notes)) => normalize_payee_name(notes),
+            (None, None) => String::new(),
+        };
+        if let Some(asset_id) = asset_id.filter(|_| !label.is_empty()) {
+            groups
+                .entry((asset_id, tx.transaction_type as i32, label))
+                .or_default()
+                .push(tx);
+        }
+    }
+
+    let paid_at = |tx: &Transaction| tx.transaction_time.unwrap_or(tx.created_at);
+
+    let mut suggestions = Vec::new();
+    for ((asset_id, _, _), mut series) in groups {
+        if series.len() < MIN_OCCURRENCES {
+            continue;
+        }
+
+        let mut amounts: Vec<Decimal> = series.iter().map(|tx| tx.amount).collect();
+        amounts.sort_unstable();
+        let typical = amounts[amounts.len() / 2];
+        series.retain(|tx| similar_amount(tx.amount, typical));
+        series.sort_by_key(|tx| paid_at(tx));
+        if series.len() < MIN_OCCURRENCES {
+            continue;
+        }
+
+        let gaps: Vec<i64> = series
+            .windows(2)
+            .map(|pair| (paid_at(pair[1]).date_naive() - paid_at(pair[0]).date_naive()).num_days())
+            .collect();
+        let Some(interval) = detect_interval(&gaps) else {
+            continue;
+        };
+
+        let latest = series[series.len() - 1];
+        let mut next_execution = interval.advance(paid_at(latest));
+        if interval.advance(next_execution) < now {
+            continue;
+        }
+        while next_execution <= now {
+            next_execution = interval.advance(next_execution);
+        }
+
+        let transaction_type = match latest.transaction_type {
+            TransactionType::Income => RecurringTransactionType::Income,
+            _ => RecurringTransactionType::Expense,
+        };
+        let covered = existing.iter().any(|recurring| {
+            recurring.is_active
+                && recurring.asset_id == asset_id
+                && recurring.transaction_type == transaction_type
+                && recurring.interval == interval
+                && similar_amount(recurring.amount, latest.amount)
+        });
+        if covered {
+            continue;
+        }
+
+        suggestions.push(SubscriptionSuggestion {
+            account_id,
+            asset_id,
+            transaction_type,
+            amount: latest.amount,
+            interval,
+            next_execution,
+            category_id: latest.category_id,
+            payee_id: latest.payee_id,
+            notes: latest.notes.clone(),
+            transaction_ids: series.iter().map(|tx| tx.id).collect(),
+        });
+    }
+
+    suggestions.sort_by_key(|suggestion| suggestion.next_execution);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn payment(
+        account_id: Uuid,
+        asset_id: Uuid,
+        notes: &str,
+        amount: i64,
+        at: DateTime<Utc>,
+    ) -> Transaction {
+        Transaction {
+            from_asset_id: Some(asset_id),
+            from_account_id: Some(account_id),
+            amount: Decimal::new(amount, 0),
+            notes: Some(notes.to_string()),
+            transaction_time: Some(at),
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_monthly_and_weekly_series() {
+        let account_id = Uuid::new_v4();
+        let bank = Uuid::new_v4();
+        let day = |m, d| Utc.with_ymd_and_hms(2025, m, d, 8, 0, 0).unwrap();
+        let now = day(5, 20);
+
+        let mut transactions = vec![
+            // Monthly streaming bill; the store code in the notes changes
+            payment(account_id, bank, "NETFLIX #1001", 390, day(1, 15)),
+            payment(account_id, bank, "NETFLIX #1002", 390, day(2, 15)),
+            payment(account_id, bank, "NETFLIX #1003", 390, day(3, 14)),
+            payment(account_id, bank, "NETFLIX #1004", 420, day(4, 15)),
+            // A one-off purchase at the same payee is not part of the series
+            payment(account_id, bank, "NETFLIX #1005", 3000, day(4, 20)),
+            // Irregular coffee purchases
+            payment(account_id, bank, "Coffee", 120, day(1, 3)),
+            payment(account_id, bank, "Coffee", 120, day(1, 4)),
+            payment(account_id, bank, "Coffee", 120, day(2, 27)),
+            payment(account_id, bank, "Coffee", 120, day(4, 1)),
+            // A gym membership that was cancelled in February
+            payment(
+                account_id,
+                bank,
+                "Gym",
+                900,
+                day(11, 1) - Duration::days(365),
+            ),
+            payment(
+                account_id,
+                bank,
+                "Gym",
+                900,
+                day(12, 1) - Duration::days(365),
+            ),
+            payment(account_id, bank, "Gym", 900, day(1, 1)),
+        ];
+        // Weekly cleaning
+        transactions.extend((0..4).map(|week| {
+            payment(
+                account_id,
+                bank,
+                "Cleaner",
+                800,
+                day(4, 22) + Duration::weeks(week),
+            )
+        }));
+
+        let suggestions = detect_subscriptions(account_id, &transactions, &[], now);
+
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(suggestions[0].interval, IntervalChoices::Weekly);
+        assert_eq!(
+            suggestions[0].next_execution,
+            day(5, 20) + Duration::weeks(1)
+        );
+        assert_eq!(suggestions[1].interval, IntervalChoices::Monthly);
+        assert_eq!(suggestions[1].amount, Decimal::new(420, 0));
+        assert_eq!(
+            suggestions[1].next_execution,
+            day(5, 15) + Duration::days(31)
+        );
+        assert_eq!(suggestions[1].transaction_ids.len(), 4);
+        assert_eq!(
+            suggestions[1].transaction_type,
+            RecurringTransactionType::Expense
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod rule;
+pub mod rule_handler;
+pub mod rule_repository;
+pub mod rule_routes;
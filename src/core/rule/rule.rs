@@ -0,0 +1,318 @@
+use axum::response::{IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::{Transaction, TransactionType};
+
+/// A rule that categorises and tags matching transactions, e.g.
+/// "notes contain 7-ELEVEN → Food, tag convenience".
+///
+/// Every condition that is set must hold; unset conditions are ignored.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TransactionRule {
+    pub id: Uuid,
+
+    /// Account the rule belongs to; it only applies to that account's transactions
+    pub account_id: Uuid,
+
+    pub name: String,
+
+    /// Rules run in ascending priority; the first matching rule with a category decides it
+    pub priority: i32,
+
+    pub is_active: bool,
+
+    /// Notes contain this text, ignoring case
+    pub notes_contains: Option<String>,
+
+    pub transaction_type: Option<TransactionType>,
+
+    /// Money moves from or to this asset
+    pub asset_id: Option<Uuid>,
+
+    pub amount_equals: Option<Decimal>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+
+    /// Category given to matching transactions
+    pub category_id: Option<Uuid>,
+
+    /// Tags added to matching transactions
+    pub tags: Vec<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TransactionRule {
+    /// Whether every condition of the rule holds for the transaction
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        let notes_match = self.notes_contains.as_ref().is_none_or(|needle| {
+            transaction
+                .notes
+                .as_ref()
+                .is_some_and(|notes| notes.to_lowercase().contains(&needle.to_lowercase()))
+        });
+        let asset_match = self.asset_id.is_none_or(|asset_id| {
+            transaction.from_asset_id == Some(asset_id) || transaction.to_asset_id == Some(asset_id)
+        });
+
+        self.is_active
+            && notes_match
+            && asset_match
+            && self
+                .transaction_type
+                .is_none_or(|transaction_type| transaction.transaction_type == transaction_type)
+            && self
+                .amount_equals
+                .is_none_or(|amount| transaction.amount == amount)
+            && self.min_amount.is_none_or(|min| transaction.amount >= min)
+            && self.max_amount.is_none_or(|max| transaction.amount <= max)
+    }
+}
+
+/// Allows a `TransactionRule` to be returned as a JSON response
+impl IntoResponse for TransactionRule {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Wrapper for returning the rules of an account
+#[derive(Debug, Serialize)]
+pub struct TransactionRuleList(pub Vec<TransactionRule>);
+
+/// Enables `TransactionRuleList` to be returned as a JSON response
+impl IntoResponse for TransactionRuleList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Conditions and actions of a rule as submitted by the client
+#[derive(Debug, Deserialize)]
+pub struct NewTransactionRule {
+    pub name: String,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_is_active")]
+    pub is_active: bool,
+    pub notes_contains: Option<String>,
+    pub transaction_type: Option<TransactionType>,
+    pub asset_id: Option<Uuid>,
+    pub amount_equals: Option<Decimal>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub category_id: Option<Uuid>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_is_active() -> bool {
+    true
+}
+
+impl NewTransactionRule {
+    /// Tags trimmed, without empties and duplicates
+    pub fn normalized_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.iter().map(|tag| tag.trim()) {
+            if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+}
+
+/// What a set of rules decides for one transaction
+#[derive(Debug, Default, PartialEq)]
+pub struct RuleOutcome {
+    /// Category of the first matching rule that sets one
+    pub category_id: Option<Uuid>,
+
+    /// Tags of all matching rules, in rule order
+    pub tags: Vec<String>,
+}
+
+/// Run rules (sorted by priority) against a transaction
+pub fn evaluate_rules(rules: &[TransactionRule], transaction: &Transaction) -> RuleOutcome {
+    let mut outcome = RuleOutcome::default();
+    for rule in rules.iter().filter(|rule| rule.matches(transaction)) {
+        if outcome.category_id.is_none() {
+            outcome.category_id = rule.category_id;
+        }
+        for tag in &rule.tags {
+            if !outcome.tags.contains(tag) {
+                outcome.tags.push(tag.clone());
+            }
+        }
+    }
+    outcome
+}
+
+/// Change that rules make (or would make) to one transaction
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RuleChange {
+    pub transaction_id: Uuid,
+    pub old_category_id: Option<Uuid>,
+
+    /// Same as `old_category_id` when the category stays
+    pub new_category_id: Option<Uuid>,
+
+    /// Tags the transaction didn't have yet
+    pub added_tags: Vec<String>,
+}
+
+impl RuleChange {
+    /// Compare a rule outcome with the transaction. The category is only filled in
+    /// when the transaction has none (or `overwrite_category` is set), and never on
+    /// split transactions, whose lines carry their own categories.
+    pub fn between(
+        transaction: &Transaction,
+        outcome: &RuleOutcome,
+        overwrite_category: bool,
+    ) -> Option<RuleChange> {
+        let new_category_id = match outcome.category_id {
+            Some(category_id)
+                if transaction.splits.is_empty()
+                    && (transaction.category_id.is_none() || overwrite_category) =>
+            {
+                Some(category_id)
+            }
+            _ => transaction.category_id,
+        };
+        let added_tags: Vec<String> = outcome
+            .tags
+            .iter()
+            .filter(|tag| !transaction.tags.contains(tag))
+            .cloned()
+            .collect();
+
+        if new_category_id == transaction.category_id && added_tags.is_empty() {
+            return None;
+        }
+        Some(RuleChange {
+            transaction_id: transaction.id,
+            old_category_id: transaction.category_id,
+            new_category_id,
+            added_tags,
+        })
+    }
+}
+
+/// Options for re-applying rules to an account's existing transactions
+#[derive(Debug, Deserialize, Default)]
+pub struct ReapplyRulesRequest {
+    /// Report the changes without writing them
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Also replace categories that are already set
+    #[serde(default)]
+    pub overwrite_category: bool,
+
+    /// Only transactions at or after this time
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only transactions before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Result of re-applying rules to existing transactions
+#[derive(Debug, Serialize)]
+pub struct RuleApplication {
+    /// Nothing was written when true
+    pub dry_run: bool,
+    pub changes: Vec<RuleChange>,
+}
+
+/// Enables `RuleApplication` to be returned as a JSON response
+impl IntoResponse for RuleApplication {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(priority: i32, category_id: Option<Uuid>, tags: &[&str]) -> TransactionRule {
+        TransactionRule {
+            id: Uuid::new_v4(),
+            account_id: Uuid::new_v4(),
+            name: "rule".to_string(),
+            priority,
+            is_active: true,
+            notes_contains: None,
+            transaction_type: None,
+            asset_id: None,
+            amount_equals: None,
+            min_amount: None,
+            max_amount: None,
+            category_id,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_rules() {
+        let food = Uuid::new_v4();
+        let netflix = Uuid::new_v4();
+        let credit_card = Uuid::new_v4();
+
+        let mut seven_eleven = rule(0, Some(food), &["convenience"]);
+        seven_eleven.notes_contains = Some("7-eleven".to_string());
+        let mut subscription = rule(1, Some(netflix), &["subscription"]);
+        subscription.amount_equals = Some(Decimal::new(390, 0));
+        subscription.asset_id = Some(credit_card);
+        let mut expenses = rule(2, None, &["spending", "convenience"]);
+        expenses.transaction_type = Some(TransactionType::Expense);
+
+        let rules = vec![seven_eleven, subscription, expenses];
+
+        let snack = Transaction {
+            transaction_type: TransactionType::Expense,
+            amount: Decimal::new(85, 0),
+            notes: Some("7-ELEVEN Taipei Main Station".to_string()),
+            ..Transaction::default()
+        };
+        let outcome = evaluate_rules(&rules, &snack);
+        assert_eq!(outcome.category_id, Some(food));
+        assert_eq!(outcome.tags, vec!["convenience", "spending"]);
+
+        // Same amount on another asset isn't the subscription
+        let mut streaming = Transaction {
+            transaction_type: TransactionType::Expense,
+            amount: Decimal::new(390, 0),
+            from_asset_id: Some(Uuid::new_v4()),
+            ..Transaction::default()
+        };
+        assert_eq!(evaluate_rules(&rules, &streaming).category_id, None);
+        streaming.from_asset_id = Some(credit_card);
+        assert_eq!(
+            evaluate_rules(&rules, &streaming).category_id,
+            Some(netflix)
+        );
+
+        // Existing categories are kept unless overwriting; tags are only added
+        let mut categorized = snack;
+        categorized.category_id = Some(netflix);
+        categorized.tags = vec!["convenience".to_string()];
+        let outcome = evaluate_rules(&rules, &categorized);
+        let change = RuleChange::between(&categorized, &outcome, false).unwrap();
+        assert_eq!(change.new_category_id, Some(netflix));
+        assert_eq!(change.added_tags, vec!["spending"]);
+        let change = RuleChange::between(&categorized, &outcome, true).unwrap();
+        assert_eq!(change.new_category_id, Some(food));
+
+        categorized.tags.push("spending".to_string());
+        assert_eq!(RuleChange::between(&categorized, &outcome, false), None);
+    }
+}
@@ -0,0 +1,191 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{
+    NewTransactionRule, ReapplyRulesRequest, RuleApplication, TransactionRuleList,
+};
+use crate::repository::{
+    create_rule, delete_rule, get_asset_by_id, get_category_by_id, get_rule_by_id,
+    get_rules_by_account_id, reapply_rules, update_rule,
+};
+
+/// Request payload for creating a rule
+#[derive(Deserialize)]
+pub struct CreateRuleRequest {
+    pub account_id: Uuid,
+    #[serde(flatten)]
+    pub rule: NewTransactionRule,
+}
+
+/// Map a database error from a rule operation to a response status
+fn rule_error_status(err: &sqlx::Error) -> StatusCode {
+    match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        sqlx::Error::Database(db_err)
+            if db_err.is_check_violation() || db_err.is_foreign_key_violation() =>
+        {
+            StatusCode::BAD_REQUEST
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Check that a rule does something and only refers to the account's own
+/// category and asset
+async fn validate_rule(
+    pool: &PgPool,
+    account_id: Uuid,
+    rule: &NewTransactionRule,
+) -> Result<(), StatusCode> {
+    if rule.category_id.is_none() && rule.normalized_tags().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(category_id) = rule.category_id {
+        match get_category_by_id(pool, category_id).await {
+            Ok(category) if category.account_id == account_id => {}
+            _ => return Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }
+    if let Some(asset_id) = rule.asset_id {
+        match get_asset_by_id(pool, asset_id).await {
+            Ok(asset) if asset.account_id == account_id => {}
+            _ => return Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }
+    Ok(())
+}
+
+/// Handler: Fetch the rules of an account in evaluation order
+pub async fn get_rules_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_rules_by_account_id(&pool, account_id).await {
+        Ok(rules) => TransactionRuleList(rules).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch rules for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Fetch a rule by ID
+pub async fn get_rule_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(rule_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_rule_by_id(&pool, rule_id).await {
+        Ok(rule) => rule.into_response(),
+        Err(err) => {
+            eprintln!("Failed to fetch rule {}: {:#?}", rule_id, err);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// Handler: Create a rule
+pub async fn add_rule_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<CreateRuleRequest>,
+) -> impl IntoResponse {
+    if let Err(status) = validate_rule(&pool, payload.account_id, &payload.rule).await {
+        return status.into_response();
+    }
+
+    match create_rule(&pool, payload.account_id, &payload.rule).await {
+        Ok(rule) => (StatusCode::CREATED, rule).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to create rule for account {}: {:#?}",
+                payload.account_id, err
+            );
+            rule_error_status(&err).into_response()
+        }
+    }
+}
+
+/// Handler: Replace the conditions and actions of a rule
+pub async fn update_rule_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(rule_id): Path<Uuid>,
+    Json(payload): Json<NewTransactionRule>,
+) -> impl IntoResponse {
+    let existing = match get_rule_by_id(&pool, rule_id).await {
+        Ok(rule) => rule,
+        Err(err) => return rule_error_status(&err).into_response(),
+    };
+    if let Err(status) = validate_rule(&pool, existing.account_id, &payload).await {
+        return status.into_response();
+    }
+
+    match update_rule(&pool, rule_id, &payload).await {
+        Ok(rule) => rule.into_response(),
+        Err(err) => {
+            eprintln!("Failed to update rule {}: {:#?}", rule_id, err);
+            rule_error_status(&err).into_response()
+        }
+    }
+}
+
+/// Handler: Delete a rule by ID
+pub async fn delete_rule_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(rule_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_rule(&pool, rule_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            eprintln!("Failed to delete rule {}: {:#?}", rule_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Re-apply an account's rules to its existing transactions.
+/// With `dry_run` the changes are only reported; otherwise they are all written or none is.
+pub async fn reapply_rules_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(account_id): Path<Uuid>,
+    Json(payload): Json<ReapplyRulesRequest>,
+) -> impl IntoResponse {
+    match reapply_rules_atomically(&pool, account_id, &payload).await {
+        Ok(application) => application.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to re-apply rules for account {}: {:#?}",
+                account_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Run the re-application in one database transaction
+async fn reapply_rules_atomically(
+    pool: &PgPool,
+    account_id: Uuid,
+    request: &ReapplyRulesRequest,
+) -> Result<RuleApplication, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+    let changes = reapply_rules(&mut db_tx, account_id, request).await?;
+    if !request.dry_run {
+        db_tx.commit().await?;
+    }
+
+    Ok(RuleApplication {
+        dry_run: request.dry_run,
+        changes,
+    })
+}
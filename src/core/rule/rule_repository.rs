@@ -0,0 +1,429 @@
+use sqlx::{Executor, PgConnection, PgPool, Postgres};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::core::rule::rule::{evaluate_rules, RuleChange};
+use crate::models::{
+    NewTransactionRule, ReapplyRulesRequest, Transaction, TransactionRule, TransactionSplit,
+};
+use crate::repository::get_transaction_splits;
+
+// SQL query to fetch all rules of an account in evaluation order
+const QUERY_SELECT_BY_ACCOUNT_ID: &str =
+    "SELECT * FROM transaction_rules WHERE account_id = $1 ORDER BY priority, created_at, id";
+
+// SQL query to fetch the active rules of an account in evaluation order
+const QUERY_SELECT_ACTIVE_BY_ACCOUNT_ID: &str = "
+    SELECT * FROM transaction_rules
+    WHERE account_id = $1 AND is_active
+    ORDER BY priority, created_at, id
+";
+
+// SQL query to fetch a single rule by ID
+const QUERY_SELECT_BY_ID: &str = "SELECT * FROM transaction_rules WHERE id = $1";
+
+// SQL insert query for creating a rule
+const QUERY_INSERT: &str = "
+    INSERT INTO transaction_rules (
+        account_id, name, priority, is_active,
+        notes_contains, transaction_type, asset_id, amount_equals, min_amount, max_amount,
+        category_id, tags
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    RETURNING *
+";
+
+// SQL query replacing the conditions and actions of a rule
+const QUERY_UPDATE: &str = "
+    UPDATE transaction_rules SET
+        name = $2, priority = $3, is_active = $4,
+        notes_contains = $5, transaction_type = $6, asset_id = $7,
+        amount_equals = $8, min_amount = $9, max_amount = $10,
+        category_id = $11, tags = $12, updated_at = now()
+    WHERE id = $1
+    RETURNING *
+";
+
+// SQL query to delete a rule by ID
+const QUERY_DELETE: &str = "DELETE FROM transaction_rules WHERE id = $1";
+
+// SQL query to fetch the transactions of an account within an optional period
+const QUERY_SELECT_ACCOUNT_TRANSACTIONS: &str = "
+    SELECT * FROM transactions
+    WHERE (from_account_id = $1 OR to_account_id = $1)
+      AND ($2::TIMESTAMPTZ IS NULL OR COALESCE(transaction_time, created_at) >= $2)
+      AND ($3::TIMESTAMPTZ IS NULL OR COALESCE(transaction_time, created_at) < $3)
+    ORDER BY COALESCE(transaction_time, created_at), id
+";
+
+// SQL query writing the result of the rules to a transaction
+const QUERY_UPDATE_TRANSACTION: &str = "
+    UPDATE transactions
+    SET category_id = $2, tags = tags || $3, updated_at = now()
+    WHERE id = $1
+    RETURNING category_id, tags, updated_at
+";
+
+/// Get all rules of an account, in evaluation order
+pub async fn get_rules_by_account_id(
+    pool: &PgPool,
+    account_id: Uuid,
+) -> Result<Vec<TransactionRule>, sqlx::Error> {
+    sqlx::query_as::<_, TransactionRule>(QUERY_SELECT_BY_ACCOUNT_ID)
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Get a rule by its ID
+pub async fn get_rule_by_id(pool: &PgPool, rule_id: Uuid) -> Result<TransactionRule, sqlx::Error> {
+    sqlx::query_as::<_, TransactionRule>(QUERY_SELECT_BY_ID)
+        .bind(rule_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Create a rule for an account
+pub async fn create_rule(
+    pool: &PgPool,
+    account_id: Uuid,
+    rule: &NewTransactionRule,
+) -> Result<TransactionRule, sqlx::Error> {
+    sqlx::query_as::<_, TransactionRule>(QUERY_INSERT)
+        .bind(account_id)
+        .bind(rule.name.trim())
+        .bind(rule.priority)
+        .bind(rule.is_active)
+        .bind(rule.notes_contains.as_deref().map(str::trim))
+        .bind(rule.transaction_type)
+        .bind(rule.asset_id)
+        .bind(rule.amount_equals)
+        .bind(rule.min_amount)
+        .bind(rule.max_amount)
+        .bind(rule.category_id)
+        .bind(rule.normalized_tags())
+        .fetch_one(pool)
+        .await
+}
+
+/// Replace the conditions and actions of a rule
+pub async fn update_rule(
+    pool: &PgPool,
+    rule_id: Uuid,
+    rule: &NewTransactionRule,
+) -> Result<TransactionRule, sqlx::Error> {
+    sqlx::query_as::<_, TransactionRule>(QUERY_UPDATE)
+        .bind(rule_id)
+        .bind(rule.name.trim())
+        .bind(rule.priority)
+        .bind(rule.is_active)
+        .bind(rule.notes_contains.as_deref().map(str::trim))
+        .bind(rule.transaction_type)
+        .bind(rule.asset_id)
+        .bind(rule.amount_equals)
+        .bind(rule.min_amount)
+        .bind(rule.max_amount)
+        .bind(rule.category_id)
+        .bind(rule.normalized_tags())
+        .fetch_one(pool)
+        .await
+}
+
+/// Delete a rule by its ID
+pub async fn delete_rule(pool: &PgPool, rule_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(QUERY_DELETE)
+        .bind(rule_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Get the active rules of an account, in evaluation order
+pub async fn get_active_rules<'e, E>(
+    executor: E,
+    account_id: Uuid,
+) -> Result<Vec<TransactionRule>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, TransactionRule>(QUERY_SELECT_ACTIVE_BY_ACCOUNT_ID)
+        .bind(account_id)
+        .fetch_all(executor)
+        .await
+}
+
+/// Run the owning account's rules against a newly created transaction and store the
+/// category and tags they give it. The transaction is updated in place.
+///
+/// Call after the split lines are written, so split transactions keep their categories.
+pub async fn apply_rules_to_transaction(
+    conn: &mut PgConnection,
+    transaction: &mut Transaction,
+) -> Result<(), sqlx::Error> {
+    let Some(account_id) = transaction.from_account_id.or(transaction.to_account_id) else {
+        return Ok(());
+    };
+
+    let rules = get_active_rules(&mut *conn, account_id).await?;
+    let outcome = evaluate_rules(&rules, transaction);
+    if let Some(change) = RuleChange::between(transaction, &outcome, false) {
+        write_rule_change(conn, transaction, &change).await?;
+    }
+    Ok(())
+}
+
+/// Run an account's rules against its existing transactions and return what changes.
+/// With `dry_run` nothing is written.
+pub async fn reapply_rules(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    request: &ReapplyRulesRequest,
+) -> Result<Vec<RuleChange>, sqlx::Error> {
+    let rules = get_active_rules(&mut *conn, account_id).await?;
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut transactions = sqlx::query_as::<_, Transaction>(QUERY_SELECT_ACCOUNT_TRANSACTIONS)
+        .bind(account_id)
+        .bind(request.from)
+        .bind(request.to)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    // Split transactions keep the categories of their lines
+    let ids: Vec<Uuid> = transactions.iter().map(|tx| tx.id).collect();
+    let mut splits_by_transaction: HashMap<Uuid, Vec<TransactionSplit>> = HashMap::new();
+    for split in get_transaction_splits(&mut *conn, &ids).await? {
+        splits_by_transaction
+            .entry(split.transaction_id)
+            .or_default()
+            .push(split);
+    }
+
+    let mut changes = Vec::new();
+    for transaction in transactions.iter_mut() {
+        transaction.splits = splits_by_transaction
+            .remove(&transaction.id)
+            .unwrap_or_default();
+
+        let outcome = evaluate_rules(&rules, transaction);
+        let Some(change) = RuleChange::between(transaction, &outcome, request.overwrite_category)
+        else {
+            continue;
+        };
+
+        if !request.dry_run {
+            write_rule_change(conn, transaction, &change).await?;
+        }
+        changes.push(change);
+    }
+
+    Ok(changes)
+}
+
+/// Store a rule change and mirror it on the in-memory transaction
+async fn write_rule_change(
+    conn: &mut PgConnection,
+    transaction: &mut Transaction,
+    change: &RuleChange,
+) -> Result<(), sqlx::Error> {
+    let (category_id, tags, updated_at) = sqlx::query_as(QUERY_UPDATE_TRANSACTION)
+        .bind(transaction.id)
+        .bind(change.new_category_id)
+        .bind(&change.added_tags)
+        .fetch_one(conn)
+        .await?;
+
+    transaction.category_id = category_id;
+    transaction.tags = tags;
+    transaction.updated_at = updated_at;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use sqlx::{migrate::MigrateDatabase, Postgres};
+    use std::env;
+
+    use crate::models::TransactionType;
+    use crate::repository::{apply_transaction_balance, create_category, create_transaction};
+
+    async fn setup_test_db() -> PgPool {
+        dotenvy::from_filename(".env.test").ok();
+        let test_database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        if !Postgres::database_exists(&test_database_url)
+            .await
+            .unwrap_or(false)
+        {
+            Postgres::create_database(&test_database_url)
+                .await
+                .expect("Failed to create test database");
+        }
+
+        let pool = PgPool::connect(&test_database_url)
+            .await
+            .expect("Failed to connect to test DB");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        pool
+    }
+
+    async fn insert_account_and_asset(pool: &PgPool) -> (Uuid, Uuid) {
+        let user_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO users (id, username, email, hashed_password) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(format!("user_{}", &user_id.to_string()[..8]))
+        .bind(format!("{}@test.com", &user_id.to_string()[..8]))
+        .bind("hashed_pw")
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query("INSERT INTO accounts (account_id, balance, created_at, updated_at) VALUES ($1, $2, now(), now())")
+            .bind(user_id)
+            .bind(Decimal::ZERO)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let asset_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO assets (id, account_id, asset_type, balance, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, now(), now())",
+        )
+        .bind(asset_id)
+        .bind(user_id)
+        .bind("bank")
+        .bind(Decimal::new(100000, 2))
+        .execute(pool)
+        .await
+        .unwrap();
+
+        (user_id, asset_id)
+    }
+
+    async fn add_expense(
+        pool: &PgPool,
+        account_id: Uuid,
+        asset_id: Uuid,
+        amount: i64,
+        notes: &str,
+    ) -> Transaction {
+        let mut db_tx = pool.begin().await.unwrap();
+        let mut transaction = create_transaction(
+            &mut *db_tx,
+            Some(asset_id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(amount, 0),
+            None,
+            Some(account_id),
+            None,
+            None,
+            Some(notes.to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &transaction)
+            .await
+            .unwrap();
+        apply_rules_to_transaction(&mut db_tx, &mut transaction)
+            .await
+            .unwrap();
+        db_tx.commit().await.unwrap();
+        transaction
+    }
+
+    #[tokio::test]
+    async fn test_rules_on_create_and_reapply() {
+        let pool = setup_test_db().await;
+        let (account_id, asset_id) = insert_account_and_asset(&pool).await;
+        let food = create_category(&pool, account_id, None, "Food")
+            .await
+            .unwrap();
+
+        // Recorded before any rule exists
+        let earlier = add_expense(&pool, account_id, asset_id, 60, "7-ELEVEN Xinyi").await;
+        assert_eq!(earlier.category_id, None);
+
+        create_rule(
+            &pool,
+            account_id,
+            &NewTransactionRule {
+                name: "Convenience stores".to_string(),
+                priority: 0,
+                is_active: true,
+                notes_contains: Some("7-eleven".to_string()),
+                transaction_type: None,
+                asset_id: None,
+                amount_equals: None,
+                min_amount: None,
+                max_amount: None,
+                category_id: Some(food.id),
+                tags: vec![" convenience ".to_string(), "convenience".to_string()],
+            },
+        )
+        .await
+        .unwrap();
+
+        let later = add_expense(&pool, account_id, asset_id, 45, "7-eleven Da'an").await;
+        assert_eq!(later.category_id, Some(food.id));
+        assert_eq!(later.tags, vec!["convenience"]);
+        let unrelated = add_expense(&pool, account_id, asset_id, 500, "Dentist").await;
+        assert_eq!(unrelated.category_id, None);
+
+        // A dry run reports the older transaction without touching it
+        let request = ReapplyRulesRequest {
+            dry_run: true,
+            ..ReapplyRulesRequest::default()
+        };
+        let mut conn = pool.acquire().await.unwrap();
+        let changes = reapply_rules(&mut conn, account_id, &request)
+            .await
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].transaction_id, earlier.id);
+        assert_eq!(changes[0].new_category_id, Some(food.id));
+
+        let category: Option<Uuid> =
+            sqlx::query_scalar("SELECT category_id FROM transactions WHERE id = $1")
+                .bind(earlier.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(category, None);
+
+        // Applying for real writes it, after which nothing is left to change
+        let request = ReapplyRulesRequest::default();
+        let changes = reapply_rules(&mut conn, account_id, &request)
+            .await
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        let changes = reapply_rules(&mut conn, account_id, &request)
+            .await
+            .unwrap();
+        assert!(changes.is_empty());
+
+        let (category, tags): (Option<Uuid>, Vec<String>) =
+            sqlx::query_as("SELECT category_id, tags FROM transactions WHERE id = $1")
+                .bind(earlier.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(category, Some(food.id));
+        assert_eq!(tags, vec!["convenience"]);
+    }
+}
@@ -0,0 +1,32 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use axum_login::login_required;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::{core::rule::rule_handler::*, models::Backend};
+
+/// Defines routes for managing the rules that categorise and tag transactions
+pub fn rule_routes(state: Arc<PgPool>) -> Router {
+    Router::new()
+        // POST /rules -> Create a rule
+        .route("/rules", post(add_rule_handler))
+        // GET    /rules/{id} -> Fetch one by ID
+        // PUT    /rules/{id} -> Replace its conditions and actions
+        // DELETE /rules/{id} -> Delete a rule
+        .route(
+            "/rules/{id}",
+            get(get_rule_handler)
+                .put(update_rule_handler)
+                .delete(delete_rule_handler),
+        )
+        // GET /rules/account/{id} -> Rules of an account in evaluation order
+        .route("/rules/account/{id}", get(get_rules_handler))
+        // POST /rules/account/{id}/apply -> Re-apply rules to existing transactions
+        // (`"dry_run": true` only reports what would change)
+        .route("/rules/account/{id}/apply", post(reapply_rules_handler))
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .with_state(state)
+}
@@ -1,4 +1,6 @@
 pub mod transaction;
+pub mod transaction_export;
 pub mod transaction_handler;
 pub mod transaction_repository;
 pub mod transaction_routes;
+pub mod transaction_validation;
@@ -1,12 +1,17 @@
 use axum::response::{IntoResponse, Json};
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::models::BASE_CURRENCY;
+
 /// Defines the type of a transaction (e.g. income, expense, transfer)
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
 #[repr(i32)] // Stored as integers in the database
 pub enum TransactionType {
     /// Represents incoming funds
@@ -19,6 +24,19 @@ pub enum TransactionType {
     InternalTransfer = 4,
 }
 
+/// Where a transaction stands against the bank's statements
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy, Default)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum TransactionStatus {
+    /// Entered but not seen on a statement yet
+    #[default]
+    Pending,
+    /// Seen on a statement
+    Cleared,
+    /// Part of a completed reconciliation; locked against edits
+    Reconciled,
+}
+
 /// Represents a financial transaction, including transfers, incomes, and expenses
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Transaction {
@@ -40,6 +58,23 @@ pub struct Transaction {
     /// Any additional fee associated with the transaction
     pub fee: Decimal,
 
+    /// Currency of `amount` and `fee`: that of the paying asset
+    #[serde(default = "default_currency_code")]
+    pub currency_code: String,
+
+    /// Currency of the receiving asset
+    #[serde(default = "default_currency_code")]
+    pub to_currency_code: String,
+
+    /// Units of `to_currency_code` per unit of `currency_code` at transaction time;
+    /// None when both sides share a currency
+    #[serde(default)]
+    pub exchange_rate: Option<Decimal>,
+
+    /// Amount the receiving asset gets in its own currency; None when it is `amount`
+    #[serde(default)]
+    pub to_amount: Option<Decimal>,
+
     /// Account ID where the funds are coming from (nullable)
     pub from_account_id: Option<Uuid>,
 
@@ -60,6 +95,30 @@ pub struct Transaction {
 
     /// Optional image URL or base64 string associated with the transaction
     pub image: Option<String>,
+
+    /// Category of the transaction (e.g., "Food > Restaurants"); None when uncategorized
+    pub category_id: Option<Uuid>,
+
+    /// Free-form labels, mostly added by transaction rules
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Who was paid or paid in; recognised from the notes when not given
+    #[serde(default)]
+    pub payee_id: Option<Uuid>,
+
+    #[serde(default)]
+    pub status: TransactionStatus,
+
+    /// Reconciliation that locked the transaction, once reconciled
+    #[serde(default)]
+    pub reconciliation_id: Option<Uuid>,
+
+    /// Split lines spreading the amount over several categories (empty when not split).
+    /// Not a column; filled in by the queries that serve the transaction API.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub splits: Vec<TransactionSplit>,
 }
 
 /// Allows the `Transaction` struct to be returned as a JSON response
@@ -79,6 +138,10 @@ impl Default for Transaction {
             transaction_type: TransactionType::Expense,
             amount: Decimal::ZERO,
             fee: Decimal::ZERO,
+            currency_code: BASE_CURRENCY.to_string(),
+            to_currency_code: BASE_CURRENCY.to_string(),
+            exchange_rate: None,
+            to_amount: None,
             from_account_id: None,
             to_account_id: None,
             created_at: Utc::now(),
@@ -86,10 +149,93 @@ impl Default for Transaction {
             transaction_time: None,
             notes: None,
             image: None,
+            category_id: None,
+            tags: Vec::new(),
+            payee_id: None,
+            status: TransactionStatus::Pending,
+            reconciliation_id: None,
+            splits: Vec::new(),
         }
     }
 }
 
+fn default_currency_code() -> String {
+    BASE_CURRENCY.to_string()
+}
+
+impl Transaction {
+    /// Amount the receiving asset gets, in its own currency
+    pub fn received_amount(&self) -> Decimal {
+        self.to_amount.unwrap_or(self.amount)
+    }
+
+    /// Change of each asset balance when the transaction is applied:
+    /// `to_asset` receives the converted amount, `from_asset` pays the amount plus fee
+    pub fn balance_effects(&self) -> Vec<(Uuid, Decimal)> {
+        let mut effects = Vec::with_capacity(2);
+        if let Some(to_asset_id) = self.to_asset_id {
+            effects.push((to_asset_id, self.received_amount()));
+        }
+        if let Some(from_asset_id) = self.from_asset_id {
+            effects.push((from_asset_id, -(self.amount + self.fee)));
+        }
+        effects
+    }
+}
+
+/// Asset balance changes of several transactions netted per asset,
+/// so they can be written with one update per asset
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BalanceChanges(BTreeMap<Uuid, Decimal>);
+
+impl BalanceChanges {
+    /// Add a transaction's effects; `direction` is `1` to apply and `-1` to revert
+    pub fn add(&mut self, transaction: &Transaction, direction: Decimal) {
+        for (asset_id, amount) in transaction.balance_effects() {
+            *self.0.entry(asset_id).or_default() += amount * direction;
+        }
+    }
+
+    pub fn merge(&mut self, other: BalanceChanges) {
+        for (asset_id, amount) in other.0 {
+            *self.0.entry(asset_id).or_default() += amount;
+        }
+    }
+
+    /// Non-zero changes in asset ID order (the order rows get locked in)
+    pub fn changes(&self) -> Vec<(Uuid, Decimal)> {
+        self.0
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(asset_id, amount)| (*asset_id, *amount))
+            .collect()
+    }
+}
+
+/// One line of a split transaction, with its own amount, category and note
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TransactionSplit {
+    pub id: Uuid,
+
+    /// Transaction the line belongs to
+    pub transaction_id: Uuid,
+
+    pub category_id: Option<Uuid>,
+
+    /// Share of the transaction amount; all lines add up to `Transaction.amount`
+    pub amount: Decimal,
+
+    pub notes: Option<String>,
+}
+
+/// A split line as submitted with a transaction, before it is written to the database
+#[derive(Debug, Deserialize, Clone)]
+pub struct NewTransactionSplit {
+    pub category_id: Option<Uuid>,
+    pub amount: Decimal,
+    pub notes: Option<String>,
+}
+
 /// Wrapper for returning a list of transactions
 #[derive(Debug, Serialize)]
 pub struct TransactionList(pub Vec<Transaction>);
@@ -101,9 +247,104 @@ impl IntoResponse for TransactionList {
     }
 }
 
+/// Kind of change recorded in a transaction's history
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "TEXT")] // Maps to a TEXT column in the database
+pub enum HistoryOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+/// One recorded change of a transaction, with the full row before and after it
+#[derive(Debug, Serialize, FromRow)]
+pub struct TransactionVersion {
+    pub transaction_id: Uuid,
+
+    /// Starts at 1 and increases with every change of the transaction
+    pub version: i32,
+
+    pub operation: HistoryOperation,
+
+    /// The transaction before the change; `None` for `Create`
+    pub before: Option<sqlx::types::Json<Transaction>>,
+
+    /// The transaction after the change; `None` for `Delete`
+    pub after: Option<sqlx::types::Json<Transaction>>,
+
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Wrapper for returning the history of a transaction, oldest version first
+#[derive(Debug, Serialize)]
+pub struct TransactionVersionList(pub Vec<TransactionVersion>);
+
+/// Enables `TransactionVersionList` to be returned as a JSON response
+impl IntoResponse for TransactionVersionList {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// How a batch of transaction operations handles failing items
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// The first failure rolls back the whole batch; later items are skipped
+    #[default]
+    AllOrNothing,
+    /// Failing items are left out and everything else is kept
+    BestEffort,
+}
+
+/// Outcome of one item of a batch
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum BatchItemStatus {
+    Succeeded,
+    Failed,
+    /// Went through, but was undone because another item failed
+    RolledBack,
+    /// Not attempted because an earlier item failed
+    Skipped,
+}
+
+/// Result of one item of a batch, in request order
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    /// Position of the operation in the request
+    pub index: usize,
+
+    pub status: BatchItemStatus,
+
+    /// HTTP status the item would have got as a single request (e.g., 201, 404, 422)
+    pub code: u16,
+
+    /// Transaction created, updated or deleted by the item
+    pub transaction_id: Option<Uuid>,
+
+    /// Rule of the transaction type the item broke, when it was refused for one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Result of a batch of transaction operations
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    /// Whether anything was written
+    pub committed: bool,
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Enables `BatchResult` to be returned as a JSON response
+impl IntoResponse for BatchResult {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
 /// Extended version of `Transaction` used for frontend APIs,
 /// includes `from_asset_type` and `to_asset_type` for easier display
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, FromRow)]
 pub struct EnrichedTransaction {
     pub id: Uuid,
     pub from_asset_id: Option<Uuid>,
@@ -111,6 +352,10 @@ pub struct EnrichedTransaction {
     pub transaction_type: TransactionType,
     pub amount: Decimal,
     pub fee: Decimal,
+    pub currency_code: String,
+    pub to_currency_code: String,
+    pub exchange_rate: Option<Decimal>,
+    pub to_amount: Option<Decimal>,
     pub from_account_id: Option<Uuid>,
     pub to_account_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
@@ -118,6 +363,14 @@ pub struct EnrichedTransaction {
     pub transaction_time: Option<DateTime<Utc>>,
     pub notes: Option<String>,
     pub image: Option<String>,
+    pub category_id: Option<Uuid>,
+    pub tags: Vec<String>,
+    pub payee_id: Option<Uuid>,
+    pub status: TransactionStatus,
+    pub reconciliation_id: Option<Uuid>,
+
+    #[sqlx(skip)]
+    pub splits: Vec<TransactionSplit>,
 
     /// Human-readable asset type of the source asset (e.g., "cash", "bank")
     pub from_asset_type: Option<String>,
@@ -126,13 +379,178 @@ pub struct EnrichedTransaction {
     pub to_asset_type: Option<String>,
 }
 
-/// Wrapper for returning a list of enriched transactions with asset type details
+/// One page of enriched transactions
 #[derive(Debug, Serialize)]
-pub struct EnrichedTransactionList(pub Vec<EnrichedTransaction>);
+pub struct EnrichedTransactionPage {
+    pub transactions: Vec<EnrichedTransaction>,
 
-/// Enables `EnrichedTransactionList` to be returned as a JSON response
-impl IntoResponse for EnrichedTransactionList {
+    /// Pass back as `cursor` to fetch the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Enables `EnrichedTransactionPage` to be returned as a JSON response
+impl IntoResponse for EnrichedTransactionPage {
     fn into_response(self) -> axum::response::Response {
         Json(self).into_response()
     }
 }
+
+/// Sort direction of a transaction listing, by transaction time
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    /// Newest first
+    #[default]
+    Desc,
+}
+
+/// Position in a transaction listing: the sort key of the last row of the previous page.
+/// Serialized as `<microseconds since epoch>_<transaction id>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransactionCursor {
+    pub time: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl fmt::Display for TransactionCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.time.timestamp_micros(), self.id)
+    }
+}
+
+impl FromStr for TransactionCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (micros, id) = s.split_once('_').ok_or("cursor must be <micros>_<id>")?;
+        let micros: i64 = micros.parse().map_err(|_| "invalid cursor timestamp")?;
+        let time =
+            DateTime::from_timestamp_micros(micros).ok_or("cursor timestamp out of range")?;
+        let id = Uuid::parse_str(id).map_err(|_| "invalid cursor id")?;
+        Ok(Self { time, id })
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionCursor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Filters, sort order and pagination for listing an account's transactions.
+/// Every field is optional and taken from the query string.
+#[derive(Debug, Deserialize, Default)]
+pub struct TransactionFilter {
+    /// Only transactions at or after this time
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only transactions before this time
+    pub to: Option<DateTime<Utc>>,
+
+    pub transaction_type: Option<TransactionType>,
+
+    /// Only transactions in this category or one of its subcategories
+    pub category_id: Option<Uuid>,
+
+    /// Only transactions moving money from or to this asset
+    pub asset_id: Option<Uuid>,
+
+    pub payee_id: Option<Uuid>,
+
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+
+    /// Full-text search on `notes`
+    pub q: Option<String>,
+
+    #[serde(default)]
+    pub sort: SortOrder,
+
+    /// Page size (defaults to `DEFAULT_PAGE_SIZE`, capped at `MAX_PAGE_SIZE`)
+    pub limit: Option<i64>,
+
+    /// `next_cursor` of the previous page
+    pub cursor: Option<TransactionCursor>,
+}
+
+impl TransactionFilter {
+    pub const DEFAULT_PAGE_SIZE: i64 = 100;
+    pub const MAX_PAGE_SIZE: i64 = 500;
+
+    /// Effective page size after applying the default and the upper bound
+    pub fn page_size(&self) -> i64 {
+        self.limit
+            .unwrap_or(Self::DEFAULT_PAGE_SIZE)
+            .clamp(1, Self::MAX_PAGE_SIZE)
+    }
+}
+
+/// File format of a transaction export
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// Quicken Interchange Format
+    Qif,
+}
+
+impl ExportFormat {
+    /// `Content-Type` of the exported file
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Qif => "application/qif",
+        }
+    }
+
+    /// File name extension of the exported file
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Qif => "qif",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_changes_net_per_asset() {
+        let cash = Uuid::new_v4();
+        let bank = Uuid::new_v4();
+
+        let withdrawal = Transaction {
+            transaction_type: TransactionType::InternalTransfer,
+            from_asset_id: Some(bank),
+            to_asset_id: Some(cash),
+            amount: Decimal::new(100, 0),
+            fee: Decimal::new(5, 0),
+            ..Transaction::default()
+        };
+        let lunch = Transaction {
+            from_asset_id: Some(cash),
+            amount: Decimal::new(100, 0),
+            ..Transaction::default()
+        };
+
+        let mut changes = BalanceChanges::default();
+        changes.add(&withdrawal, Decimal::ONE);
+        changes.add(&lunch, Decimal::ONE);
+        // Cash nets out to zero and is left alone
+        assert_eq!(changes.changes(), vec![(bank, Decimal::new(-105, 0))]);
+
+        // Reverting what was applied leaves nothing to write
+        let mut reverted = BalanceChanges::default();
+        reverted.add(&withdrawal, Decimal::NEGATIVE_ONE);
+        reverted.add(&lunch, Decimal::NEGATIVE_ONE);
+        changes.merge(reverted);
+        assert!(changes.changes().is_empty());
+    }
+}
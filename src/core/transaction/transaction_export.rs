@@ -0,0 +1,273 @@
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{EnrichedTransaction, ExportFormat, TransactionType};
+
+/// Column names of a CSV export
+const CSV_COLUMNS: [&str; 15] = [
+    "id",
+    "transaction_time",
+    "transaction_type",
+    "amount",
+    "fee",
+    "currency_code",
+    "from_asset_id",
+    "from_asset_type",
+    "to_asset_id",
+    "to_asset_type",
+    "to_amount",
+    "to_currency_code",
+    "category_id",
+    "tags",
+    "notes",
+];
+
+/// Renders an export chunk by chunk, so pages of transactions can be written out
+/// as they are fetched instead of being collected first.
+pub struct ExportWriter {
+    format: ExportFormat,
+
+    /// Account being exported; QIF amounts are signed from its point of view
+    account_id: Uuid,
+
+    /// Asset filter of the export; when set, QIF amounts are signed from its point of view
+    asset_id: Option<Uuid>,
+
+    rows_written: usize,
+}
+
+impl ExportWriter {
+    pub fn new(format: ExportFormat, account_id: Uuid, asset_id: Option<Uuid>) -> Self {
+        ExportWriter {
+            format,
+            account_id,
+            asset_id,
+            rows_written: 0,
+        }
+    }
+
+    /// Bytes that open the file
+    pub fn header(&self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Csv => format!("{}\n", CSV_COLUMNS.join(",")).into_bytes(),
+            ExportFormat::Json => b"[".to_vec(),
+            ExportFormat::Qif => b"!Type:Bank\n".to_vec(),
+        }
+    }
+
+    /// Render the next batch of transactions
+    pub fn rows(&mut self, transactions: &[EnrichedTransaction]) -> Result<Vec<u8>, String> {
+        let chunk = match self.format {
+            ExportFormat::Csv => csv_rows(transactions)?,
+            ExportFormat::Json => self.json_rows(transactions)?,
+            ExportFormat::Qif => transactions
+                .iter()
+                .flat_map(|tx| self.qif_record(tx).into_bytes())
+                .collect(),
+        };
+        self.rows_written += transactions.len();
+        Ok(chunk)
+    }
+
+    /// Bytes that close the file
+    pub fn footer(&self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Json => b"]".to_vec(),
+            ExportFormat::Csv | ExportFormat::Qif => Vec::new(),
+        }
+    }
+
+    /// JSON array elements, comma-separated across batches
+    fn json_rows(&self, transactions: &[EnrichedTransaction]) -> Result<Vec<u8>, String> {
+        let mut chunk = Vec::new();
+        for (index, tx) in transactions.iter().enumerate() {
+            if self.rows_written + index > 0 {
+                chunk.push(b',');
+            }
+            serde_json::to_writer(&mut chunk, tx).map_err(|err| err.to_string())?;
+        }
+        Ok(chunk)
+    }
+
+    /// One QIF record; the amount is positive for money coming in
+    fn qif_record(&self, tx: &EnrichedTransaction) -> String {
+        let time = tx.transaction_time.unwrap_or(tx.created_at);
+        let mut record = format!(
+            "D{}\nT{}\n",
+            time.format("%m/%d/%Y"),
+            self.signed_amount(tx)
+        );
+        if let Some(notes) = tx.notes.as_deref().filter(|notes| !notes.is_empty()) {
+            // QIF fields are line based
+            record.push_str(&format!("M{}\n", notes.replace(['\r', '\n'], " ")));
+        }
+        record.push_str("^\n");
+        record
+    }
+
+    /// Balance effect of a transaction on the exported asset, or on the account
+    /// when the export isn't narrowed to one asset. Mirrors `apply_transaction_balance`:
+    /// the source pays the amount plus the fee, the destination gets the converted amount.
+    fn signed_amount(&self, tx: &EnrichedTransaction) -> Decimal {
+        let incoming = tx.to_amount.unwrap_or(tx.amount);
+        let outgoing = -(tx.amount + tx.fee);
+
+        if let Some(asset_id) = self.asset_id {
+            let mut amount = Decimal::ZERO;
+            if tx.to_asset_id == Some(asset_id) {
+                amount += incoming;
+            }
+            if tx.from_asset_id == Some(asset_id) {
+                amount += outgoing;
+            }
+            return amount;
+        }
+
+        match tx.transaction_type {
+            TransactionType::Income => incoming,
+            TransactionType::Expense => outgoing,
+            TransactionType::Transfer | TransactionType::InternalTransfer => {
+                let mut amount = Decimal::ZERO;
+                if tx.to_account_id == Some(self.account_id) {
+                    amount += incoming;
+                }
+                if tx.from_account_id == Some(self.account_id) {
+                    amount += outgoing;
+                }
+                amount
+            }
+        }
+    }
+}
+
+/// CSV records for a batch, without the header; split lines are not included
+fn csv_rows(transactions: &[EnrichedTransaction]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+
+    let optional = |id: Option<Uuid>| id.map(|id| id.to_string()).unwrap_or_default();
+    for tx in transactions {
+        writer
+            .write_record([
+                tx.id.to_string(),
+                tx.transaction_time.unwrap_or(tx.created_at).to_rfc3339(),
+                format!("{:?}", tx.transaction_type),
+                tx.amount.to_string(),
+                tx.fee.to_string(),
+                tx.currency_code.clone(),
+                optional(tx.from_asset_id),
+                tx.from_asset_type.clone().unwrap_or_default(),
+                optional(tx.to_asset_id),
+                tx.to_asset_type.clone().unwrap_or_default(),
+                tx.to_amount.unwrap_or(tx.amount).to_string(),
+                tx.to_currency_code.clone(),
+                optional(tx.category_id),
+                tx.tags.join(";"),
+                tx.notes.clone().unwrap_or_default(),
+            ])
+            .map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionStatus;
+    use chrono::{TimeZone, Utc};
+
+    fn transaction(
+        transaction_type: TransactionType,
+        from_asset_id: Option<Uuid>,
+        to_asset_id: Option<Uuid>,
+        amount: i64,
+        notes: &str,
+    ) -> EnrichedTransaction {
+        EnrichedTransaction {
+            id: Uuid::new_v4(),
+            from_asset_id,
+            to_asset_id,
+            transaction_type,
+            amount: Decimal::new(amount, 0),
+            fee: Decimal::ONE,
+            currency_code: "TWD".to_string(),
+            to_currency_code: "TWD".to_string(),
+            exchange_rate: None,
+            to_amount: None,
+            from_account_id: None,
+            to_account_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            transaction_time: Some(Utc.with_ymd_and_hms(2025, 7, 4, 9, 30, 0).unwrap()),
+            notes: Some(notes.to_string()),
+            image: None,
+            category_id: None,
+            tags: vec!["cash".to_string(), "atm".to_string()],
+            payee_id: None,
+            status: TransactionStatus::Cleared,
+            reconciliation_id: None,
+            splits: Vec::new(),
+            from_asset_type: from_asset_id.map(|_| "bank".to_string()),
+            to_asset_type: to_asset_id.map(|_| "cash".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_export_chunks() {
+        let bank = Uuid::new_v4();
+        let cash = Uuid::new_v4();
+        let withdrawal = transaction(
+            TransactionType::InternalTransfer,
+            Some(bank),
+            Some(cash),
+            100,
+            "ATM, main street",
+        );
+        let salary = transaction(TransactionType::Income, None, Some(bank), 3000, "Salary");
+
+        // CSV: quoted notes and the resolved asset types
+        let mut writer = ExportWriter::new(ExportFormat::Csv, Uuid::new_v4(), None);
+        let csv = String::from_utf8(
+            [
+                writer.header(),
+                writer.rows(std::slice::from_ref(&withdrawal)).unwrap(),
+                writer.footer(),
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+        let row = lines.next().unwrap();
+        assert!(row.contains(",InternalTransfer,100,1,TWD,"));
+        assert!(row.contains(",bank,"));
+        assert!(row.ends_with(",cash;atm,\"ATM, main street\""));
+
+        // JSON: one array across several batches
+        let mut writer = ExportWriter::new(ExportFormat::Json, Uuid::new_v4(), None);
+        let json = [
+            writer.header(),
+            writer.rows(&[]).unwrap(),
+            writer.rows(std::slice::from_ref(&withdrawal)).unwrap(),
+            writer.rows(std::slice::from_ref(&salary)).unwrap(),
+            writer.footer(),
+        ]
+        .concat();
+        let parsed: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+        assert_eq!(parsed[0]["to_asset_type"], "cash");
+
+        // QIF: signed from the bank asset's point of view, fee included
+        let mut writer = ExportWriter::new(ExportFormat::Qif, Uuid::new_v4(), Some(bank));
+        let qif = String::from_utf8(
+            [writer.header(), writer.rows(&[withdrawal, salary]).unwrap()].concat(),
+        )
+        .unwrap();
+        assert_eq!(
+            qif,
+            "!Type:Bank\nD07/04/2025\nT-101\nMATM, main street\n^\nD07/04/2025\nT3000\nMSalary\n^\n"
+        );
+    }
+}
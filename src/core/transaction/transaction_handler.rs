@@ -1,19 +1,31 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
+use futures_util::stream::{self, StreamExt};
 use rust_decimal::Decimal;
 use serde::Deserialize;
-use sqlx::PgPool;
+use sqlx::{Connection, PgConnection, PgPool};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::models::{EnrichedTransactionList, Transaction, TransactionType};
+use crate::core::transaction::transaction_export::ExportWriter;
+use crate::core::transaction::transaction_validation::{check_transaction, TransactionError};
+use crate::models::{
+    is_reconciled_lock, BalanceChanges, BatchItemResult, BatchItemStatus, BatchMode, BatchResult,
+    ExportFormat, NewTransactionSplit, Transaction, TransactionCursor, TransactionFilter,
+    TransactionStatus, TransactionType, TransactionVersion, TransactionVersionList,
+};
 use crate::repository::{
-    create_transaction, delete_transaction, get_transaction_by_transation_id,
-    get_transactions_by_account_id, update_asset_balance, update_transaction_info,
+    apply_balance_changes, apply_rules_to_transaction, create_transaction, delete_transaction,
+    find_payee_by_alias, get_latest_transaction_version, get_transaction_by_transation_id,
+    get_transaction_history, get_transaction_splits, get_transaction_version,
+    get_transactions_by_account_id, lock_transaction_by_id, replace_transaction_splits,
+    restore_transaction, revert_transaction_balance, stage_transaction_balance,
+    update_transaction_info, update_transaction_status,
 };
 
 /// Payload for creating a new transaction
@@ -29,6 +41,16 @@ pub struct CreateTransactionRequest {
     transaction_time: Option<chrono::DateTime<chrono::Utc>>,
     notes: Option<String>,
     image: Option<String>,
+    category_id: Option<Uuid>,
+
+    /// Rate to convert into the receiving asset's currency; defaults to the current rate
+    exchange_rate: Option<Decimal>,
+
+    /// Recognised from the notes by payee name or alias when omitted
+    payee_id: Option<Uuid>,
+
+    /// Split lines; their amounts must add up to the transaction amount
+    splits: Option<Vec<NewTransactionSplit>>,
 }
 
 /// Payload for updating an existing transaction
@@ -44,6 +66,15 @@ pub struct UpdateTransactionRequest {
     transaction_time: Option<chrono::DateTime<chrono::Utc>>,
     notes: Option<String>,
     image: Option<String>,
+    category_id: Option<Uuid>,
+
+    /// Corrects the rate of a transaction between currencies
+    exchange_rate: Option<Decimal>,
+
+    payee_id: Option<Uuid>,
+
+    /// Replaces all split lines when present; an empty list removes the split
+    splits: Option<Vec<NewTransactionSplit>>,
 }
 
 /// Handler: Get a single transaction by its ID
@@ -60,13 +91,15 @@ pub async fn get_transaction_by_transaction_id_handler(
     }
 }
 
-/// Handler: Get all transactions associated with a given account ID
+/// Handler: Search the transactions associated with a given account ID,
+/// one page at a time (see `TransactionFilter` for the query parameters)
 pub async fn get_transaction_by_account_id_handler(
     State(pool): State<Arc<PgPool>>,
     Path(account_id): Path<Uuid>,
+    Query(filter): Query<TransactionFilter>,
 ) -> impl IntoResponse {
-    match get_transactions_by_account_id(&pool, account_id).await {
-        Ok(tx) => EnrichedTransactionList(tx).into_response(),
+    match get_transactions_by_account_id(&pool, account_id, &filter).await {
+        Ok(page) => page.into_response(),
         Err(err) => {
             eprintln!(
                 "Failed to fetch transactions by account {}: {:?}",
@@ -77,89 +110,503 @@ pub async fn get_transaction_by_account_id_handler(
     }
 }
 
-/// Handler: Create a new transaction and update the asset balances accordingly
-pub async fn add_transaction_handler(
+/// Query parameter selecting the file format of an export
+#[derive(Deserialize)]
+pub struct TransactionExportQuery {
+    pub format: ExportFormat,
+}
+
+/// Handler: Export every transaction of an account matching the listing filters
+/// as CSV, JSON or QIF.
+///
+/// Rows are fetched page by page and written to the response as they arrive, so large
+/// accounts are never held in memory at once. `limit` and `cursor` are ignored.
+pub async fn export_transactions_handler(
     State(pool): State<Arc<PgPool>>,
-    Json(payload): Json<CreateTransactionRequest>,
+    Path(account_id): Path<Uuid>,
+    Query(mut filter): Query<TransactionFilter>,
+    Query(export): Query<TransactionExportQuery>,
 ) -> impl IntoResponse {
-    match create_transaction(
-        &pool,
-        payload.from_asset_id,
-        payload.to_asset_id,
-        payload.transaction_type,
-        payload.amount,
-        payload.fee,
-        payload.from_account_id,
-        payload.to_account_id,
-        payload.transaction_time,
-        payload.notes,
-        payload.image,
-    )
-    .await
-    {
-        Ok(transaction) => {
-            // Apply balance to `to_asset`
-            if let Some(to_asset_id) = payload.to_asset_id {
-                if let Err(e) = update_asset_balance(&pool, to_asset_id, payload.amount).await {
-                    eprintln!("Failed to update to_asset balance: {:?}", e);
-                }
+    filter.limit = Some(TransactionFilter::MAX_PAGE_SIZE);
+    filter.cursor = None;
+
+    // Fetch the first page up front so a failing query still gets a proper status
+    let mut writer = ExportWriter::new(export.format, account_id, filter.asset_id);
+    let (first_chunk, next_cursor) =
+        match export_page(&pool, account_id, &filter, &mut writer).await {
+            Ok(page) => page,
+            Err(err) => {
+                eprintln!(
+                    "Failed to export transactions of account {}: {}",
+                    account_id, err
+                );
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
             }
+        };
+    let head = [writer.header(), first_chunk].concat();
+
+    let rest = stream::unfold(Some((filter, writer, next_cursor)), move |state| {
+        let pool = pool.clone();
+        async move {
+            let (mut filter, mut writer, next_cursor) = state?;
+            let Some(cursor) = next_cursor else {
+                return Some((Ok(Bytes::from(writer.footer())), None));
+            };
 
-            // Deduct from `from_asset`, including fee
-            if let Some(from_asset_id) = payload.from_asset_id {
-                let mut offset = payload.amount;
-                if let Some(fee) = payload.fee {
-                    offset += fee;
+            filter.cursor = Some(cursor);
+            match export_page(&pool, account_id, &filter, &mut writer).await {
+                Ok((chunk, next_cursor)) => {
+                    Some((Ok(Bytes::from(chunk)), Some((filter, writer, next_cursor))))
                 }
-                if let Err(e) = update_asset_balance(&pool, from_asset_id, -offset).await {
-                    eprintln!("Failed to update from_asset balance: {:?}", e);
+                Err(err) => {
+                    // Headers are already sent; abort the response so the client
+                    // doesn't mistake a truncated file for a complete one
+                    eprintln!(
+                        "Failed to export transactions of account {}: {}",
+                        account_id, err
+                    );
+                    Some((Err(std::io::Error::other(err)), None))
                 }
             }
+        }
+    });
+    let body = stream::once(async { Ok::<_, std::io::Error>(Bytes::from(head)) }).chain(rest);
 
-            transaction.into_response()
+    let disposition = format!(
+        "attachment; filename=\"transactions-{}.{}\"",
+        account_id,
+        export.format.extension()
+    );
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                export.format.content_type().to_string(),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Fetch and render one page of an export, returning the cursor of the next page if any
+async fn export_page(
+    pool: &PgPool,
+    account_id: Uuid,
+    filter: &TransactionFilter,
+    writer: &mut ExportWriter,
+) -> Result<(Vec<u8>, Option<TransactionCursor>), String> {
+    let page = get_transactions_by_account_id(pool, account_id, filter)
+        .await
+        .map_err(|err| err.to_string())?;
+    let chunk = writer.rows(&page.transactions)?;
+    let next_cursor = page.next_cursor.map(|cursor| cursor.parse()).transpose()?;
+    Ok((chunk, next_cursor))
+}
+
+/// Payload for marking a transaction as seen (or not) on the bank's statement
+#[derive(Deserialize)]
+pub struct UpdateTransactionStatusRequest {
+    status: TransactionStatus,
+}
+
+/// Most operations accepted in one batch request
+const MAX_BATCH_SIZE: usize = 500;
+
+/// One operation of a batch request, tagged by `op`
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    Create(CreateTransactionRequest),
+    Update {
+        id: Uuid,
+        #[serde(flatten)]
+        changes: UpdateTransactionRequest,
+    },
+    Delete {
+        id: Uuid,
+    },
+}
+
+/// Payload for creating, updating and deleting many transactions in one request
+#[derive(Deserialize)]
+pub struct BatchTransactionRequest {
+    #[serde(default)]
+    mode: BatchMode,
+    operations: Vec<BatchOperation>,
+}
+
+/// Handler: Create a new transaction and update the asset balances accordingly
+///
+/// The insert and both balance updates run in one database transaction,
+/// so a failure in any step leaves neither the ledger nor the balances changed.
+pub async fn add_transaction_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<CreateTransactionRequest>,
+) -> impl IntoResponse {
+    match create_transaction_atomically(&pool, payload).await {
+        Ok(transaction) => transaction.into_response(),
+        Err(TransactionError::Invalid(err)) => {
+            eprintln!("Rejected transaction: {}", err);
+            err.into_response()
         }
-        Err(err) => {
+        Err(TransactionError::Database(sqlx::Error::Database(err))) if err.is_check_violation() => {
+            eprintln!("Rejected transaction: {}", err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+        Err(TransactionError::Database(err)) => {
             eprintln!("Failed to create transaction: {:?}", err);
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
 
-/// Handler: Update an existing transaction and rollback/reapply its asset balance
+/// Handler: Update an existing transaction and rollback/reapply its asset balance.
+/// The updated transaction must still follow the rules of its type.
+/// Reconciled transactions only take category changes; anything else is a conflict.
 pub async fn update_transaction_handler(
     State(pool): State<Arc<PgPool>>,
     Path(transaction_id): Path<Uuid>,
     Json(payload): Json<UpdateTransactionRequest>,
-) -> (StatusCode, Json<Transaction>) {
-    // Step 1: Load existing transaction for rollback
-    let old_transaction = match get_transaction_by_transation_id(&pool, transaction_id).await {
-        Ok(tx) => tx,
+) -> impl IntoResponse {
+    match update_transaction_atomically(&pool, transaction_id, payload).await {
+        Ok(transaction) => transaction.into_response(),
+        Err(TransactionError::Invalid(err)) => {
+            eprintln!("Rejected update of transaction {}: {}", transaction_id, err);
+            err.into_response()
+        }
+        Err(TransactionError::Database(sqlx::Error::RowNotFound)) => {
+            eprintln!("Transaction {} not found, update skipped", transaction_id);
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Err(TransactionError::Database(sqlx::Error::Database(err)))
+            if is_reconciled_lock(&*err) =>
+        {
+            eprintln!(
+                "Transaction {} is reconciled, update refused",
+                transaction_id
+            );
+            StatusCode::CONFLICT.into_response()
+        }
+        Err(TransactionError::Database(sqlx::Error::Database(err))) if err.is_check_violation() => {
+            eprintln!("Rejected update of transaction {}: {}", transaction_id, err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+        Err(TransactionError::Database(err)) => {
+            eprintln!("Failed to update transaction {}: {:?}", transaction_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Mark a transaction as pending or cleared while reconciling.
+/// Transactions become reconciled only by completing a reconciliation.
+pub async fn update_transaction_status_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+    Json(payload): Json<UpdateTransactionStatusRequest>,
+) -> impl IntoResponse {
+    if payload.status == TransactionStatus::Reconciled {
+        return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+    }
+
+    match update_transaction_status(&*pool, transaction_id, payload.status).await {
+        Ok(transaction) => transaction.into_response(),
+        Err(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(sqlx::Error::Database(err)) if is_reconciled_lock(&*err) => {
+            StatusCode::CONFLICT.into_response()
+        }
         Err(err) => {
             eprintln!(
-                "Failed to fetch transaction before update {}: {:?}",
+                "Failed to update status of transaction {}: {:?}",
                 transaction_id, err
             );
-            return (StatusCode::NOT_FOUND, Json(dummy_transaction()));
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Delete a transaction and roll back its asset balance changes
+pub async fn delete_transaction_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match delete_transaction_atomically(&pool, transaction_id).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(sqlx::Error::RowNotFound) => {
+            eprintln!("Transaction {} not found, delete skipped", transaction_id);
+            StatusCode::NO_CONTENT.into_response()
         }
+        Err(sqlx::Error::Database(err)) if is_reconciled_lock(&*err) => {
+            eprintln!(
+                "Transaction {} is reconciled, delete refused",
+                transaction_id
+            );
+            StatusCode::CONFLICT.into_response()
+        }
+        Err(err) => {
+            eprintln!("Failed to delete transaction {}: {:?}", transaction_id, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handler: Create, update and delete many transactions in one request, e.g. when a
+/// client syncs its offline entries. Returns a result per operation, in request order.
+/// Balance changes are netted per asset and written once at the end.
+pub async fn batch_transactions_handler(
+    State(pool): State<Arc<PgPool>>,
+    Json(payload): Json<BatchTransactionRequest>,
+) -> impl IntoResponse {
+    if payload.operations.len() > MAX_BATCH_SIZE {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    }
+
+    match run_batch_atomically(&pool, payload).await {
+        Ok(result) => result.into_response(),
+        Err(sqlx::Error::Database(err)) if err.is_check_violation() => {
+            eprintln!("Rejected transaction batch: {}", err);
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+        Err(err) => {
+            eprintln!("Failed to run transaction batch: {:?}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Status a failed batch item would have got as a single request
+fn batch_error_status(err: &TransactionError) -> StatusCode {
+    let err = match err {
+        TransactionError::Invalid(_) => return StatusCode::UNPROCESSABLE_ENTITY,
+        TransactionError::Database(err) => err,
     };
+    match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        sqlx::Error::Database(err) if is_reconciled_lock(&**err) => StatusCode::CONFLICT,
+        sqlx::Error::Database(err)
+            if err.is_check_violation() || err.is_foreign_key_violation() =>
+        {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
 
-    // Step 2: Revert old balance effects
-    if let Some(to_asset_id) = old_transaction.to_asset_id {
-        if let Err(e) = update_asset_balance(&pool, to_asset_id, -old_transaction.amount).await {
-            eprintln!("Failed to revert to_asset balance: {:?}", e);
+/// Handler: List every recorded change of a transaction, oldest first.
+/// Works for deleted transactions too.
+pub async fn get_transaction_history_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match get_transaction_history(&*pool, transaction_id).await {
+        Ok(versions) if versions.is_empty() => StatusCode::NOT_FOUND.into_response(),
+        Ok(versions) => TransactionVersionList(versions).into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch history of transaction {}: {:?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
+}
 
-    if let Some(from_asset_id) = old_transaction.from_asset_id {
-        let offset = old_transaction.amount + old_transaction.fee;
-        if let Err(e) = update_asset_balance(&pool, from_asset_id, offset).await {
-            eprintln!("Failed to revert from_asset balance: {:?}", e);
+/// Handler: Put a transaction back the way it was after the given version,
+/// including its asset balance effects. Re-creates the transaction if it was deleted.
+pub async fn restore_transaction_version_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path((transaction_id, version)): Path<(Uuid, i32)>,
+) -> impl IntoResponse {
+    let image = match get_transaction_version(&*pool, transaction_id, version).await {
+        Ok(TransactionVersion {
+            after: Some(image), ..
+        }) => image.0,
+        // The version is the deletion itself; there is nothing to put back
+        Ok(_) => return StatusCode::UNPROCESSABLE_ENTITY.into_response(),
+        Err(sqlx::Error::RowNotFound) => return StatusCode::NOT_FOUND.into_response(),
+        Err(err) => {
+            eprintln!(
+                "Failed to fetch version {} of transaction {}: {:?}",
+                version, transaction_id, err
+            );
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
+    };
+
+    match restore_transaction_atomically(&pool, &image).await {
+        Ok(transaction) => transaction.into_response(),
+        Err(err) => history_error_response(transaction_id, err),
+    }
+}
+
+/// Handler: Undo the latest change of a transaction, including its asset balance effects.
+/// Undoing the creation deletes the transaction. The undo is recorded like any other
+/// change, so undoing twice redoes.
+pub async fn undo_transaction_handler(
+    State(pool): State<Arc<PgPool>>,
+    Path(transaction_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match undo_transaction_atomically(&pool, transaction_id).await {
+        Ok(Some(transaction)) => transaction.into_response(),
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => history_error_response(transaction_id, err),
+    }
+}
+
+/// Map an error of putting a past version back to a response; a version that breaks the
+/// current transaction rules (e.g. its asset moved to another account) is refused with 422
+fn history_error_response(transaction_id: Uuid, err: TransactionError) -> axum::response::Response {
+    let err = match err {
+        TransactionError::Invalid(err) => {
+            eprintln!(
+                "Rejected restore of transaction {}: {}",
+                transaction_id, err
+            );
+            return err.into_response();
+        }
+        TransactionError::Database(err) => err,
+    };
+    match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND.into_response(),
+        sqlx::Error::Database(err) if is_reconciled_lock(&*err) => {
+            eprintln!(
+                "Transaction {} is reconciled, restore refused",
+                transaction_id
+            );
+            StatusCode::CONFLICT.into_response()
+        }
+        // e.g. the asset of the old version is gone, or split lines no longer add up
+        sqlx::Error::Database(err)
+            if err.is_check_violation() || err.is_foreign_key_violation() =>
+        {
+            eprintln!(
+                "Rejected restore of transaction {}: {}",
+                transaction_id, err
+            );
+            StatusCode::UNPROCESSABLE_ENTITY.into_response()
+        }
+        err => {
+            eprintln!(
+                "Failed to restore transaction {}: {:?}",
+                transaction_id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Insert a transaction with its split lines and apply its balance effects.
+/// Dropping `db_tx` on an early return rolls everything back.
+async fn create_transaction_atomically(
+    pool: &PgPool,
+    payload: CreateTransactionRequest,
+) -> Result<Transaction, TransactionError> {
+    let mut db_tx = pool.begin().await?;
+    let mut changes = BalanceChanges::default();
+
+    let transaction = create_transaction_with_splits(&mut db_tx, payload, &mut changes).await?;
+    apply_balance_changes(&mut db_tx, &changes).await?;
+
+    db_tx.commit().await?;
+    Ok(transaction)
+}
+
+/// Revert the old balance effects, update the row and apply the new balance effects
+async fn update_transaction_atomically(
+    pool: &PgPool,
+    transaction_id: Uuid,
+    payload: UpdateTransactionRequest,
+) -> Result<Transaction, TransactionError> {
+    let mut db_tx = pool.begin().await?;
+    let mut changes = BalanceChanges::default();
+
+    let transaction =
+        update_transaction_with_splits(&mut db_tx, transaction_id, payload, &mut changes).await?;
+    apply_balance_changes(&mut db_tx, &changes).await?;
+
+    db_tx.commit().await?;
+    Ok(transaction)
+}
+
+/// Revert a transaction's balance effects and delete the row
+async fn delete_transaction_atomically(
+    pool: &PgPool,
+    transaction_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+    let mut changes = BalanceChanges::default();
+
+    remove_transaction(&mut db_tx, transaction_id, &mut changes).await?;
+    apply_balance_changes(&mut db_tx, &changes).await?;
+
+    db_tx.commit().await
+}
+
+/// Insert a transaction with its split lines, journal it and stage its balance changes.
+/// A transaction breaking the rules of its type is refused.
+async fn create_transaction_with_splits(
+    conn: &mut PgConnection,
+    payload: CreateTransactionRequest,
+    changes: &mut BalanceChanges,
+) -> Result<Transaction, TransactionError> {
+    let account_id = payload.from_account_id.or(payload.to_account_id);
+    let payee_id = match (payload.payee_id, account_id, payload.notes.as_deref()) {
+        (Some(payee_id), _, _) => Some(payee_id),
+        (None, Some(account_id), Some(notes)) => find_payee_by_alias(&mut *conn, account_id, notes)
+            .await?
+            .map(|payee| payee.id),
+        _ => None,
+    };
+
+    let mut transaction = create_transaction(
+        &mut *conn,
+        payload.from_asset_id,
+        payload.to_asset_id,
+        payload.transaction_type,
+        payload.amount,
+        payload.fee,
+        payload.from_account_id,
+        payload.to_account_id,
+        payload.transaction_time,
+        payload.notes,
+        payload.image,
+        payload.category_id,
+        payload.exchange_rate,
+        payee_id,
+    )
+    .await?;
+    check_transaction(conn, &transaction).await?;
+
+    if let Some(splits) = payload.splits {
+        transaction.splits = replace_transaction_splits(conn, transaction.id, &splits).await?;
     }
 
-    // Step 3: Apply new update
-    let updated_transaction = match update_transaction_info(
-        &pool,
+    stage_transaction_balance(conn, &transaction, Decimal::ONE, changes).await?;
+
+    // Let the account's rules fill in the category and tags
+    apply_rules_to_transaction(conn, &mut transaction).await?;
+
+    Ok(transaction)
+}
+
+/// Update a transaction, staging the reversal of its old balance effects and the new ones
+async fn update_transaction_with_splits(
+    conn: &mut PgConnection,
+    transaction_id: Uuid,
+    payload: UpdateTransactionRequest,
+    changes: &mut BalanceChanges,
+) -> Result<Transaction, TransactionError> {
+    // Step 1: Lock the existing transaction so concurrent edits can't interleave
+    let old_transaction = lock_transaction_by_id(&mut *conn, transaction_id).await?;
+
+    // Step 2: Revert old balance effects
+    stage_transaction_balance(conn, &old_transaction, Decimal::NEGATIVE_ONE, changes).await?;
+
+    // Step 3: Apply new update; a payload carrying nothing but splits leaves the row as is
+    let updated = update_transaction_info(
+        &mut *conn,
         transaction_id,
         payload.from_asset_id,
         payload.to_asset_id,
@@ -171,75 +618,192 @@ pub async fn update_transaction_handler(
         payload.transaction_time,
         payload.notes,
         payload.image,
+        payload.category_id,
+        payload.exchange_rate,
+        payload.payee_id,
     )
-    .await
-    {
-        Ok(tx) => tx,
-        Err(err) => {
-            eprintln!("Failed to update transaction {}: {:?}", transaction_id, err);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(dummy_transaction()));
+    .await;
+    let mut updated_transaction = match updated {
+        Err(sqlx::Error::RowNotFound) if payload.splits.is_some() => {
+            lock_transaction_by_id(&mut *conn, transaction_id).await?
         }
+        result => result?,
     };
+    check_transaction(conn, &updated_transaction).await?;
 
-    // Step 4: Apply new balance effects
-    if let Some(to_asset_id) = updated_transaction.to_asset_id {
-        if let Err(e) = update_asset_balance(&pool, to_asset_id, updated_transaction.amount).await {
-            eprintln!("Failed to apply new to_asset balance: {:?}", e);
-        }
-    }
+    // Step 4: Replace the split lines if given; they are checked against the new amount on commit
+    updated_transaction.splits = match payload.splits {
+        Some(splits) => replace_transaction_splits(&mut *conn, transaction_id, &splits).await?,
+        None => get_transaction_splits(&mut *conn, &[transaction_id]).await?,
+    };
 
-    if let Some(from_asset_id) = updated_transaction.from_asset_id {
-        let offset = updated_transaction.amount + updated_transaction.fee;
-        if let Err(e) = update_asset_balance(&pool, from_asset_id, -offset).await {
-            eprintln!("Failed to apply new from_asset balance: {:?}", e);
-        }
-    }
+    // Step 5: Apply new balance effects
+    stage_transaction_balance(conn, &updated_transaction, Decimal::ONE, changes).await?;
 
-    (StatusCode::OK, Json(updated_transaction))
+    Ok(updated_transaction)
 }
 
-/// Handler: Delete a transaction and roll back its asset balance changes
-pub async fn delete_transaction_handler(
-    State(pool): State<Arc<PgPool>>,
-    Path(transaction_id): Path<Uuid>,
-) -> impl IntoResponse {
-    // Step 1: Load transaction before deleting
-    let old_transaction = match get_transaction_by_transation_id(&pool, transaction_id).await {
-        Ok(tx) => tx,
-        Err(err) => {
-            eprintln!(
-                "Transaction {} not found, delete skipped: {:?}",
-                transaction_id, err
-            );
-            return StatusCode::NO_CONTENT.into_response();
+/// Delete a transaction, staging the reversal of its balance effects
+async fn remove_transaction(
+    conn: &mut PgConnection,
+    transaction_id: Uuid,
+    changes: &mut BalanceChanges,
+) -> Result<(), sqlx::Error> {
+    let old_transaction = lock_transaction_by_id(&mut *conn, transaction_id).await?;
+    stage_transaction_balance(conn, &old_transaction, Decimal::NEGATIVE_ONE, changes).await?;
+    delete_transaction(&mut *conn, transaction_id).await
+}
+
+/// Restore a recorded image in one database transaction
+async fn restore_transaction_atomically(
+    pool: &PgPool,
+    image: &Transaction,
+) -> Result<Transaction, TransactionError> {
+    let mut db_tx = pool.begin().await?;
+    let restored = restore_transaction(&mut db_tx, image).await?;
+    check_transaction(&mut db_tx, &restored).await?;
+    db_tx.commit().await?;
+    Ok(restored)
+}
+
+/// Go back to the image before the latest change, or delete the transaction when the
+/// latest change created it. Returns the restored transaction, `None` when deleted.
+async fn undo_transaction_atomically(
+    pool: &PgPool,
+    transaction_id: Uuid,
+) -> Result<Option<Transaction>, TransactionError> {
+    let mut db_tx = pool.begin().await?;
+
+    let latest = get_latest_transaction_version(&mut *db_tx, transaction_id).await?;
+    let restored = match latest.before {
+        Some(image) => {
+            let restored = restore_transaction(&mut db_tx, &image).await?;
+            check_transaction(&mut db_tx, &restored).await?;
+            Some(restored)
+        }
+        None => {
+            let created = lock_transaction_by_id(&mut db_tx, transaction_id).await?;
+            revert_transaction_balance(&mut db_tx, &created).await?;
+            delete_transaction(&mut *db_tx, transaction_id).await?;
+            None
         }
     };
 
-    // Step 2: Roll back balance effects
-    if let Some(to_asset_id) = old_transaction.to_asset_id {
-        if let Err(e) = update_asset_balance(&pool, to_asset_id, -old_transaction.amount).await {
-            eprintln!("Failed to revert to_asset balance on delete: {:?}", e);
+    db_tx.commit().await?;
+    Ok(restored)
+}
+
+/// Run every operation of a batch in its own savepoint. Deferred checks (such as split
+/// totals) are forced per item so a failure is pinned on the item that caused it.
+/// In all-or-nothing mode the first failure rolls back the batch; in best-effort mode
+/// only the failing items are left out.
+async fn run_batch_atomically(
+    pool: &PgPool,
+    request: BatchTransactionRequest,
+) -> Result<BatchResult, sqlx::Error> {
+    let mut db_tx = pool.begin().await?;
+    let mut changes = BalanceChanges::default();
+    let mut results = Vec::with_capacity(request.operations.len());
+    let mut failed = false;
+
+    for (index, operation) in request.operations.into_iter().enumerate() {
+        if failed && request.mode == BatchMode::AllOrNothing {
+            results.push(BatchItemResult {
+                index,
+                status: BatchItemStatus::Skipped,
+                code: StatusCode::FAILED_DEPENDENCY.as_u16(),
+                transaction_id: None,
+                message: None,
+            });
+            continue;
         }
-    }
 
-    if let Some(from_asset_id) = old_transaction.from_asset_id {
-        let offset = old_transaction.amount + old_transaction.fee;
-        if let Err(e) = update_asset_balance(&pool, from_asset_id, offset).await {
-            eprintln!("Failed to revert from_asset balance on delete: {:?}", e);
+        let mut item_changes = BalanceChanges::default();
+        let mut savepoint = db_tx.begin().await?;
+        let outcome = match run_batch_operation(&mut savepoint, operation, &mut item_changes).await
+        {
+            Ok(done) => sqlx::query("SET CONSTRAINTS ALL IMMEDIATE")
+                .execute(&mut *savepoint)
+                .await
+                .map(|_| done)
+                .map_err(TransactionError::from),
+            Err(err) => Err(err),
+        };
+
+        match outcome {
+            Ok((code, transaction_id)) => {
+                savepoint.commit().await?;
+                changes.merge(item_changes);
+                results.push(BatchItemResult {
+                    index,
+                    status: BatchItemStatus::Succeeded,
+                    code: code.as_u16(),
+                    transaction_id: Some(transaction_id),
+                    message: None,
+                });
+            }
+            Err(err) => {
+                eprintln!("Batch item {} failed: {:?}", index, err);
+                savepoint.rollback().await?;
+                failed = true;
+                results.push(BatchItemResult {
+                    index,
+                    status: BatchItemStatus::Failed,
+                    code: batch_error_status(&err).as_u16(),
+                    transaction_id: None,
+                    message: match err {
+                        TransactionError::Invalid(err) => Some(err.to_string()),
+                        TransactionError::Database(_) => None,
+                    },
+                });
+            }
         }
+        sqlx::query("SET CONSTRAINTS ALL DEFERRED")
+            .execute(&mut *db_tx)
+            .await?;
     }
 
-    // Step 3: Delete transaction record
-    match delete_transaction(&pool, transaction_id).await {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(err) => {
-            eprintln!("Failed to delete transaction {}: {:?}", transaction_id, err);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    if failed && request.mode == BatchMode::AllOrNothing {
+        for result in results.iter_mut() {
+            if result.status == BatchItemStatus::Succeeded {
+                result.status = BatchItemStatus::RolledBack;
+            }
         }
+        return Ok(BatchResult {
+            committed: false,
+            results,
+        });
     }
+
+    apply_balance_changes(&mut db_tx, &changes).await?;
+    db_tx.commit().await?;
+    Ok(BatchResult {
+        committed: true,
+        results,
+    })
 }
 
-/// Fallback for failed update/insert operations
-fn dummy_transaction() -> Transaction {
-    Transaction::default()
+/// Run one batch operation, returning its single-request status and the transaction ID
+async fn run_batch_operation(
+    conn: &mut PgConnection,
+    operation: BatchOperation,
+    changes: &mut BalanceChanges,
+) -> Result<(StatusCode, Uuid), TransactionError> {
+    match operation {
+        BatchOperation::Create(payload) => {
+            let transaction = create_transaction_with_splits(conn, payload, changes).await?;
+            Ok((StatusCode::CREATED, transaction.id))
+        }
+        BatchOperation::Update {
+            id,
+            changes: payload,
+        } => {
+            update_transaction_with_splits(conn, id, payload, changes).await?;
+            Ok((StatusCode::OK, id))
+        }
+        BatchOperation::Delete { id } => {
+            remove_transaction(conn, id, changes).await?;
+            Ok((StatusCode::NO_CONTENT, id))
+        }
+    }
 }
@@ -1,96 +1,323 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use sqlx::{PgPool, Postgres, QueryBuilder};
+use sqlx::{Executor, PgConnection, PgPool, Postgres, QueryBuilder};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::core::asset::asset_repository::get_asset_type_by_asset_id;
-use crate::models::{EnrichedTransaction, Transaction, TransactionType};
+use crate::models::{
+    BalanceChanges, EnrichedTransaction, EnrichedTransactionPage, NewTransactionSplit, SortOrder,
+    Transaction, TransactionCursor, TransactionFilter, TransactionSplit, TransactionStatus,
+    TransactionType, TransactionVersion,
+};
+use crate::repository::{post_transaction_entry, update_asset_balance, update_asset_balances};
 
-// SQL query to get all transactions where the given account is either sender or receiver
-const QUERY_SELECT_BY_ACCOUNT_ID: &str =
-    "SELECT * FROM transactions WHERE from_account_id = $1 OR to_account_id = $1";
+// Base query for listing an account's transactions with the asset types joined in.
+// Filters, ordering and the page limit are appended by `get_transactions_by_account_id`.
+const QUERY_SELECT_ENRICHED_BY_ACCOUNT_ID: &str = "
+    SELECT t.*, fa.asset_type AS from_asset_type, ta.asset_type AS to_asset_type
+    FROM transactions t
+    LEFT JOIN assets fa ON fa.id = t.from_asset_id
+    LEFT JOIN assets ta ON ta.id = t.to_asset_id
+    WHERE (t.from_account_id = ";
+
+// Sort key of a transaction; rows without a transaction time fall back to their creation time
+const SORT_KEY: &str = "COALESCE(t.transaction_time, t.created_at)";
 
 // SQL query to fetch a single transaction by ID
 const QUERY_SELECT_BY_TRANSACTION_ID: &str = "SELECT * FROM transactions WHERE id = $1";
 
+// SQL query to fetch a single transaction by ID and lock the row until the surrounding
+// database transaction ends
+const QUERY_SELECT_BY_TRANSACTION_ID_FOR_UPDATE: &str =
+    "SELECT * FROM transactions WHERE id = $1 FOR UPDATE";
+
 // SQL insert query for creating a new transaction
 const QUERY_INSERT: &str = "
     INSERT INTO transactions (
         from_asset_id, to_asset_id, transaction_type,
         amount, fee, from_account_id, to_account_id,
-        created_at, updated_at, transaction_time, notes, image
+        created_at, updated_at, transaction_time, notes, image, category_id, exchange_rate,
+        payee_id
     ) VALUES (
         $1, $2, $3, $4, $5, $6, $7,
-        $8, $9, $10, $11, $12
+        $8, $9, $10, $11, $12, $13, $14, $15
     )
     RETURNING *
 ";
 
+// SQL query to set the statement status of a transaction
+const QUERY_UPDATE_STATUS: &str =
+    "UPDATE transactions SET status = $2, updated_at = now() WHERE id = $1 RETURNING *";
+
+// SQL query to fetch the recorded versions of a transaction, oldest first
+const QUERY_SELECT_HISTORY: &str = "
+    SELECT transaction_id, version, operation, before, after, changed_at
+    FROM transaction_history
+    WHERE transaction_id = $1
+    ORDER BY version
+";
+
+// SQL query to fetch one recorded version of a transaction
+const QUERY_SELECT_VERSION: &str = "
+    SELECT transaction_id, version, operation, before, after, changed_at
+    FROM transaction_history
+    WHERE transaction_id = $1 AND version = $2
+";
+
+// SQL query to fetch the latest recorded version of a transaction
+const QUERY_SELECT_LATEST_VERSION: &str = "
+    SELECT transaction_id, version, operation, before, after, changed_at
+    FROM transaction_history
+    WHERE transaction_id = $1
+    ORDER BY version DESC
+    LIMIT 1
+";
+
+// SQL query putting a recorded image of a transaction back, re-creating the row if it
+// was deleted. An existing row keeps its status and creation time.
+const QUERY_RESTORE: &str = "
+    INSERT INTO transactions (
+        id, from_asset_id, to_asset_id, transaction_type,
+        amount, fee, from_account_id, to_account_id,
+        transaction_time, notes, image, category_id, tags,
+        status, created_at, updated_at, exchange_rate, payee_id
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, now(), $16, $17
+    )
+    ON CONFLICT (id) DO UPDATE SET
+        from_asset_id = EXCLUDED.from_asset_id,
+        to_asset_id = EXCLUDED.to_asset_id,
+        transaction_type = EXCLUDED.transaction_type,
+        amount = EXCLUDED.amount,
+        fee = EXCLUDED.fee,
+        from_account_id = EXCLUDED.from_account_id,
+        to_account_id = EXCLUDED.to_account_id,
+        transaction_time = EXCLUDED.transaction_time,
+        notes = EXCLUDED.notes,
+        image = EXCLUDED.image,
+        category_id = EXCLUDED.category_id,
+        tags = EXCLUDED.tags,
+        exchange_rate = EXCLUDED.exchange_rate,
+        payee_id = EXCLUDED.payee_id,
+        updated_at = now()
+    RETURNING *
+";
+
 // SQL query to delete a transaction by ID
 const QUERY_DELETE: &str = "DELETE FROM transactions WHERE id = $1";
 
-/// Get all transactions involving a specific account and enrich them with asset type names.
+// SQL query to fetch the split lines of several transactions, in their submitted order
+const QUERY_SELECT_SPLITS_BY_TRANSACTION_IDS: &str = "
+    SELECT * FROM transaction_splits
+    WHERE transaction_id = ANY($1)
+    ORDER BY transaction_id, position
+";
+
+// SQL query to drop all split lines of a transaction
+const QUERY_DELETE_SPLITS: &str = "DELETE FROM transaction_splits WHERE transaction_id = $1";
+
+/// Get one page of the transactions involving a specific account, enriched with asset
+/// type names, narrowed down and ordered by `filter`.
 pub async fn get_transactions_by_account_id(
     pool: &PgPool,
     account_id: Uuid,
-) -> Result<Vec<EnrichedTransaction>, sqlx::Error> {
-    let transactions = sqlx::query_as::<_, Transaction>(QUERY_SELECT_BY_ACCOUNT_ID)
-        .bind(account_id)
+    filter: &TransactionFilter,
+) -> Result<EnrichedTransactionPage, sqlx::Error> {
+    let mut query = QueryBuilder::<Postgres>::new(QUERY_SELECT_ENRICHED_BY_ACCOUNT_ID);
+    query
+        .push_bind(account_id)
+        .push(" OR t.to_account_id = ")
+        .push_bind(account_id)
+        .push(")");
+
+    if let Some(from) = filter.from {
+        query.push(format!(" AND {SORT_KEY} >= ")).push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(format!(" AND {SORT_KEY} < ")).push_bind(to);
+    }
+    if let Some(transaction_type) = filter.transaction_type {
+        query
+            .push(" AND t.transaction_type = ")
+            .push_bind(transaction_type);
+    }
+    if let Some(category_id) = filter.category_id {
+        // Matches the transaction's own category or any of its split lines
+        query
+            .push(" AND EXISTS (SELECT 1 FROM categories c WHERE (c.id = ")
+            .push_bind(category_id)
+            .push(" OR c.parent_id = ")
+            .push_bind(category_id)
+            .push(") AND (c.id = t.category_id OR EXISTS (SELECT 1 FROM transaction_splits s")
+            .push(" WHERE s.transaction_id = t.id AND s.category_id = c.id)))");
+    }
+    if let Some(asset_id) = filter.asset_id {
+        query
+            .push(" AND (t.from_asset_id = ")
+            .push_bind(asset_id)
+            .push(" OR t.to_asset_id = ")
+            .push_bind(asset_id)
+            .push(")");
+    }
+    if let Some(payee_id) = filter.payee_id {
+        query.push(" AND t.payee_id = ").push_bind(payee_id);
+    }
+    if let Some(min_amount) = filter.min_amount {
+        query.push(" AND t.amount >= ").push_bind(min_amount);
+    }
+    if let Some(max_amount) = filter.max_amount {
+        query.push(" AND t.amount <= ").push_bind(max_amount);
+    }
+    if let Some(q) = filter.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        query
+            .push(" AND to_tsvector('simple', COALESCE(t.notes, '')) @@ plainto_tsquery('simple', ")
+            .push_bind(q.to_string())
+            .push(")");
+    }
+
+    // Keyset pagination: continue strictly after the last row of the previous page
+    let (cmp, direction) = match filter.sort {
+        SortOrder::Asc => (">", "ASC"),
+        SortOrder::Desc => ("<", "DESC"),
+    };
+    if let Some(cursor) = filter.cursor {
+        query
+            .push(format!(" AND ({SORT_KEY}, t.id) {cmp} ("))
+            .push_bind(cursor.time)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    // Fetch one extra row to find out whether another page follows
+    let page_size = filter.page_size();
+    query
+        .push(format!(
+            " ORDER BY {SORT_KEY} {direction}, t.id {direction} LIMIT "
+        ))
+        .push_bind(page_size + 1);
+
+    let mut transactions = query
+        .build_query_as::<EnrichedTransaction>()
         .fetch_all(pool)
         .await?;
 
-    let mut enriched = Vec::with_capacity(transactions.len());
+    let next_cursor = if transactions.len() as i64 > page_size {
+        transactions.truncate(page_size as usize);
+        transactions.last().map(|tx| {
+            TransactionCursor {
+                time: tx.transaction_time.unwrap_or(tx.created_at),
+                id: tx.id,
+            }
+            .to_string()
+        })
+    } else {
+        None
+    };
 
-    for tx in transactions {
-        // Get asset type for from_asset_id (if exists)
-        let from_asset_type = match tx.from_asset_id {
-            Some(asset_id) => Some(get_asset_type_by_asset_id(pool, asset_id).await?),
-            None => None,
-        };
-
-        // Get asset type for to_asset_id (if exists)
-        let to_asset_type = match tx.to_asset_id {
-            Some(asset_id) => Some(get_asset_type_by_asset_id(pool, asset_id).await?),
-            None => None,
-        };
+    // Attach split lines with one query for the whole page
+    let ids: Vec<Uuid> = transactions.iter().map(|tx| tx.id).collect();
+    let mut splits_by_transaction: HashMap<Uuid, Vec<TransactionSplit>> = HashMap::new();
+    for split in get_transaction_splits(pool, &ids).await? {
+        splits_by_transaction
+            .entry(split.transaction_id)
+            .or_default()
+            .push(split);
+    }
+    for tx in transactions.iter_mut() {
+        tx.splits = splits_by_transaction.remove(&tx.id).unwrap_or_default();
+    }
 
-        // Combine original transaction with enriched asset type info
-        enriched.push(EnrichedTransaction {
-            id: tx.id,
-            from_asset_id: tx.from_asset_id,
-            to_asset_id: tx.to_asset_id,
-            transaction_type: tx.transaction_type,
-            amount: tx.amount,
-            fee: tx.fee,
-            from_account_id: tx.from_account_id,
-            to_account_id: tx.to_account_id,
-            created_at: tx.created_at,
-            updated_at: tx.updated_at,
-            transaction_time: tx.transaction_time,
-            notes: tx.notes,
-            image: tx.image,
-            from_asset_type,
-            to_asset_type,
-        });
-    }
-
-    Ok(enriched)
+    Ok(EnrichedTransactionPage {
+        transactions,
+        next_cursor,
+    })
 }
 
-/// Get a transaction by its ID
+/// Get a transaction by its ID, including its split lines
 pub async fn get_transaction_by_transation_id(
     pool: &PgPool,
     transaction_id: Uuid,
 ) -> Result<Transaction, sqlx::Error> {
-    sqlx::query_as::<_, Transaction>(QUERY_SELECT_BY_TRANSACTION_ID)
+    let mut transaction = sqlx::query_as::<_, Transaction>(QUERY_SELECT_BY_TRANSACTION_ID)
         .bind(transaction_id)
         .fetch_one(pool)
+        .await?;
+    transaction.splits = get_transaction_splits(pool, &[transaction_id]).await?;
+
+    Ok(transaction)
+}
+
+/// Get the split lines of the given transactions, grouped by transaction in submitted order
+pub async fn get_transaction_splits<'e, E>(
+    executor: E,
+    transaction_ids: &[Uuid],
+) -> Result<Vec<TransactionSplit>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, TransactionSplit>(QUERY_SELECT_SPLITS_BY_TRANSACTION_IDS)
+        .bind(transaction_ids)
+        .fetch_all(executor)
+        .await
+}
+
+/// Replace all split lines of a transaction; an empty slice un-splits it.
+///
+/// The lines must add up to the transaction amount by the time the surrounding
+/// database transaction commits, otherwise the commit fails with a check violation.
+pub async fn replace_transaction_splits(
+    conn: &mut PgConnection,
+    transaction_id: Uuid,
+    splits: &[NewTransactionSplit],
+) -> Result<Vec<TransactionSplit>, sqlx::Error> {
+    sqlx::query(QUERY_DELETE_SPLITS)
+        .bind(transaction_id)
+        .execute(&mut *conn)
+        .await?;
+
+    if splits.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "INSERT INTO transaction_splits (transaction_id, category_id, amount, notes, position) ",
+    );
+    builder.push_values(splits.iter().enumerate(), |mut row, (position, split)| {
+        row.push_bind(transaction_id)
+            .push_bind(split.category_id)
+            .push_bind(split.amount)
+            .push_bind(split.notes.clone())
+            .push_bind(position as i32);
+    });
+    builder.push(" RETURNING *");
+
+    builder
+        .build_query_as::<TransactionSplit>()
+        .fetch_all(&mut *conn)
+        .await
+}
+
+/// Get a transaction by its ID and lock it against concurrent edits.
+/// Must be called inside a database transaction for the lock to have any effect.
+pub async fn lock_transaction_by_id(
+    conn: &mut PgConnection,
+    transaction_id: Uuid,
+) -> Result<Transaction, sqlx::Error> {
+    sqlx::query_as::<_, Transaction>(QUERY_SELECT_BY_TRANSACTION_ID_FOR_UPDATE)
+        .bind(transaction_id)
+        .fetch_one(conn)
         .await
 }
 
 /// Create a new transaction
-pub async fn create_transaction(
-    pool: &PgPool,
+///
+/// Between assets of different currencies the receiving side gets `amount` converted at
+/// `exchange_rate`, or at the current rate of the `currencies` table when none is given.
+/// Only inserts the row; callers are responsible for applying the balance effects
+/// with `apply_transaction_balance` in the same database transaction.
+pub async fn create_transaction<'e, E>(
+    executor: E,
     from_asset_id: Option<Uuid>,
     to_asset_id: Option<Uuid>,
     transaction_type: TransactionType,
@@ -101,7 +328,13 @@ pub async fn create_transaction(
     transaction_time: Option<DateTime<Utc>>,
     notes: Option<String>,
     image: Option<String>,
-) -> Result<Transaction, sqlx::Error> {
+    category_id: Option<Uuid>,
+    exchange_rate: Option<Decimal>,
+    payee_id: Option<Uuid>,
+) -> Result<Transaction, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     match sqlx::query_as::<_, Transaction>(QUERY_INSERT)
         .bind(from_asset_id)
         .bind(to_asset_id)
@@ -115,7 +348,10 @@ pub async fn create_transaction(
         .bind(transaction_time.unwrap_or(Utc::now())) // default to now if missing
         .bind(notes)
         .bind(image)
-        .fetch_one(pool)
+        .bind(category_id)
+        .bind(exchange_rate)
+        .bind(payee_id)
+        .fetch_one(executor)
         .await
     {
         Ok(transaction) => {
@@ -130,8 +366,8 @@ pub async fn create_transaction(
 }
 
 /// Update one or more fields of a transaction
-pub async fn update_transaction_info(
-    pool: &PgPool,
+pub async fn update_transaction_info<'e, E>(
+    executor: E,
     transaction_id: Uuid,
     from_asset_id: Option<Uuid>,
     to_asset_id: Option<Uuid>,
@@ -143,7 +379,13 @@ pub async fn update_transaction_info(
     transaction_time: Option<DateTime<Utc>>,
     notes: Option<String>,
     image: Option<String>,
-) -> Result<Transaction, sqlx::Error> {
+    category_id: Option<Uuid>,
+    exchange_rate: Option<Decimal>,
+    payee_id: Option<Uuid>,
+) -> Result<Transaction, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     // If no fields are being updated, return an error
     if from_asset_id.is_none()
         && to_asset_id.is_none()
@@ -155,6 +397,9 @@ pub async fn update_transaction_info(
         && transaction_time.is_none()
         && notes.is_none()
         && image.is_none()
+        && category_id.is_none()
+        && exchange_rate.is_none()
+        && payee_id.is_none()
     {
         return Err(sqlx::Error::RowNotFound);
     }
@@ -210,6 +455,21 @@ pub async fn update_transaction_info(
     if let Some(image) = image {
         builder.push("image = ").push_bind(image).push(", ");
     }
+    if let Some(category_id) = category_id {
+        builder
+            .push("category_id = ")
+            .push_bind(category_id)
+            .push(", ");
+    }
+    if let Some(exchange_rate) = exchange_rate {
+        builder
+            .push("exchange_rate = ")
+            .push_bind(exchange_rate)
+            .push(", ");
+    }
+    if let Some(payee_id) = payee_id {
+        builder.push("payee_id = ").push_bind(payee_id).push(", ");
+    }
 
     // Always update the timestamp
     builder.push("updated_at = ").push_bind(Utc::now());
@@ -218,24 +478,190 @@ pub async fn update_transaction_info(
 
     // Execute and return the updated transaction
     let query = builder.build_query_as::<Transaction>();
-    let transaction = query.fetch_one(pool).await?;
+    let transaction = query.fetch_one(executor).await?;
 
     Ok(transaction)
 }
 
+/// Mark a transaction as pending or cleared against the bank's statement
+pub async fn update_transaction_status<'e, E>(
+    executor: E,
+    transaction_id: Uuid,
+    status: TransactionStatus,
+) -> Result<Transaction, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, Transaction>(QUERY_UPDATE_STATUS)
+        .bind(transaction_id)
+        .bind(status)
+        .fetch_one(executor)
+        .await
+}
+
 /// Delete a transaction by its ID
-pub async fn delete_transaction(pool: &PgPool, transaction_id: Uuid) -> Result<(), sqlx::Error> {
+pub async fn delete_transaction<'e, E>(executor: E, transaction_id: Uuid) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     sqlx::query(QUERY_DELETE)
         .bind(transaction_id)
-        .execute(pool)
+        .execute(executor)
         .await?;
 
     Ok(())
 }
 
+/// Get every recorded change of a transaction, including after it was deleted
+pub async fn get_transaction_history<'e, E>(
+    executor: E,
+    transaction_id: Uuid,
+) -> Result<Vec<TransactionVersion>, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, TransactionVersion>(QUERY_SELECT_HISTORY)
+        .bind(transaction_id)
+        .fetch_all(executor)
+        .await
+}
+
+/// Get one recorded version of a transaction
+pub async fn get_transaction_version<'e, E>(
+    executor: E,
+    transaction_id: Uuid,
+    version: i32,
+) -> Result<TransactionVersion, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, TransactionVersion>(QUERY_SELECT_VERSION)
+        .bind(transaction_id)
+        .bind(version)
+        .fetch_one(executor)
+        .await
+}
+
+/// Get the most recent change of a transaction
+pub async fn get_latest_transaction_version<'e, E>(
+    executor: E,
+    transaction_id: Uuid,
+) -> Result<TransactionVersion, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, TransactionVersion>(QUERY_SELECT_LATEST_VERSION)
+        .bind(transaction_id)
+        .fetch_one(executor)
+        .await
+}
+
+/// Put a recorded image of a transaction back, moving the asset balances with it:
+/// the current row's effects are reverted and the image's applied. A deleted
+/// transaction is re-created under its old ID, without its split lines.
+pub async fn restore_transaction(
+    conn: &mut PgConnection,
+    image: &Transaction,
+) -> Result<Transaction, sqlx::Error> {
+    match lock_transaction_by_id(&mut *conn, image.id).await {
+        Ok(current) => revert_transaction_balance(&mut *conn, &current).await?,
+        Err(sqlx::Error::RowNotFound) => {}
+        Err(err) => return Err(err),
+    }
+
+    // A re-created row is no longer part of a reconciliation
+    let status = match image.status {
+        TransactionStatus::Reconciled => TransactionStatus::Cleared,
+        status => status,
+    };
+
+    let mut restored = sqlx::query_as::<_, Transaction>(QUERY_RESTORE)
+        .bind(image.id)
+        .bind(image.from_asset_id)
+        .bind(image.to_asset_id)
+        .bind(image.transaction_type as i32)
+        .bind(image.amount)
+        .bind(image.fee)
+        .bind(image.from_account_id)
+        .bind(image.to_account_id)
+        .bind(image.transaction_time)
+        .bind(&image.notes)
+        .bind(&image.image)
+        .bind(image.category_id)
+        .bind(&image.tags)
+        .bind(status)
+        .bind(image.created_at)
+        .bind(image.exchange_rate)
+        .bind(image.payee_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    restored.splits = get_transaction_splits(&mut *conn, &[restored.id]).await?;
+
+    apply_transaction_balance(conn, &restored).await?;
+    Ok(restored)
+}
+
+/// Apply a transaction's effect on its assets:
+/// credits `to_asset` with the received amount and debits `from_asset` with the amount plus fee,
+/// and journals the matching postings
+pub async fn apply_transaction_balance(
+    conn: &mut PgConnection,
+    transaction: &Transaction,
+) -> Result<(), sqlx::Error> {
+    adjust_transaction_balance(conn, transaction, Decimal::ONE).await
+}
+
+/// Undo the effect previously applied by `apply_transaction_balance`,
+/// journaling a reversing entry
+pub async fn revert_transaction_balance(
+    conn: &mut PgConnection,
+    transaction: &Transaction,
+) -> Result<(), sqlx::Error> {
+    adjust_transaction_balance(conn, transaction, Decimal::NEGATIVE_ONE).await
+}
+
+/// Shift both asset balances of a transaction; `direction` is `1` to apply and `-1` to revert
+async fn adjust_transaction_balance(
+    conn: &mut PgConnection,
+    transaction: &Transaction,
+    direction: Decimal,
+) -> Result<(), sqlx::Error> {
+    for (asset_id, amount) in transaction.balance_effects() {
+        update_asset_balance(&mut *conn, asset_id, amount * direction).await?;
+    }
+
+    post_transaction_entry(conn, transaction, direction).await
+}
+
+/// Journal a transaction's effect now but only collect its balance changes,
+/// for writing many transactions' changes at once with `apply_balance_changes`.
+/// `direction` is `1` to apply and `-1` to revert.
+pub async fn stage_transaction_balance(
+    conn: &mut PgConnection,
+    transaction: &Transaction,
+    direction: Decimal,
+    changes: &mut BalanceChanges,
+) -> Result<(), sqlx::Error> {
+    changes.add(transaction, direction);
+    post_transaction_entry(conn, transaction, direction).await
+}
+
+/// Write staged balance changes with one update per asset
+pub async fn apply_balance_changes(
+    conn: &mut PgConnection,
+    changes: &BalanceChanges,
+) -> Result<(), sqlx::Error> {
+    let changes = changes.changes();
+    if changes.is_empty() {
+        return Ok(());
+    }
+    update_asset_balances(conn, &changes).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::transaction::transaction::HistoryOperation;
     use chrono::Utc;
     use rust_decimal::Decimal;
     use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
@@ -329,6 +755,9 @@ mod tests {
             Some(Utc::now()),
             Some("Test transfer".to_string()),
             None,
+            None,
+            None,
+            None,
         )
         .await
         .expect("Transaction creation failed");
@@ -351,6 +780,9 @@ mod tests {
             Some(Utc::now()),
             Some("updated note".to_string()),
             Some("image.jpg".to_string()),
+            None,
+            None,
+            None,
         )
         .await
         .expect("Update failed");
@@ -365,9 +797,11 @@ mod tests {
         assert_eq!(fetched.id, tx.id);
 
         // Get by account
-        let enriched = get_transactions_by_account_id(&pool, from_account_id)
-            .await
-            .expect("Get by account failed");
+        let enriched =
+            get_transactions_by_account_id(&pool, from_account_id, &TransactionFilter::default())
+                .await
+                .expect("Get by account failed")
+                .transactions;
         assert!(enriched.iter().any(|etx| etx.id == tx.id));
         assert_eq!(enriched[0].from_asset_type.as_deref(), Some("cash"));
         assert_eq!(enriched[0].to_asset_type.as_deref(), Some("bank"));
@@ -379,4 +813,540 @@ mod tests {
         let result = get_transaction_by_transation_id(&pool, tx.id).await;
         assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
     }
+
+    async fn asset_balance(pool: &PgPool, asset_id: Uuid) -> Decimal {
+        sqlx::query_scalar("SELECT balance FROM assets WHERE id = $1")
+            .bind(asset_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transaction_balance_is_atomic() {
+        let pool = setup_test_db().await;
+
+        let account_id = insert_user_and_account(&pool).await;
+        let from_asset_id = insert_asset(&pool, account_id, "cash").await;
+        let to_asset_id = insert_asset(&pool, account_id, "bank").await;
+
+        // Rolled back: neither the row nor the balances survive
+        let mut db_tx = pool.begin().await.unwrap();
+        let tx = create_transaction(
+            &mut *db_tx,
+            Some(from_asset_id),
+            Some(to_asset_id),
+            TransactionType::InternalTransfer,
+            Decimal::new(1000, 2),
+            Some(Decimal::new(100, 2)),
+            Some(account_id),
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        db_tx.rollback().await.unwrap();
+
+        assert_eq!(
+            asset_balance(&pool, from_asset_id).await,
+            Decimal::new(5000, 2)
+        );
+        assert_eq!(
+            asset_balance(&pool, to_asset_id).await,
+            Decimal::new(5000, 2)
+        );
+        let result = get_transaction_by_transation_id(&pool, tx.id).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+        // Committed: amount + fee leaves `from_asset`, amount arrives at `to_asset`
+        let mut db_tx = pool.begin().await.unwrap();
+        let tx = create_transaction(
+            &mut *db_tx,
+            Some(from_asset_id),
+            Some(to_asset_id),
+            TransactionType::InternalTransfer,
+            Decimal::new(1000, 2),
+            Some(Decimal::new(100, 2)),
+            Some(account_id),
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        db_tx.commit().await.unwrap();
+
+        assert_eq!(
+            asset_balance(&pool, from_asset_id).await,
+            Decimal::new(3900, 2)
+        );
+        assert_eq!(
+            asset_balance(&pool, to_asset_id).await,
+            Decimal::new(6000, 2)
+        );
+
+        // Reverting restores the original balances
+        let mut conn = pool.acquire().await.unwrap();
+        revert_transaction_balance(&mut conn, &tx).await.unwrap();
+
+        assert_eq!(
+            asset_balance(&pool, from_asset_id).await,
+            Decimal::new(5000, 2)
+        );
+        assert_eq!(
+            asset_balance(&pool, to_asset_id).await,
+            Decimal::new(5000, 2)
+        );
+
+        // A missing asset fails the whole operation
+        let mut orphan = tx;
+        orphan.to_asset_id = Some(Uuid::new_v4());
+        let result = apply_transaction_balance(&mut conn, &orphan).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_paginate_transactions() {
+        let pool = setup_test_db().await;
+
+        let account_id = insert_user_and_account(&pool).await;
+        let cash_id = insert_asset(&pool, account_id, "cash").await;
+        let bank_id = insert_asset(&pool, account_id, "bank").await;
+
+        // Five expenses from cash on consecutive days, plus one income into the bank
+        let start = Utc::now() - chrono::Duration::days(30);
+        for day in 0..5 {
+            create_transaction(
+                &pool,
+                Some(cash_id),
+                None,
+                TransactionType::Expense,
+                Decimal::new(1000 * (day + 1), 2),
+                None,
+                Some(account_id),
+                None,
+                Some(start + chrono::Duration::days(day)),
+                Some(format!("lunch day {}", day)),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        create_transaction(
+            &pool,
+            None,
+            Some(bank_id),
+            TransactionType::Income,
+            Decimal::new(100000, 2),
+            None,
+            None,
+            Some(account_id),
+            Some(start + chrono::Duration::days(2)),
+            Some("salary".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Walk every page of the expenses, oldest first
+        let mut filter = TransactionFilter {
+            transaction_type: Some(TransactionType::Expense),
+            sort: SortOrder::Asc,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut amounts = Vec::new();
+        loop {
+            let page = get_transactions_by_account_id(&pool, account_id, &filter)
+                .await
+                .unwrap();
+            assert!(page.transactions.len() <= 2);
+            amounts.extend(page.transactions.iter().map(|tx| tx.amount));
+            match page.next_cursor {
+                Some(cursor) => filter.cursor = Some(cursor.parse().unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(
+            amounts,
+            (1..=5)
+                .map(|n| Decimal::new(1000 * n, 2))
+                .collect::<Vec<_>>()
+        );
+
+        // Date range, amount range and asset filters combine
+        let filter = TransactionFilter {
+            from: Some(start + chrono::Duration::days(1)),
+            to: Some(start + chrono::Duration::days(4)),
+            min_amount: Some(Decimal::new(2500, 2)),
+            max_amount: Some(Decimal::new(3500, 2)),
+            asset_id: Some(cash_id),
+            ..Default::default()
+        };
+        let page = get_transactions_by_account_id(&pool, account_id, &filter)
+            .await
+            .unwrap();
+        let amounts: Vec<_> = page.transactions.iter().map(|tx| tx.amount).collect();
+        assert_eq!(amounts, vec![Decimal::new(3000, 2)]);
+        assert!(page.next_cursor.is_none());
+
+        // Full-text search on notes, with asset types joined in
+        let filter = TransactionFilter {
+            q: Some("salary".to_string()),
+            ..Default::default()
+        };
+        let page = get_transactions_by_account_id(&pool, account_id, &filter)
+            .await
+            .unwrap();
+        assert_eq!(page.transactions.len(), 1);
+        assert_eq!(page.transactions[0].from_asset_type, None);
+        assert_eq!(page.transactions[0].to_asset_type.as_deref(), Some("bank"));
+    }
+
+    #[tokio::test]
+    async fn test_split_transaction_lines() {
+        let pool = setup_test_db().await;
+
+        let account_id = insert_user_and_account(&pool).await;
+        let cash_id = insert_asset(&pool, account_id, "cash").await;
+        let food = crate::repository::create_category(&pool, account_id, None, "Food")
+            .await
+            .unwrap();
+        let home = crate::repository::create_category(&pool, account_id, None, "Home")
+            .await
+            .unwrap();
+
+        // One supermarket receipt split over two categories
+        let mut db_tx = pool.begin().await.unwrap();
+        let tx = create_transaction(
+            &mut *db_tx,
+            Some(cash_id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(10000, 2),
+            None,
+            Some(account_id),
+            None,
+            None,
+            Some("supermarket".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let lines = [
+            NewTransactionSplit {
+                category_id: Some(food.id),
+                amount: Decimal::new(6000, 2),
+                notes: Some("groceries".to_string()),
+            },
+            NewTransactionSplit {
+                category_id: Some(home.id),
+                amount: Decimal::new(4000, 2),
+                notes: None,
+            },
+        ];
+        replace_transaction_splits(&mut db_tx, tx.id, &lines)
+            .await
+            .unwrap();
+        db_tx.commit().await.unwrap();
+
+        let fetched = get_transaction_by_transation_id(&pool, tx.id)
+            .await
+            .unwrap();
+        assert_eq!(fetched.splits.len(), 2);
+        assert_eq!(fetched.splits[0].notes.as_deref(), Some("groceries"));
+
+        // Lines that don't add up are rejected on commit
+        let mut db_tx = pool.begin().await.unwrap();
+        replace_transaction_splits(&mut db_tx, tx.id, &lines[..1])
+            .await
+            .unwrap();
+        let result = db_tx.commit().await;
+        assert!(matches!(result, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+
+        // So is changing the amount without touching the lines
+        let mut db_tx = pool.begin().await.unwrap();
+        update_transaction_info(
+            &mut *db_tx,
+            tx.id,
+            None,
+            None,
+            None,
+            Some(Decimal::new(9000, 2)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let result = db_tx.commit().await;
+        assert!(matches!(result, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+
+        // Listing and the category filter see the lines
+        let filter = TransactionFilter {
+            category_id: Some(home.id),
+            ..Default::default()
+        };
+        let page = get_transactions_by_account_id(&pool, account_id, &filter)
+            .await
+            .unwrap();
+        assert_eq!(page.transactions.len(), 1);
+        assert_eq!(page.transactions[0].splits.len(), 2);
+
+        // Per-category totals count each line under its own category
+        let totals = crate::repository::get_category_totals(
+            &pool,
+            account_id,
+            Utc::now() - chrono::Duration::days(1),
+            Utc::now() + chrono::Duration::days(1),
+        )
+        .await
+        .unwrap();
+        let expense_of = |id: Uuid| {
+            totals
+                .iter()
+                .find(|total| total.category_id == Some(id))
+                .map(|total| total.expense)
+        };
+        assert_eq!(expense_of(food.id), Some(Decimal::new(6000, 2)));
+        assert_eq!(expense_of(home.id), Some(Decimal::new(4000, 2)));
+    }
+
+    #[tokio::test]
+    async fn test_history_and_restore() {
+        let pool = setup_test_db().await;
+
+        let account_id = insert_user_and_account(&pool).await;
+        let cash_id = insert_asset(&pool, account_id, "cash").await;
+
+        let mut db_tx = pool.begin().await.unwrap();
+        let tx = create_transaction(
+            &mut *db_tx,
+            Some(cash_id),
+            None,
+            TransactionType::Expense,
+            Decimal::new(1000, 2),
+            None,
+            Some(account_id),
+            None,
+            None,
+            Some("Lunch".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        db_tx.commit().await.unwrap();
+
+        // A mistaken edit: 10.00 became 15.00
+        let mut db_tx = pool.begin().await.unwrap();
+        revert_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        let edited = update_transaction_info(
+            &mut *db_tx,
+            tx.id,
+            None,
+            None,
+            None,
+            Some(Decimal::new(1500, 2)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        apply_transaction_balance(&mut db_tx, &edited)
+            .await
+            .unwrap();
+        db_tx.commit().await.unwrap();
+        assert_eq!(asset_balance(&pool, cash_id).await, Decimal::new(3500, 2));
+
+        let history = get_transaction_history(&pool, tx.id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].operation, HistoryOperation::Create);
+        assert!(history[0].before.is_none());
+        assert_eq!(history[1].operation, HistoryOperation::Update);
+        let before = &history[1].before.as_ref().unwrap().0;
+        assert_eq!(before.amount, Decimal::new(1000, 2));
+        assert_eq!(before.transaction_type, TransactionType::Expense);
+        assert_eq!(before.notes.as_deref(), Some("Lunch"));
+        assert_eq!(
+            history[1].after.as_ref().unwrap().0.amount,
+            Decimal::new(1500, 2)
+        );
+
+        // Restoring version 1 puts the amount and the balance back
+        let version = get_transaction_version(&pool, tx.id, 1).await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        let restored = restore_transaction(&mut conn, &version.after.unwrap().0)
+            .await
+            .unwrap();
+        assert_eq!(restored.amount, Decimal::new(1000, 2));
+        assert_eq!(asset_balance(&pool, cash_id).await, Decimal::new(4000, 2));
+
+        // A deleted transaction keeps its history and can be brought back
+        revert_transaction_balance(&mut conn, &restored)
+            .await
+            .unwrap();
+        delete_transaction(&mut *conn, tx.id).await.unwrap();
+        assert_eq!(asset_balance(&pool, cash_id).await, Decimal::new(5000, 2));
+
+        let deletion = get_latest_transaction_version(&pool, tx.id).await.unwrap();
+        assert_eq!(deletion.version, 4);
+        assert_eq!(deletion.operation, HistoryOperation::Delete);
+        assert!(deletion.after.is_none());
+
+        let recreated = restore_transaction(&mut conn, &deletion.before.unwrap().0)
+            .await
+            .unwrap();
+        assert_eq!(recreated.id, tx.id);
+        assert_eq!(recreated.created_at, tx.created_at);
+        assert_eq!(asset_balance(&pool, cash_id).await, Decimal::new(4000, 2));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_across_currencies() {
+        let pool = setup_test_db().await;
+
+        // XTS is reserved for testing, so the currency scheduler never touches its rate
+        sqlx::query(
+            "INSERT INTO currencies (code, name, rate) VALUES ('XTS', 'Test currency', '32.0')
+             ON CONFLICT (code) DO UPDATE SET rate = EXCLUDED.rate",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let account_id = insert_user_and_account(&pool).await;
+        let cash_id = insert_asset(&pool, account_id, "cash").await;
+        let foreign_id = insert_asset(&pool, account_id, "currency").await;
+        sqlx::query("UPDATE assets SET currency_code = 'XTS' WHERE id = $1")
+            .bind(foreign_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // 10.00 TWD at the scheduler's rate of 32 TWD per XTS arrive as 0.31 XTS
+        let mut db_tx = pool.begin().await.unwrap();
+        let tx = create_transaction(
+            &mut *db_tx,
+            Some(cash_id),
+            Some(foreign_id),
+            TransactionType::InternalTransfer,
+            Decimal::new(1000, 2),
+            None,
+            Some(account_id),
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(tx.currency_code, "TWD");
+        assert_eq!(tx.to_currency_code, "XTS");
+        assert_eq!(tx.exchange_rate, Some(Decimal::new(3125, 5)));
+        assert_eq!(tx.to_amount, Some(Decimal::new(31, 2)));
+        apply_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        db_tx.commit().await.unwrap();
+        assert_eq!(asset_balance(&pool, cash_id).await, Decimal::new(4000, 2));
+        assert_eq!(
+            asset_balance(&pool, foreign_id).await,
+            Decimal::new(5031, 2)
+        );
+
+        // An explicit rate overrides the stored one
+        let mut db_tx = pool.begin().await.unwrap();
+        revert_transaction_balance(&mut db_tx, &tx).await.unwrap();
+        let edited = update_transaction_info(
+            &mut *db_tx,
+            tx.id,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(Decimal::new(5, 2)),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(edited.to_amount, Some(Decimal::new(50, 2)));
+        apply_transaction_balance(&mut db_tx, &edited)
+            .await
+            .unwrap();
+        db_tx.commit().await.unwrap();
+        assert_eq!(
+            asset_balance(&pool, foreign_id).await,
+            Decimal::new(5050, 2)
+        );
+
+        // Without any known rate the transfer is refused
+        sqlx::query("UPDATE assets SET currency_code = 'XTX' WHERE id = $1")
+            .bind(foreign_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let result = create_transaction(
+            &pool,
+            Some(cash_id),
+            Some(foreign_id),
+            TransactionType::InternalTransfer,
+            Decimal::new(1000, 2),
+            None,
+            Some(account_id),
+            Some(account_id),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(sqlx::Error::Database(ref e)) if e.is_check_violation()));
+    }
 }
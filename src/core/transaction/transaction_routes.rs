@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, patch, post},
     Router,
 };
 use axum_login::login_required;
@@ -14,6 +14,9 @@ pub fn transaction_routes(state: Arc<PgPool>) -> Router {
         // POST /transactions
         // -> Create a new transaction and apply balance changes
         .route("/transactions", post(add_transaction_handler))
+        // POST /transactions/batch
+        // -> Create, update and delete many transactions (`"mode": "all_or_nothing" | "best_effort"`)
+        .route("/transactions/batch", post(batch_transactions_handler))
         // GET    /transactions/{id} -> Retrieve a transaction by ID
         // PATCH  /transactions/{id} -> Update a transaction and re-calculate balances
         // DELETE /transactions/{id} -> Delete a transaction and roll back balances
@@ -23,12 +26,38 @@ pub fn transaction_routes(state: Arc<PgPool>) -> Router {
                 .patch(update_transaction_handler)
                 .delete(delete_transaction_handler),
         )
+        // PATCH /transactions/{id}/status
+        // -> Mark a transaction as Pending or Cleared against the bank statement
+        .route(
+            "/transactions/{id}/status",
+            patch(update_transaction_status_handler),
+        )
+        // GET /transactions/{id}/history
+        // -> Every recorded change with the transaction before and after it
+        .route(
+            "/transactions/{id}/history",
+            get(get_transaction_history_handler),
+        )
+        // POST /transactions/{id}/history/{version}/restore
+        // -> Put the transaction back as it was after that version, balances included
+        .route(
+            "/transactions/{id}/history/{version}/restore",
+            post(restore_transaction_version_handler),
+        )
+        // POST /transactions/{id}/undo -> Undo the latest change, balances included
+        .route("/transactions/{id}/undo", post(undo_transaction_handler))
         // GET /transactions/account/{id}
         // -> Retrieve all enriched transactions for a specific account
         .route(
             "/transactions/account/{id}",
             get(get_transaction_by_account_id_handler),
         )
+        // GET /transactions/account/{id}/export?format=csv|json|qif
+        // -> Download every transaction matching the listing filters
+        .route(
+            "/transactions/account/{id}/export",
+            get(export_transactions_handler),
+        )
         // Optional: Enable this line to restrict transaction routes to authenticated users
         .route_layer(login_required!(Backend, login_url = "/login"))
         // Share the database connection pool with all route handlers
@@ -0,0 +1,409 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgConnection;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::models::{Asset, Transaction, TransactionType};
+use crate::repository::{get_asset_by_id, get_payee_by_id};
+
+/// A rule of the transaction's type that its assets, accounts or amounts break
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionValidationError {
+    AmountNotPositive,
+    NegativeFee,
+    /// A fee is paid by the source, so there must be one
+    FeeWithoutSource,
+    /// `from_asset_id` and `from_account_id` are required for the type
+    MissingSource(TransactionType),
+    /// `to_asset_id` and `to_account_id` are required for the type
+    MissingDestination(TransactionType),
+    /// An income has nothing to take the funds from
+    UnexpectedSource(TransactionType),
+    /// An expense has nothing to move the funds to
+    UnexpectedDestination(TransactionType),
+    SameAsset,
+    /// A transfer between two accounts names the same account twice
+    SameAccount,
+    /// An internal transfer leaves its account
+    DifferentAccounts,
+    /// The asset isn't held by the account stated for its side
+    AssetNotInAccount {
+        asset_id: Uuid,
+        account_id: Uuid,
+    },
+    /// The payee belongs to an account the transaction doesn't touch
+    PayeeNotInAccount(Uuid),
+}
+
+impl fmt::Display for TransactionValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AmountNotPositive => write!(f, "amount must be greater than zero"),
+            Self::NegativeFee => write!(f, "fee can't be negative"),
+            Self::FeeWithoutSource => write!(f, "a fee needs a from_asset_id to be paid from"),
+            Self::MissingSource(kind) => {
+                write!(f, "{kind:?} needs from_asset_id and from_account_id")
+            }
+            Self::MissingDestination(kind) => {
+                write!(f, "{kind:?} needs to_asset_id and to_account_id")
+            }
+            Self::UnexpectedSource(kind) => {
+                write!(f, "{kind:?} can't have from_asset_id or from_account_id")
+            }
+            Self::UnexpectedDestination(kind) => {
+                write!(f, "{kind:?} can't have to_asset_id or to_account_id")
+            }
+            Self::SameAsset => write!(f, "from_asset_id and to_asset_id must differ"),
+            Self::SameAccount => write!(
+                f,
+                "Transfer moves funds between two accounts; use InternalTransfer within one"
+            ),
+            Self::DifferentAccounts => write!(
+                f,
+                "InternalTransfer stays within one account; use Transfer between accounts"
+            ),
+            Self::AssetNotInAccount {
+                asset_id,
+                account_id,
+            } => write!(f, "asset {asset_id} doesn't belong to account {account_id}"),
+            Self::PayeeNotInAccount(payee_id) => {
+                write!(f, "payee {payee_id} belongs to another account")
+            }
+        }
+    }
+}
+
+/// Rejections are answered with 422 and the broken rule
+impl IntoResponse for TransactionValidationError {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "status": "fail",
+                "message": self.to_string(),
+                "code": 422
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Why writing a transaction failed: refused by validation, or by the database
+#[derive(Debug)]
+pub enum TransactionError {
+    Invalid(TransactionValidationError),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(err) => write!(f, "{err}"),
+            Self::Database(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<sqlx::Error> for TransactionError {
+    fn from(err: sqlx::Error) -> Self {
+        TransactionError::Database(err)
+    }
+}
+
+impl From<TransactionValidationError> for TransactionError {
+    fn from(err: TransactionValidationError) -> Self {
+        TransactionError::Invalid(err)
+    }
+}
+
+/// Check a transaction against the rules of its type:
+/// - `Income` only has a destination, `Expense` only a source
+/// - `Transfer` moves funds between assets of two different accounts
+/// - `InternalTransfer` moves funds between two assets of the same account
+///
+/// Each side names both an asset and an account, and the asset must belong to that account.
+/// `from_asset` and `to_asset` are the transaction's assets as stored.
+pub fn validate_transaction(
+    transaction: &Transaction,
+    from_asset: Option<&Asset>,
+    to_asset: Option<&Asset>,
+) -> Result<(), TransactionValidationError> {
+    use TransactionValidationError::*;
+
+    let kind = transaction.transaction_type;
+    if transaction.amount <= Decimal::ZERO {
+        return Err(AmountNotPositive);
+    }
+    if transaction.fee < Decimal::ZERO {
+        return Err(NegativeFee);
+    }
+
+    let has_source = transaction.from_asset_id.is_some() || transaction.from_account_id.is_some();
+    let has_destination = transaction.to_asset_id.is_some() || transaction.to_account_id.is_some();
+    let source = transaction.from_asset_id.zip(transaction.from_account_id);
+    let destination = transaction.to_asset_id.zip(transaction.to_account_id);
+
+    match kind {
+        TransactionType::Income => {
+            if has_source {
+                return Err(UnexpectedSource(kind));
+            }
+            if destination.is_none() {
+                return Err(MissingDestination(kind));
+            }
+        }
+        TransactionType::Expense => {
+            if source.is_none() {
+                return Err(MissingSource(kind));
+            }
+            if has_destination {
+                return Err(UnexpectedDestination(kind));
+            }
+        }
+        TransactionType::Transfer | TransactionType::InternalTransfer => {
+            let ((from_asset_id, from_account_id), (to_asset_id, to_account_id)) =
+                match (source, destination) {
+                    (None, _) => return Err(MissingSource(kind)),
+                    (_, None) => return Err(MissingDestination(kind)),
+                    (Some(source), Some(destination)) => (source, destination),
+                };
+            if from_asset_id == to_asset_id {
+                return Err(SameAsset);
+            }
+            if kind == TransactionType::Transfer && from_account_id == to_account_id {
+                return Err(SameAccount);
+            }
+            if kind == TransactionType::InternalTransfer && from_account_id != to_account_id {
+                return Err(DifferentAccounts);
+            }
+        }
+    }
+
+    if !transaction.fee.is_zero() && source.is_none() {
+        return Err(FeeWithoutSource);
+    }
+
+    for (asset, account_id) in [
+        (from_asset, transaction.from_account_id),
+        (to_asset, transaction.to_account_id),
+    ] {
+        if let (Some(asset), Some(account_id)) = (asset, account_id) {
+            if asset.account_id != account_id {
+                return Err(AssetNotInAccount {
+                    asset_id: asset.id,
+                    account_id,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Load a written transaction's assets and validate it, and check its payee belongs to
+/// one of its accounts. Returning an error before the surrounding database transaction
+/// commits discards the write.
+pub async fn check_transaction(
+    conn: &mut PgConnection,
+    transaction: &Transaction,
+) -> Result<(), TransactionError> {
+    let from_asset = match transaction.from_asset_id {
+        Some(asset_id) => Some(get_asset_by_id(&mut *conn, asset_id).await?),
+        None => None,
+    };
+    let to_asset = match transaction.to_asset_id {
+        Some(asset_id) => Some(get_asset_by_id(&mut *conn, asset_id).await?),
+        None => None,
+    };
+
+    validate_transaction(transaction, from_asset.as_ref(), to_asset.as_ref())?;
+
+    if let Some(payee_id) = transaction.payee_id {
+        let payee = get_payee_by_id(&mut *conn, payee_id).await?;
+        if transaction.from_account_id != Some(payee.account_id)
+            && transaction.to_account_id != Some(payee.account_id)
+        {
+            return Err(TransactionValidationError::PayeeNotInAccount(payee_id).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AssetKind, LiabilityTerms};
+    use chrono::Utc;
+
+    fn asset(account_id: Uuid) -> Asset {
+        Asset {
+            id: Uuid::new_v4(),
+            account_id,
+            asset_type: "bank".to_string(),
+            kind: AssetKind::Asset,
+            balance: Decimal::ZERO,
+            balance_owed: None,
+            terms: LiabilityTerms::default(),
+            currency_code: "TWD".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn transaction(
+        transaction_type: TransactionType,
+        from: Option<&Asset>,
+        to: Option<&Asset>,
+    ) -> Transaction {
+        Transaction {
+            transaction_type,
+            amount: Decimal::new(100, 0),
+            from_asset_id: from.map(|asset| asset.id),
+            from_account_id: from.map(|asset| asset.account_id),
+            to_asset_id: to.map(|asset| asset.id),
+            to_account_id: to.map(|asset| asset.account_id),
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_rules_per_transaction_type() {
+        let account_a = Uuid::new_v4();
+        let cash = asset(account_a);
+        let bank = asset(account_a);
+        let other = asset(Uuid::new_v4());
+
+        let check = |tx: &Transaction| {
+            let from = [&cash, &bank, &other]
+                .into_iter()
+                .find(|asset| Some(asset.id) == tx.from_asset_id);
+            let to = [&cash, &bank, &other]
+                .into_iter()
+                .find(|asset| Some(asset.id) == tx.to_asset_id);
+            validate_transaction(tx, from, to)
+        };
+
+        // Income: destination only
+        assert_eq!(
+            check(&transaction(TransactionType::Income, None, Some(&cash))),
+            Ok(())
+        );
+        assert_eq!(
+            check(&transaction(TransactionType::Income, None, None)),
+            Err(TransactionValidationError::MissingDestination(
+                TransactionType::Income
+            ))
+        );
+        assert_eq!(
+            check(&transaction(
+                TransactionType::Income,
+                Some(&bank),
+                Some(&cash)
+            )),
+            Err(TransactionValidationError::UnexpectedSource(
+                TransactionType::Income
+            ))
+        );
+        let mut with_fee = transaction(TransactionType::Income, None, Some(&cash));
+        with_fee.fee = Decimal::ONE;
+        assert_eq!(
+            check(&with_fee),
+            Err(TransactionValidationError::FeeWithoutSource)
+        );
+
+        // Expense: source only
+        assert_eq!(
+            check(&transaction(TransactionType::Expense, Some(&cash), None)),
+            Ok(())
+        );
+        assert_eq!(
+            check(&transaction(
+                TransactionType::Expense,
+                Some(&cash),
+                Some(&bank)
+            )),
+            Err(TransactionValidationError::UnexpectedDestination(
+                TransactionType::Expense
+            ))
+        );
+
+        // InternalTransfer: two assets of one account
+        assert_eq!(
+            check(&transaction(
+                TransactionType::InternalTransfer,
+                Some(&cash),
+                Some(&bank)
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(&transaction(
+                TransactionType::InternalTransfer,
+                Some(&cash),
+                Some(&other)
+            )),
+            Err(TransactionValidationError::DifferentAccounts)
+        );
+        assert_eq!(
+            check(&transaction(
+                TransactionType::InternalTransfer,
+                Some(&cash),
+                Some(&cash)
+            )),
+            Err(TransactionValidationError::SameAsset)
+        );
+
+        // Transfer: two accounts
+        assert_eq!(
+            check(&transaction(
+                TransactionType::Transfer,
+                Some(&cash),
+                Some(&other)
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(&transaction(
+                TransactionType::Transfer,
+                Some(&cash),
+                Some(&bank)
+            )),
+            Err(TransactionValidationError::SameAccount)
+        );
+        assert_eq!(
+            check(&transaction(TransactionType::Transfer, None, Some(&other))),
+            Err(TransactionValidationError::MissingSource(
+                TransactionType::Transfer
+            ))
+        );
+
+        // Amounts
+        let mut zero = transaction(TransactionType::Expense, Some(&cash), None);
+        zero.amount = Decimal::ZERO;
+        assert_eq!(
+            check(&zero),
+            Err(TransactionValidationError::AmountNotPositive)
+        );
+    }
+
+    #[test]
+    fn test_assets_belong_to_stated_accounts() {
+        let cash = asset(Uuid::new_v4());
+        let stranger = Uuid::new_v4();
+
+        let mut tx = transaction(TransactionType::Expense, Some(&cash), None);
+        tx.from_account_id = Some(stranger);
+        assert_eq!(
+            validate_transaction(&tx, Some(&cash), None),
+            Err(TransactionValidationError::AssetNotInAccount {
+                asset_id: cash.id,
+                account_id: stranger,
+            })
+        );
+    }
+}
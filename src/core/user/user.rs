@@ -13,8 +13,8 @@ use tower_sessions::session::Session;
 use uuid::Uuid;
 
 use crate::repository::{
-    create_account, create_user, delete_user, get_user_by_email, get_user_by_id,
-    get_user_by_username,
+    create_account, create_default_categories, create_user, delete_user, get_user_by_email,
+    get_user_by_id, get_user_by_username,
 };
 
 /// Represents a user in the system, mapping to the `users` table
@@ -128,6 +128,13 @@ impl Backend {
         }
     }
 
+    /// Seed the default category tree for the new user's account
+    pub async fn create_default_categories_(&self, user: &User) -> Result<(), sqlx::Error> {
+        let mut db_tx = self.db.begin().await?;
+        create_default_categories(&mut db_tx, user.id).await?;
+        db_tx.commit().await
+    }
+
     /// Delete a user by their ID
     pub async fn delete_user(&self, user_id: &Uuid) -> Result<(), sqlx::Error> {
         delete_user(&self.db, *user_id).await
@@ -35,13 +35,24 @@ use scheduler::start_all_schedulers;
 use crate::core::account::account_routes::account_routes;
 use crate::core::account::login_logout_routes::login_routes;
 use crate::core::asset::asset_routes::asset_routes;
+use crate::core::attachment::attachment_routes::attachment_routes;
+use crate::core::attachment::attachment_storage::attachment_storage_from_env;
+use crate::core::category::category_routes::category_routes;
 use crate::core::country::country_routes::country_routes;
 use crate::core::currency::currency_holding_routes::currency_routes;
+use crate::core::forecast::forecast_routes::forecast_routes;
+use crate::core::import::import_routes::import_routes;
+use crate::core::journal::journal_routes::journal_routes;
+use crate::core::net_worth::net_worth_routes::net_worth_routes;
+use crate::core::payee::payee_routes::payee_routes;
+use crate::core::reconciliation::reconciliation_routes::reconciliation_routes;
 use crate::core::recurring_transaction::recurring_transaction_routes::recurringtransaction_routes;
+use crate::core::rule::rule_routes::rule_routes;
 use crate::core::stock::stock_routes::stock_routes;
 use crate::core::transaction::transaction_routes::transaction_routes;
 use crate::core::user::user_routes::user_routes;
 use crate::db::pool;
+use crate::repository::{backfill_balance_snapshots, snapshot_balances};
 
 /// Struct for holding environment-provided service URLs
 struct Url {
@@ -76,8 +87,18 @@ async fn main() {
     // Initialize Postgres connection and run migrations
     let state: Arc<sqlx::Pool<sqlx::Postgres>> = Arc::new(pool::init_db(&urls.database_url).await);
 
+    // `vito backfill-balance-snapshots [account_id]` rebuilds the balance history of assets
+    // from their transactions, then exits
+    if std::env::args().nth(1).as_deref() == Some("backfill-balance-snapshots") {
+        backfill_balance_history(&state).await;
+        return;
+    }
+
+    // Storage backend for transaction attachments (local directory unless configured otherwise)
+    let attachment_storage = attachment_storage_from_env();
+
     // Start all scheduled background jobs (e.g., stock metadata updates)
-    start_all_schedulers(state.clone()).await;
+    start_all_schedulers(state.clone(), attachment_storage.clone()).await;
 
     // Initialize backend logic for axum-login (e.g., user/password auth)
     let backend = Backend::new(&urls.database_url)
@@ -121,10 +142,19 @@ async fn main() {
         .merge(asset_routes(state.clone()))
         .merge(recurringtransaction_routes(state.clone()))
         .merge(transaction_routes(state.clone()))
+        .merge(category_routes(state.clone()))
+        .merge(rule_routes(state.clone()))
+        .merge(payee_routes(state.clone()))
+        .merge(reconciliation_routes(state.clone()))
+        .merge(attachment_routes(state.clone(), attachment_storage.clone()))
         .merge(stock_routes(state.clone()))
         .merge(country_routes(state.clone()))
         .merge(login_routes(backend.clone()))
         .merge(currency_routes(state.clone()))
+        .merge(journal_routes(state.clone()))
+        .merge(import_routes(state.clone()))
+        .merge(forecast_routes(state.clone()))
+        .merge(net_worth_routes(state.clone()))
         .layer(middleware::from_fn(log_all))
         .layer(CookieManagerLayer::new()) // Enable cookie support
         .layer(auth_layer) // Enable login session middleware
@@ -139,3 +169,24 @@ async fn main() {
     let listener = TcpListener::bind(addr).await.unwrap();
     serve(listener, routes_all).await.unwrap();
 }
+
+/// Rebuilds past balance snapshots from transactions and takes today's, for the account
+/// given as second argument or for all of them
+async fn backfill_balance_history(pool: &sqlx::PgPool) {
+    let account_id = std::env::args()
+        .nth(2)
+        .map(|id| id.parse::<uuid::Uuid>().expect("Account ID must be a UUID"));
+    let today = chrono::Utc::now().date_naive();
+
+    match backfill_balance_snapshots(pool, account_id, today).await {
+        Ok(written) => println!("Rebuilt {} balance snapshots.", written),
+        Err(e) => {
+            eprintln!("Balance snapshot backfill failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Err(e) = snapshot_balances(pool, today).await {
+        eprintln!("Balance snapshot failed: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -3,6 +3,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+/// Currency every rate in the `currencies` table is quoted against, and the default
+/// currency of assets and transactions
+pub const BASE_CURRENCY: &str = "TWD";
+
 /// Represents a single currency record stored in the database.
 /// Each record contains a unique ID, a standardized currency code (e.g. USD, EUR),
 /// the full name of the currency, and an optional exchange rate value.
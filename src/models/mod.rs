@@ -1,16 +1,57 @@
 pub mod currency;
+pub mod nullable;
 
-pub use crate::core::asset::asset::{Asset, AssetList};
+pub use crate::core::asset::asset::{
+    Asset, AssetKind, AssetList, LiabilityTerms, LiabilityTermsUpdate,
+};
+pub use crate::core::attachment::attachment::{
+    Attachment, AttachmentList, AttachmentResponse, MAX_ATTACHMENT_SIZE,
+};
+pub use crate::core::category::category::{
+    Category, CategoryTotal, CategoryTotalList, CategoryTree, DEFAULT_CATEGORIES,
+};
 pub use crate::core::country::country::{Country, CountryList};
+pub use crate::core::forecast::forecast::{Forecast, ForecastEvent, ForecastSource};
+pub use crate::core::import::import::{
+    CsvImportProfile, CsvImportProfileList, ImportPreview, ImportResult, ImportRow,
+    ImportRowStatus, NewCsvImportProfile, ParsedStatement, SignConvention, StatementBalance,
+    StatementBalanceCheck, StatementLine,
+};
+pub use crate::core::journal::journal::{
+    AssetBalanceCheck, AssetBalanceCheckList, JournalEntry, LedgerKind, LedgerLine, LedgerLineList,
+    NewPosting, TrialBalance, TrialBalanceRow,
+};
+pub use crate::core::net_worth::net_worth::{
+    DailyHoldingValue, Granularity, HoldingValuation, NetWorth, NetWorthHistory,
+};
+pub use crate::core::payee::payee::{
+    resolve_payee, Payee, PayeeList, PayeeSummary, PayeeSummaryList,
+};
+pub use crate::core::reconciliation::reconciliation::{
+    is_reconciled_lock, Reconciliation, ReconciliationList, ReconciliationStatus,
+    ReconciliationSummary,
+};
 pub use crate::core::recurring_transaction::recurring_transaction::{
-    IntervalChoices, RecurringTransaction, RecurringTransactionType,
+    IntervalChoices, MissedRunPolicy, NewRecurringTransaction, RecurringTransaction,
+    RecurringTransactionRun, RecurringTransactionRuns, RecurringTransactionType,
+    RecurringTransactionUpdate, RunOutcome,
+};
+pub use crate::core::recurring_transaction::subscription::{
+    SubscriptionSuggestion, SubscriptionSuggestionList,
+};
+pub use crate::core::rule::rule::{
+    NewTransactionRule, ReapplyRulesRequest, RuleApplication, TransactionRule, TransactionRuleList,
 };
 pub use crate::core::stock::stock::{
     StockHolding, StockHoldingList, StockHoldingResponse, StockInfo, StockMetadata,
     StockMetadataList,
 };
 pub use crate::core::transaction::transaction::{
-    EnrichedTransaction, EnrichedTransactionList, Transaction, TransactionType,
+    BalanceChanges, BatchItemResult, BatchItemStatus, BatchMode, BatchResult, EnrichedTransaction,
+    EnrichedTransactionPage, ExportFormat, NewTransactionSplit, SortOrder, Transaction,
+    TransactionCursor, TransactionFilter, TransactionSplit, TransactionStatus, TransactionType,
+    TransactionVersion, TransactionVersionList,
 };
 pub use crate::core::user::user::{Backend, Credentials, User};
-pub use currency::Currency;
+pub use currency::{Currency, BASE_CURRENCY};
+pub use nullable::double_option;
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes a field that may be left out or set to null, for updates that can clear
+/// a value. Use with `#[serde(default, deserialize_with = "double_option")]`: a missing
+/// field is `None` (unchanged), `null` is `Some(None)` (cleared) and a value `Some(Some(_))`.
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[derive(Deserialize)]
+    struct Update {
+        #[serde(default, deserialize_with = "double_option")]
+        parent_id: Option<Option<Uuid>>,
+    }
+
+    #[test]
+    fn test_double_option_tells_missing_from_null() {
+        let missing: Update = serde_json::from_str("{}").unwrap();
+        assert_eq!(missing.parent_id, None);
+
+        let cleared: Update = serde_json::from_str(r#"{"parent_id": null}"#).unwrap();
+        assert_eq!(cleared.parent_id, Some(None));
+
+        let id = Uuid::new_v4();
+        let set: Update = serde_json::from_str(&format!(r#"{{"parent_id": "{id}"}}"#)).unwrap();
+        assert_eq!(set.parent_id, Some(Some(id)));
+    }
+}
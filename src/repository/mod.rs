@@ -4,13 +4,48 @@ pub use crate::core::account::account_repository::{
     create_account, delete_account, get_account_by_id, get_accounts, update_account_info,
 };
 pub use crate::core::asset::asset_repository::{
-    create_asset, delete_asset, get_asset_by_user_id, get_assets, update_asset_balance,
-    update_asset_info,
+    create_asset, create_liability, delete_asset, get_asset_by_id, get_asset_by_user_id,
+    get_assets, lock_asset_by_id, update_asset_balance, update_asset_balances, update_asset_info,
+    update_liability_terms,
+};
+pub use crate::core::attachment::attachment_repository::{
+    create_attachment, delete_attachment, get_attachment_by_id, get_attachments_by_transaction_id,
+    purge_deleted_attachments,
+};
+pub use crate::core::category::category_repository::{
+    create_category, create_default_categories, delete_category, get_categories_by_account_id,
+    get_category_by_id, get_category_totals, update_category_info,
 };
 pub use crate::core::country::country_repository::{fetch_all_countries, upsert_country};
+pub use crate::core::forecast::forecast_repository::get_account_forecast;
+pub use crate::core::import::import_repository::{
+    commit_statement_lines, create_csv_import_profile, delete_csv_import_profile,
+    get_csv_import_profile_by_id, get_csv_import_profiles_by_account_id, preview_statement_lines,
+};
+pub use crate::core::journal::journal_repository::{
+    get_asset_balance_checks, get_general_ledger, get_trial_balance, post_asset_adjustment,
+    post_transaction_entry,
+};
+pub use crate::core::net_worth::net_worth_repository::{
+    backfill_balance_snapshots, get_net_worth, get_net_worth_history, snapshot_balances,
+};
+pub use crate::core::payee::payee_repository::{
+    create_payee, delete_payee, find_payee_by_alias, get_payee_by_id, get_payee_summaries,
+    get_payees_by_account_id, update_payee_info,
+};
+pub use crate::core::reconciliation::reconciliation_repository::{
+    complete_reconciliation, create_reconciliation, delete_open_reconciliation,
+    get_reconciliation_by_id, get_reconciliation_summary, get_reconciliations_by_asset_id,
+    lock_reconciliation_by_id,
+};
 pub use crate::core::recurring_transaction::recurring_transaction_repository::{
-    create_recurring_transaction, delete_recurring_transaction, get_recurring_transaction_by_id,
-    get_recurring_transactions, update_recurring_transaction_info,
+    create_recurring_transaction, delete_recurring_transaction, execute_due_recurring_transactions,
+    get_recurring_transaction_by_id, get_recurring_transaction_runs, get_recurring_transactions,
+    get_subscription_suggestions, preview_recurring_transaction, update_recurring_transaction_info,
+};
+pub use crate::core::rule::rule_repository::{
+    apply_rules_to_transaction, create_rule, delete_rule, get_rule_by_id, get_rules_by_account_id,
+    reapply_rules, update_rule,
 };
 pub use crate::core::stock::stock_repository::{
     create_or_insert_stock_info, create_or_update_stock_metadata, create_stock_holding,
@@ -19,8 +54,12 @@ pub use crate::core::stock::stock_repository::{
     update_stock_metadata,
 };
 pub use crate::core::transaction::transaction_repository::{
-    create_transaction, delete_transaction, get_transaction_by_transation_id,
-    get_transactions_by_account_id, update_transaction_info,
+    apply_balance_changes, apply_transaction_balance, create_transaction, delete_transaction,
+    get_latest_transaction_version, get_transaction_by_transation_id, get_transaction_history,
+    get_transaction_splits, get_transaction_version, get_transactions_by_account_id,
+    lock_transaction_by_id, replace_transaction_splits, restore_transaction,
+    revert_transaction_balance, stage_transaction_balance, update_transaction_info,
+    update_transaction_status,
 };
 pub use crate::core::user::user_repository::{
     create_user, delete_user, get_user_by_email, get_user_by_id, get_user_by_username, get_users,
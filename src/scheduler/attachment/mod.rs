@@ -0,0 +1,42 @@
+use chrono::Utc;
+use cron::Schedule;
+use sqlx::PgPool;
+use std::{str::FromStr, sync::Arc, time::Duration};
+use tokio::time::sleep;
+
+use crate::core::attachment::attachment_storage::AttachmentStorage;
+use crate::repository::purge_deleted_attachments;
+
+/// Launches a background task that removes the stored files of deleted attachments,
+/// such as those of deleted transactions
+///
+/// - The task runs **once immediately** at application startup
+/// - Then it repeats **every hour** using a cron expression
+pub async fn purge_deleted_attachments_every_hour(
+    pool: &PgPool,
+    storage: Arc<dyn AttachmentStorage>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    run_attachment_purge_job(pool, storage.as_ref()).await;
+
+    // Format: sec min hour day-of-month month day-of-week year
+    let expression = "0 0 * * * * *";
+    let schedule = Schedule::from_str(expression)?;
+
+    loop {
+        if let Some(next) = schedule.upcoming(Utc).next() {
+            let duration_secs = (next - Utc::now()).num_seconds().max(0) as u64;
+            sleep(Duration::from_secs(duration_secs)).await;
+
+            run_attachment_purge_job(pool, storage.as_ref()).await;
+        }
+    }
+}
+
+/// Purges queued attachment objects, logging instead of failing so the loop keeps going
+async fn run_attachment_purge_job(pool: &PgPool, storage: &dyn AttachmentStorage) {
+    match purge_deleted_attachments(pool, storage).await {
+        Ok(0) => {}
+        Ok(removed) => println!("Removed {} deleted attachment objects.", removed),
+        Err(e) => eprintln!("Attachment purge failed: {}", e),
+    }
+}
@@ -1,3 +1,4 @@
+pub mod attachment;
 pub mod bond;
 pub mod commodity;
 pub mod cryptocurrency;
@@ -5,7 +6,9 @@ pub mod currency;
 pub mod forex;
 pub mod interest_rate;
 pub mod metals;
+pub mod net_worth;
 pub mod real_estate;
+pub mod recurring_transaction;
 pub mod scheduler_launcher;
 pub mod stock;
 
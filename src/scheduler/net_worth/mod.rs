@@ -0,0 +1,37 @@
+use chrono::Utc;
+use cron::Schedule;
+use sqlx::PgPool;
+use std::{str::FromStr, time::Duration};
+use tokio::time::sleep;
+
+use crate::repository::snapshot_balances;
+
+/// Launches a background task that snapshots the balance and value of every holding
+///
+/// - The task runs **once immediately** at application startup, so today has a snapshot
+/// - Then it repeats **daily at 23:55 (UTC)**, replacing the day's snapshot with its
+///   closing balances
+pub async fn snapshot_balances_every_day(pool: &PgPool) -> Result<(), Box<dyn std::error::Error>> {
+    run_balance_snapshot_job(pool).await;
+
+    // Format: sec min hour day-of-month month day-of-week year
+    let expression = "0 55 23 * * * *";
+    let schedule = Schedule::from_str(expression)?;
+
+    loop {
+        if let Some(next) = schedule.upcoming(Utc).next() {
+            let duration_secs = (next - Utc::now()).num_seconds().max(0) as u64;
+            sleep(Duration::from_secs(duration_secs)).await;
+
+            run_balance_snapshot_job(pool).await;
+        }
+    }
+}
+
+/// Snapshots today's balances, logging instead of failing so the loop keeps going
+async fn run_balance_snapshot_job(pool: &PgPool) {
+    match snapshot_balances(pool, Utc::now().date_naive()).await {
+        Ok(written) => println!("Wrote {} balance snapshots.", written),
+        Err(e) => eprintln!("Balance snapshot failed: {}", e),
+    }
+}
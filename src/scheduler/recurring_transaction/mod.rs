@@ -0,0 +1,40 @@
+use chrono::Utc;
+use cron::Schedule;
+use sqlx::PgPool;
+use std::{str::FromStr, time::Duration};
+use tokio::time::sleep;
+
+use crate::repository::execute_due_recurring_transactions;
+
+/// Launches a background task that posts the transactions of due recurring transactions
+///
+/// - The task runs **once immediately** at application startup, catching up on runs
+///   missed while the server was down
+/// - Then it repeats **every minute** using a cron expression
+pub async fn execute_recurring_transactions_every_minute(
+    pool: &PgPool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    run_recurring_transaction_job(pool).await;
+
+    // Format: sec min hour day-of-month month day-of-week year
+    let expression = "0 * * * * * *";
+    let schedule = Schedule::from_str(expression)?;
+
+    loop {
+        if let Some(next) = schedule.upcoming(Utc).next() {
+            let duration_secs = (next - Utc::now()).num_seconds().max(0) as u64;
+            sleep(Duration::from_secs(duration_secs)).await;
+
+            run_recurring_transaction_job(pool).await;
+        }
+    }
+}
+
+/// Executes due recurring transactions, logging instead of failing so the loop keeps going
+async fn run_recurring_transaction_job(pool: &PgPool) {
+    match execute_due_recurring_transactions(pool, Utc::now()).await {
+        Ok(0) => {}
+        Ok(posted) => println!("Posted {} recurring transactions.", posted),
+        Err(e) => eprintln!("Recurring transaction execution failed: {}", e),
+    }
+}
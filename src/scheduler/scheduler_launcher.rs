@@ -1,19 +1,30 @@
+use super::attachment::purge_deleted_attachments_every_hour;
 use super::currency::update_currency_info_every_day;
+use super::net_worth::snapshot_balances_every_day;
+use super::recurring_transaction::execute_recurring_transactions_every_minute;
 use super::stock::tasks::{
     update_country_info_every_month, update_stock_info_every_day, update_stock_metadata_every_month,
 };
 
 use std::sync::Arc;
 
+use crate::core::attachment::attachment_storage::AttachmentStorage;
+
 /// Launches all scheduled background jobs as asynchronous tasks.
 ///
 /// This includes:
 /// - Daily stock info updates (e.g., prices, volume)
 /// - Monthly stock metadata refresh (e.g., symbol and company name)
 /// - Monthly country info update (e.g., name, timezone, region)
+/// - Hourly removal of the stored files of deleted attachments
+/// - Posting of due recurring transactions every minute
+/// - Daily snapshots of the balance and value of every holding
 ///
 /// Each task runs independently on its own tokio task.
-pub async fn start_all_schedulers(state: Arc<sqlx::Pool<sqlx::Postgres>>) {
+pub async fn start_all_schedulers(
+    state: Arc<sqlx::Pool<sqlx::Postgres>>,
+    attachment_storage: Arc<dyn AttachmentStorage>,
+) {
     // Start daily stock info updater
     let cloned_pool1 = state.clone();
     tokio::spawn(async move {
@@ -45,4 +56,30 @@ pub async fn start_all_schedulers(state: Arc<sqlx::Pool<sqlx::Postgres>>) {
             eprintln!("update_currency_info_every_day failed: {}", e); // <- fixed message
         }
     });
+
+    // Start hourly attachment file purge
+    let cloned_pool5 = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) =
+            purge_deleted_attachments_every_hour(&cloned_pool5, attachment_storage).await
+        {
+            eprintln!("purge_deleted_attachments_every_hour failed: {}", e);
+        }
+    });
+
+    // Start recurring transaction executor
+    let cloned_pool6 = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = execute_recurring_transactions_every_minute(&cloned_pool6).await {
+            eprintln!("execute_recurring_transactions_every_minute failed: {}", e);
+        }
+    });
+
+    // Start daily balance snapshots
+    let cloned_pool7 = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = snapshot_balances_every_day(&cloned_pool7).await {
+            eprintln!("snapshot_balances_every_day failed: {}", e);
+        }
+    });
 }